@@ -0,0 +1,4 @@
+pub mod clip;
+pub mod controller;
+pub mod skeleton;
+pub mod skin;
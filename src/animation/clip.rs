@@ -0,0 +1,219 @@
+use glam::{Quat, Vec3};
+
+use super::skeleton::Pose;
+
+/// How values between two keyframes are found.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// Hold each keyframe until the next one.
+    Step,
+}
+
+/// The values of one joint property at each keyframe.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Keyframes {
+    Translation(Vec<Vec3>),
+    Rotation(Vec<Quat>),
+    Scale(Vec<Vec3>),
+}
+
+impl Keyframes {
+    fn len(&self) -> usize {
+        match self {
+            Keyframes::Translation(values) | Keyframes::Scale(values) => values.len(),
+            Keyframes::Rotation(values) => values.len(),
+        }
+    }
+}
+
+/// One animated property of one joint.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    joint: usize,
+    /// Seconds from the start of the clip, ascending.
+    times: Vec<f32>,
+    keyframes: Keyframes,
+    interpolation: Interpolation,
+}
+
+impl Channel {
+    pub fn new(
+        joint: usize,
+        times: Vec<f32>,
+        keyframes: Keyframes,
+        interpolation: Interpolation,
+    ) -> Result<Self, String> {
+        if times.is_empty() || times.len() != keyframes.len() {
+            return Err(format!(
+                "Channel of joint {joint} has {} key times for {} keyframes",
+                times.len(),
+                keyframes.len()
+            ));
+        }
+        if times.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(format!("Key times of joint {joint} aren't ascending"));
+        }
+        Ok(Self {
+            joint,
+            times,
+            keyframes,
+            interpolation,
+        })
+    }
+
+    pub fn joint(&self) -> usize {
+        self.joint
+    }
+
+    pub fn duration(&self) -> f32 {
+        *self.times.last().unwrap()
+    }
+
+    /// Set the animated property of the channel's joint in `pose` to its value at `time`.
+    /// Before the first and after the last keyframe the value holds.
+    pub fn sample(&self, time: f32, pose: &mut Pose) {
+        let Some(local) = pose.locals.get_mut(self.joint) else {
+            return;
+        };
+        let next = self.times.partition_point(|key_time| *key_time <= time);
+        let (from, to, factor) = if next == 0 {
+            (0, 0, 0.0)
+        } else if next == self.times.len() {
+            (next - 1, next - 1, 0.0)
+        } else {
+            let (start, end) = (self.times[next - 1], self.times[next]);
+            let factor = match self.interpolation {
+                Interpolation::Linear => (time - start) / (end - start),
+                Interpolation::Step => 0.0,
+            };
+            (next - 1, next, factor)
+        };
+
+        match &self.keyframes {
+            Keyframes::Translation(values) => {
+                local.translation = values[from].lerp(values[to], factor)
+            }
+            Keyframes::Rotation(values) => local.rotation = values[from].slerp(values[to], factor),
+            Keyframes::Scale(values) => local.scale = values[from].lerp(values[to], factor),
+        }
+    }
+}
+
+/// A named animation, a set of channels played together.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clip {
+    pub name: String,
+    channels: Vec<Channel>,
+    duration: f32,
+}
+
+impl Clip {
+    /// The clip lasts until the last keyframe of any channel.
+    pub fn new(name: &str, channels: Vec<Channel>) -> Self {
+        let duration = channels.iter().map(Channel::duration).fold(0.0, f32::max);
+        Self {
+            name: name.to_string(),
+            channels,
+            duration,
+        }
+    }
+
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Apply the clip at `time` seconds to `pose`. Joints without channels keep their
+    /// transforms, so start from the rest pose for a clip on its own.
+    pub fn sample(&self, time: f32, pose: &mut Pose) {
+        for channel in &self.channels {
+            channel.sample(time, pose);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scene::Transform;
+
+    fn pose() -> Pose {
+        Pose {
+            locals: vec![Transform::IDENTITY],
+        }
+    }
+
+    #[test]
+    fn test_linear_and_step() {
+        let times = vec![1.0, 2.0, 4.0];
+        let values = vec![Vec3::ZERO, Vec3::X, Vec3::new(3.0, 0.0, 0.0)];
+        let linear = Channel::new(
+            0,
+            times.clone(),
+            Keyframes::Translation(values.clone()),
+            Interpolation::Linear,
+        )
+        .unwrap();
+        let step = Channel::new(
+            0,
+            times,
+            Keyframes::Translation(values),
+            Interpolation::Step,
+        )
+        .unwrap();
+
+        let translation_at = |channel: &Channel, time| {
+            let mut pose = pose();
+            channel.sample(time, &mut pose);
+            pose.locals[0].translation.x
+        };
+        assert_eq!(translation_at(&linear, 0.0), 0.0);
+        assert_eq!(translation_at(&linear, 1.5), 0.5);
+        assert_eq!(translation_at(&linear, 3.0), 2.0);
+        assert_eq!(translation_at(&linear, 9.0), 3.0);
+        assert_eq!(translation_at(&step, 1.5), 0.0);
+        assert_eq!(translation_at(&step, 3.9), 1.0);
+        assert_eq!(translation_at(&step, 4.0), 3.0);
+    }
+
+    #[test]
+    fn test_clip() {
+        let turn = Channel::new(
+            0,
+            vec![0.0, 1.0],
+            Keyframes::Rotation(vec![Quat::IDENTITY, Quat::from_rotation_y(1.0)]),
+            Interpolation::Linear,
+        )
+        .unwrap();
+        let grow = Channel::new(
+            0,
+            vec![0.0, 2.0],
+            Keyframes::Scale(vec![Vec3::ONE, Vec3::splat(3.0)]),
+            Interpolation::Linear,
+        )
+        .unwrap();
+        let clip = Clip::new("turn", vec![turn, grow]);
+        assert_eq!(clip.duration(), 2.0);
+
+        let mut pose = pose();
+        clip.sample(0.5, &mut pose);
+        let local = pose.locals[0];
+        assert!((local.rotation.angle_between(Quat::IDENTITY) - 0.5).abs() < 1e-5);
+        assert_eq!(local.scale, Vec3::splat(1.5));
+        assert_eq!(local.translation, Vec3::ZERO);
+
+        assert!(Channel::new(0, vec![], Keyframes::Scale(vec![]), Interpolation::Step).is_err());
+        assert!(Channel::new(
+            0,
+            vec![1.0, 0.0],
+            Keyframes::Scale(vec![Vec3::ONE; 2]),
+            Interpolation::Step
+        )
+        .is_err());
+    }
+}
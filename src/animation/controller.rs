@@ -0,0 +1,185 @@
+use super::{
+    clip::Clip,
+    skeleton::{Pose, Skeleton},
+};
+
+/// A clip being played and how far along it is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Playback {
+    pub clip: usize,
+    /// Seconds into the clip.
+    pub time: f32,
+    pub speed: f32,
+    pub looping: bool,
+}
+
+impl Playback {
+    fn advance(&mut self, delta_time: f32, duration: f32) {
+        self.time += delta_time * self.speed;
+        if self.looping && duration > 0.0 {
+            self.time = self.time.rem_euclid(duration);
+        } else {
+            self.time = self.time.clamp(0.0, duration);
+        }
+    }
+}
+
+/// A clip cross-fading out while the current one fades in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fade {
+    from: Playback,
+    elapsed: f32,
+    duration: f32,
+}
+
+/// Plays the clips of one skeleton, cross-fading when switching between them.
+///
+/// Time only moves on in `advance`, so the controller follows whatever clock drives it, and
+/// `pose` can be evaluated without a GL context.
+#[derive(Clone, Debug)]
+pub struct AnimationController {
+    clips: Vec<Clip>,
+    current: Option<Playback>,
+    fade: Option<Fade>,
+}
+
+impl AnimationController {
+    pub fn new(clips: Vec<Clip>) -> Self {
+        Self {
+            clips,
+            current: None,
+            fade: None,
+        }
+    }
+
+    pub fn clips(&self) -> &[Clip] {
+        &self.clips
+    }
+
+    pub fn clip_index(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|clip| clip.name == name)
+    }
+
+    pub fn current(&self) -> Option<&Playback> {
+        self.current.as_ref()
+    }
+
+    /// Start `clip` from the beginning, looping, and fade over from the playing clip in
+    /// `fade_duration` seconds. 0 switches at once.
+    pub fn play(&mut self, clip: usize, fade_duration: f32) {
+        assert!(clip < self.clips.len(), "No animation clip {clip}");
+        let playback = Playback {
+            clip,
+            time: 0.0,
+            speed: 1.0,
+            looping: true,
+        };
+        self.fade = self
+            .current
+            .filter(|_| fade_duration > 0.0)
+            .map(|from| Fade {
+                from,
+                elapsed: 0.0,
+                duration: fade_duration,
+            });
+        self.current = Some(playback);
+    }
+
+    /// Change how the current clip plays, e.g. its speed or whether it loops.
+    pub fn current_mut(&mut self) -> Option<&mut Playback> {
+        self.current.as_mut()
+    }
+
+    /// Whether the current clip stopped at its end, which only clips that don't loop do.
+    pub fn is_finished(&self) -> bool {
+        self.current.is_none_or(|playback| {
+            !playback.looping && playback.time >= self.clips[playback.clip].duration()
+        })
+    }
+
+    /// Move the clips on by `delta_time` seconds of the simulation clock.
+    pub fn advance(&mut self, delta_time: f32) {
+        if let Some(playback) = &mut self.current {
+            playback.advance(delta_time, self.clips[playback.clip].duration());
+        }
+        if let Some(fade) = &mut self.fade {
+            fade.from
+                .advance(delta_time, self.clips[fade.from.clip].duration());
+            fade.elapsed += delta_time;
+            if fade.elapsed >= fade.duration {
+                self.fade = None;
+            }
+        }
+    }
+
+    /// The pose of `skeleton` the clips give now. The rest pose before any clip plays.
+    pub fn pose(&self, skeleton: &Skeleton) -> Pose {
+        let sample = |playback: &Playback| {
+            let mut pose = skeleton.rest_pose();
+            self.clips[playback.clip].sample(playback.time, &mut pose);
+            pose
+        };
+        let Some(current) = &self.current else {
+            return skeleton.rest_pose();
+        };
+        match &self.fade {
+            Some(fade) => sample(&fade.from).blend(&sample(current), fade.elapsed / fade.duration),
+            None => sample(current),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::{
+        animation::clip::{Channel, Interpolation, Keyframes},
+        scene::Transform,
+    };
+
+    fn slide(name: &str, to: Vec3) -> Clip {
+        let channel = Channel::new(
+            0,
+            vec![0.0, 2.0],
+            Keyframes::Translation(vec![Vec3::ZERO, to]),
+            Interpolation::Linear,
+        )
+        .unwrap();
+        Clip::new(name, vec![channel])
+    }
+
+    #[test]
+    fn test_playback_and_cross_fade() {
+        let mut skeleton = Skeleton::new();
+        skeleton.add_joint("root", None, Transform::IDENTITY);
+        let mut controller = AnimationController::new(vec![
+            slide("right", Vec3::new(2.0, 0.0, 0.0)),
+            slide("up", Vec3::new(0.0, 2.0, 0.0)),
+        ]);
+        let translation =
+            |controller: &AnimationController| controller.pose(&skeleton).locals[0].translation;
+        assert_eq!(translation(&controller), Vec3::ZERO);
+
+        controller.play(0, 0.0);
+        controller.advance(1.5);
+        assert_eq!(translation(&controller), Vec3::new(1.5, 0.0, 0.0));
+        // Loops back around.
+        controller.advance(1.0);
+        assert_eq!(translation(&controller), Vec3::new(0.5, 0.0, 0.0));
+
+        controller.play(controller.clip_index("up").unwrap(), 1.0);
+        controller.advance(0.5);
+        // Halfway between 1.0 along x and 0.5 along y.
+        assert!(translation(&controller).abs_diff_eq(Vec3::new(0.5, 0.25, 0.0), 1e-5));
+        controller.advance(0.5);
+        assert_eq!(translation(&controller), Vec3::new(0.0, 1.0, 0.0));
+
+        controller.current_mut().unwrap().looping = false;
+        assert!(!controller.is_finished());
+        controller.advance(5.0);
+        assert!(controller.is_finished());
+        assert_eq!(translation(&controller), Vec3::new(0.0, 2.0, 0.0));
+    }
+}
@@ -0,0 +1,162 @@
+use glam::Mat4;
+
+use crate::scene::Transform;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Joint {
+    pub name: String,
+    pub parent: Option<usize>,
+    /// Local transform of the joint in the bind pose.
+    pub rest: Transform,
+    /// Takes a vertex of the skinned mesh into the joint's space in the bind pose.
+    pub inverse_bind: Mat4,
+}
+
+/// Joints linked into a hierarchy, with every parent before its children.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Skeleton {
+    joints: Vec<Joint>,
+}
+
+impl Skeleton {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a joint under `parent`, which has to be added already, and return its index. The
+    /// inverse bind matrix is the inverse of its world matrix in the rest pose.
+    pub fn add_joint(&mut self, name: &str, parent: Option<usize>, rest: Transform) -> usize {
+        assert!(
+            parent.is_none_or(|parent| parent < self.joints.len()),
+            "The parent of joint {name} has to be added before it"
+        );
+        let parent_world = parent.map_or(Mat4::IDENTITY, |parent| {
+            self.joints[parent].inverse_bind.inverse()
+        });
+        self.joints.push(Joint {
+            name: name.to_string(),
+            parent,
+            rest,
+            inverse_bind: (parent_world * rest.matrix()).inverse(),
+        });
+        self.joints.len() - 1
+    }
+
+    /// Override the inverse bind matrix, e.g. with the one a model was exported with.
+    pub fn set_inverse_bind(&mut self, joint: usize, inverse_bind: Mat4) {
+        self.joints[joint].inverse_bind = inverse_bind;
+    }
+
+    pub fn joints(&self) -> &[Joint] {
+        &self.joints
+    }
+
+    pub fn joint_index(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.joints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.joints.is_empty()
+    }
+
+    pub fn rest_pose(&self) -> Pose {
+        Pose {
+            locals: self.joints.iter().map(|joint| joint.rest).collect(),
+        }
+    }
+
+    /// The model space matrix of every joint in `pose`.
+    pub fn world_matrices(&self, pose: &Pose) -> Vec<Mat4> {
+        let mut worlds: Vec<Mat4> = Vec::with_capacity(self.joints.len());
+        for (joint, local) in self.joints.iter().zip(&pose.locals) {
+            let parent = joint.parent.map_or(Mat4::IDENTITY, |parent| worlds[parent]);
+            worlds.push(parent * local.matrix());
+        }
+        worlds
+    }
+
+    /// The matrix moving the skinned mesh along with each joint from the bind pose to
+    /// `pose`, as the vertex shader reads them.
+    pub fn skinning_matrices(&self, pose: &Pose) -> Vec<Mat4> {
+        self.world_matrices(pose)
+            .into_iter()
+            .zip(&self.joints)
+            .map(|(world, joint)| world * joint.inverse_bind)
+            .collect()
+    }
+}
+
+/// A local transform for each joint of a skeleton.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pose {
+    pub locals: Vec<Transform>,
+}
+
+impl Pose {
+    /// This pose moved `weight` of the way to `other`, joint by joint.
+    pub fn blend(&self, other: &Pose, weight: f32) -> Pose {
+        let locals = self
+            .locals
+            .iter()
+            .zip(&other.locals)
+            .map(|(a, b)| Transform {
+                translation: a.translation.lerp(b.translation, weight),
+                rotation: a.rotation.slerp(b.rotation, weight),
+                scale: a.scale.lerp(b.scale, weight),
+            })
+            .collect();
+        Pose { locals }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::{Quat, Vec3};
+
+    use super::*;
+
+    fn arm() -> Skeleton {
+        let mut skeleton = Skeleton::new();
+        let shoulder = skeleton.add_joint("shoulder", None, Transform::IDENTITY);
+        skeleton.add_joint(
+            "elbow",
+            Some(shoulder),
+            Transform::from_translation(Vec3::X),
+        );
+        skeleton
+    }
+
+    #[test]
+    fn test_rest_pose_skins_to_identity() {
+        let skeleton = arm();
+        for matrix in skeleton.skinning_matrices(&skeleton.rest_pose()) {
+            assert!(matrix.abs_diff_eq(Mat4::IDENTITY, 1e-6));
+        }
+    }
+
+    #[test]
+    fn test_bent_pose() {
+        let skeleton = arm();
+        let mut pose = skeleton.rest_pose();
+        pose.locals[0].rotation = Quat::from_rotation_z(90f32.to_radians());
+
+        // The elbow swings up with the shoulder, and so does a vertex past it.
+        let worlds = skeleton.world_matrices(&pose);
+        assert!(worlds[1]
+            .transform_point3(Vec3::ZERO)
+            .abs_diff_eq(Vec3::Y, 1e-6));
+        let skin = skeleton.skinning_matrices(&pose)[1];
+        assert!(skin
+            .transform_point3(Vec3::new(2.0, 0.0, 0.0))
+            .abs_diff_eq(Vec3::new(0.0, 2.0, 0.0), 1e-6));
+
+        let halfway = skeleton.rest_pose().blend(&pose, 0.5);
+        let angle = halfway.locals[0].rotation.angle_between(Quat::IDENTITY);
+        assert!((angle - 45f32.to_radians()).abs() < 1e-5);
+        assert_eq!(skeleton.joint_index("elbow"), Some(1));
+    }
+}
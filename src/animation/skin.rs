@@ -0,0 +1,156 @@
+use glam::{IVec4, Mat4, Vec2, Vec3, Vec4};
+
+use crate::{
+    gl::{types::GLuint, Gl},
+    mesh::layout::{vertex_struct, Semantic},
+    renderer::storage_buffer::StorageBuffer,
+    shader::Shader,
+};
+
+/// Binding point of the `Joints` storage block the skinned variant of `light_vert.glsl`
+/// declares.
+pub const JOINTS_BLOCK_BINDING: GLuint = 2;
+/// Joints a `JointBuffer` has room for.
+pub const MAX_JOINTS: usize = 128;
+/// Joints moving each vertex.
+pub const MAX_INFLUENCES: usize = 4;
+
+/// A vertex following up to four joints, each by its weight.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkinnedVertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub tex_coords: Vec2,
+    pub joints: IVec4,
+    /// Sum to 1.
+    pub weights: Vec4,
+}
+
+vertex_struct!(SkinnedVertex {
+    position: Semantic::Position,
+    normal: Semantic::Normal,
+    tex_coords: Semantic::TexCoords,
+    joints: Semantic::Joints,
+    weights: Semantic::Weights,
+});
+
+impl SkinnedVertex {
+    /// A vertex following the `MAX_INFLUENCES` joints of `influences` with the largest
+    /// weights, normalized to sum to 1. Without any weight it follows joint 0.
+    pub fn new(
+        position: Vec3,
+        normal: Vec3,
+        tex_coords: Vec2,
+        influences: &[(usize, f32)],
+    ) -> Self {
+        let mut influences = influences
+            .iter()
+            .filter(|(_, weight)| *weight > 0.0)
+            .copied()
+            .collect::<Vec<_>>();
+        influences.sort_by(|a, b| b.1.total_cmp(&a.1));
+        influences.truncate(MAX_INFLUENCES);
+
+        let total = influences.iter().map(|(_, weight)| weight).sum::<f32>();
+        let (mut joints, mut weights) = ([0; 4], [0.0; 4]);
+        for (i, (joint, weight)) in influences.into_iter().enumerate() {
+            joints[i] = joint as i32;
+            weights[i] = weight / total;
+        }
+        if total <= 0.0 {
+            weights[0] = 1.0;
+        }
+
+        Self {
+            position,
+            normal,
+            tex_coords,
+            joints: IVec4::from_array(joints),
+            weights: Vec4::from_array(weights),
+        }
+    }
+
+    /// Where `skinning_matrices` move the vertex, like the vertex shader does.
+    pub fn skinned_position(&self, skinning_matrices: &[Mat4]) -> Vec3 {
+        let skin = self
+            .joints
+            .to_array()
+            .into_iter()
+            .zip(self.weights.to_array())
+            .map(|(joint, weight)| skinning_matrices[joint as usize] * weight)
+            .fold(Mat4::ZERO, |sum, matrix| sum + matrix);
+        skin.transform_point3(self.position)
+    }
+}
+
+/// The skinning matrices the vertex shader reads, as the `Joints` storage block.
+pub struct JointBuffer {
+    buffer: StorageBuffer,
+}
+
+impl JointBuffer {
+    pub fn new(gl: &Gl) -> Self {
+        let buffer = StorageBuffer::new(
+            gl,
+            JOINTS_BLOCK_BINDING,
+            MAX_JOINTS * std::mem::size_of::<Mat4>(),
+        );
+        Self { buffer }
+    }
+
+    /// Upload the matrices of the skeleton drawn next, and bind them for its draw. Skinned
+    /// objects share the binding point, so call this before each one.
+    pub fn update(&mut self, gl: &Gl, skinning_matrices: &[Mat4]) {
+        assert!(
+            skinning_matrices.len() <= MAX_JOINTS,
+            "Skeletons have at most {MAX_JOINTS} joints"
+        );
+        // A std430 mat4 array is tightly packed columns, just like glam's.
+        let data = skinning_matrices
+            .iter()
+            .flat_map(|matrix| matrix.to_cols_array())
+            .flat_map(f32::to_ne_bytes)
+            .collect::<Vec<_>>();
+        self.buffer.update(gl, 0, &data);
+        self.buffer.bind(gl, JOINTS_BLOCK_BINDING);
+    }
+
+    pub fn check_block(&self, shader: &Shader) -> Result<(), String> {
+        self.buffer.check_block(shader, "Joints")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mesh::layout::Vertex;
+
+    #[test]
+    fn test_influences() {
+        let vertex = SkinnedVertex::new(
+            Vec3::X,
+            Vec3::Y,
+            Vec2::ZERO,
+            &[(1, 1.0), (2, 0.0), (3, 3.0), (4, 0.5), (5, 2.0), (6, 1.5)],
+        );
+        // The four heaviest, normalized.
+        assert_eq!(vertex.joints, IVec4::new(3, 5, 6, 1));
+        assert_eq!(vertex.weights, Vec4::new(3.0, 2.0, 1.5, 1.0) / 7.5);
+
+        let unweighted = SkinnedVertex::new(Vec3::X, Vec3::Y, Vec2::ZERO, &[]);
+        assert_eq!(unweighted.weights, Vec4::X);
+
+        let halfway = SkinnedVertex::new(Vec3::X, Vec3::Y, Vec2::ZERO, &[(0, 1.0), (1, 1.0)]);
+        let matrices = [
+            Mat4::IDENTITY,
+            Mat4::from_translation(Vec3::new(0.0, 2.0, 0.0)),
+        ];
+        assert_eq!(
+            halfway.skinned_position(&matrices),
+            Vec3::new(1.0, 1.0, 0.0)
+        );
+
+        assert_eq!(SkinnedVertex::layout().stride(0), 64);
+    }
+}
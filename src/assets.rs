@@ -0,0 +1,229 @@
+use std::{
+    borrow::Cow,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Environment variable holding extra asset roots, separated like `PATH`.
+pub const ASSET_PATH_ENV: &str = "LEARN_OGL_ASSET_PATH";
+
+/// Default assets compiled into the binary, keyed by their path relative to an asset root.
+static EMBEDDED_ASSETS: &[(&str, &[u8])] = &[
+    (
+        "src/shader/common/frame.glsl",
+        include_bytes!("shader/common/frame.glsl"),
+    ),
+    (
+        "src/shader/common/lighting.glsl",
+        include_bytes!("shader/common/lighting.glsl"),
+    ),
+    (
+        "src/shader/debug_vert.glsl",
+        include_bytes!("shader/debug_vert.glsl"),
+    ),
+    (
+        "src/shader/light_source_frag.glsl",
+        include_bytes!("shader/light_source_frag.glsl"),
+    ),
+    (
+        "src/shader/light_vert.glsl",
+        include_bytes!("shader/light_vert.glsl"),
+    ),
+    (
+        "src/shader/lit_object_frag.glsl",
+        include_bytes!("shader/lit_object_frag.glsl"),
+    ),
+    (
+        "src/shader/overlay_frag.glsl",
+        include_bytes!("shader/overlay_frag.glsl"),
+    ),
+    (
+        "src/shader/overlay_vert.glsl",
+        include_bytes!("shader/overlay_vert.glsl"),
+    ),
+    (
+        "static/diffuse_container.png",
+        include_bytes!("../static/diffuse_container.png"),
+    ),
+    (
+        "static/specular_container.png",
+        include_bytes!("../static/specular_container.png"),
+    ),
+];
+
+static RESOLVER: OnceLock<AssetResolver> = OnceLock::new();
+
+/// Where an asset was found.
+pub enum AssetSource {
+    Disk(PathBuf),
+    Embedded(&'static [u8]),
+}
+
+/// Looks assets up in a list of directories, falling back to the embedded copies. Files on disk
+/// win, so any embedded asset can be overridden.
+pub struct AssetResolver {
+    search_paths: Vec<PathBuf>,
+}
+
+impl AssetResolver {
+    pub fn new(search_paths: Vec<PathBuf>) -> Self {
+        Self { search_paths }
+    }
+
+    /// Search `cli_paths` first, then the directories in `LEARN_OGL_ASSET_PATH`, then the
+    /// working directory.
+    pub fn from_env(cli_paths: Vec<PathBuf>) -> Self {
+        let mut search_paths = cli_paths;
+        if let Some(env_paths) = env::var_os(ASSET_PATH_ENV) {
+            search_paths.extend(env::split_paths(&env_paths));
+        }
+        search_paths.push(PathBuf::from("."));
+
+        Self::new(search_paths)
+    }
+
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+
+    /// Find `name`. The error lists every location that was tried.
+    pub fn resolve(&self, name: impl AsRef<Path>) -> Result<AssetSource, String> {
+        let name = name.as_ref();
+        if let Some(path) = self.disk_path(name) {
+            return Ok(AssetSource::Disk(path));
+        }
+        if let Some(data) = embedded(name) {
+            return Ok(AssetSource::Embedded(data));
+        }
+
+        let tried = self
+            .search_paths
+            .iter()
+            .map(|root| format!("\n  {}", root.join(name).display()))
+            .collect::<String>();
+        Err(format!(
+            "Asset {} not found. Tried:{tried}\n  (embedded assets)",
+            name.display()
+        ))
+    }
+
+    /// Path of `name` in the first search directory that contains it.
+    pub fn disk_path(&self, name: impl AsRef<Path>) -> Option<PathBuf> {
+        self.search_paths
+            .iter()
+            .map(|root| root.join(name.as_ref()))
+            .find(|path| path.is_file())
+    }
+
+    pub fn read(&self, name: impl AsRef<Path>) -> Result<Cow<'static, [u8]>, String> {
+        match self.resolve(name)? {
+            AssetSource::Disk(path) => fs::read(&path)
+                .map(Cow::Owned)
+                .map_err(|err| format!("Failed to read {}: {err}", path.display())),
+            AssetSource::Embedded(data) => Ok(Cow::Borrowed(data)),
+        }
+    }
+
+    pub fn read_to_string(&self, name: impl AsRef<Path>) -> Result<String, String> {
+        let name = name.as_ref();
+        let data = self.read(name)?;
+        String::from_utf8(data.into_owned())
+            .map_err(|err| format!("Asset {} is not valid UTF-8: {err}", name.display()))
+    }
+}
+
+fn embedded(name: &Path) -> Option<&'static [u8]> {
+    EMBEDDED_ASSETS
+        .iter()
+        .find(|(embedded_name, _)| Path::new(embedded_name) == name)
+        .map(|(_, data)| *data)
+}
+
+/// Set the resolver used by every asset load. Has to be called before the first asset is
+/// loaded, later calls are ignored.
+pub fn init(resolver: AssetResolver) {
+    if RESOLVER.set(resolver).is_err() {
+        eprintln!("Asset resolver was already initialized");
+    }
+}
+
+/// The process wide resolver, configured from the environment if `init` wasn't called.
+pub fn resolver() -> &'static AssetResolver {
+    RESOLVER.get_or_init(|| AssetResolver::from_env(vec![]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_disk_overrides_embedded() {
+        let root = env::temp_dir().join(format!("learn_ogl_rs_assets_{}", std::process::id()));
+        fs::create_dir_all(root.join("src/shader")).unwrap();
+        fs::write(root.join("src/shader/light_vert.glsl"), "override").unwrap();
+
+        let resolver = AssetResolver::new(vec![root.clone()]);
+        assert_eq!(
+            resolver
+                .read_to_string("src/shader/light_vert.glsl")
+                .unwrap(),
+            "override"
+        );
+        assert!(matches!(
+            resolver.resolve("src/shader/overlay_vert.glsl"),
+            Ok(AssetSource::Embedded(_))
+        ));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_renderer_shaders_are_embedded() {
+        use crate::{
+            object::{
+                cube::{LIT_OBJECT_FRAGMENT_SHADER, LIT_OBJECT_VERTEX_SHADER},
+                light::LIGHT_SOURCE_FRAGMENT_SHADER,
+            },
+            renderer::{
+                debug_draw::{DEBUG_FRAGMENT_SHADER, DEBUG_VERTEX_SHADER},
+                overlay::{OVERLAY_FRAGMENT_SHADER, OVERLAY_VERTEX_SHADER},
+            },
+            shader::{
+                preprocess::{preprocess, SHADER_ROOT},
+                ShaderDefines,
+            },
+        };
+
+        // No roots, so only the embedded table is searched, includes and all.
+        let resolver = AssetResolver::new(vec![]);
+        for path in [
+            LIT_OBJECT_VERTEX_SHADER,
+            LIT_OBJECT_FRAGMENT_SHADER,
+            LIGHT_SOURCE_FRAGMENT_SHADER,
+            OVERLAY_VERTEX_SHADER,
+            OVERLAY_FRAGMENT_SHADER,
+            DEBUG_VERTEX_SHADER,
+            DEBUG_FRAGMENT_SHADER,
+        ] {
+            preprocess(
+                Path::new(SHADER_ROOT),
+                Path::new(path),
+                &ShaderDefines::new(),
+                |path| resolver.read_to_string(path),
+            )
+            .unwrap_or_else(|err| panic!("{path} isn't embedded: {err}"));
+        }
+    }
+
+    #[test]
+    fn test_missing_asset_lists_tried_paths() {
+        let resolver = AssetResolver::new(vec![PathBuf::from("a"), PathBuf::from("b")]);
+
+        let err = resolver.read("missing.png").err().unwrap();
+        assert_eq!(
+            err,
+            "Asset missing.png not found. Tried:\n  a/missing.png\n  b/missing.png\n  (embedded assets)"
+        );
+    }
+}
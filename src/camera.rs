@@ -5,11 +5,17 @@ use glam::{vec3, Mat4, Vec3};
 use winit::keyboard::KeyCode;
 
 const SPEED: f32 = 2.0;
+const FOV_DEFAULT: f32 = 80.0;
+const NEAR_DEFAULT: f32 = 0.1;
+const FAR_DEFAULT: f32 = 100.0;
 
 pub struct Camera {
     pos: Vec3,
     dir: Direction,
     up: Vec3,
+    fov: f32,
+    near: f32,
+    far: f32,
 }
 
 impl Camera {
@@ -20,9 +26,39 @@ impl Camera {
     pub fn view_matrix(&self) -> Mat4 {
         Mat4::look_to_rh(self.pos, self.dir.euler(), self.up)
     }
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Mat4 {
+        Mat4::perspective_rh_gl(self.fov.to_radians(), aspect_ratio, self.near, self.far)
+    }
     pub fn pos(&self) -> Vec3 {
         self.pos
     }
+    pub fn set_pos(&mut self, pos: Vec3) {
+        self.pos = pos;
+    }
+    /// Point the camera along `dir`. Straight up or down is clamped like mouse look.
+    pub fn look_in(&mut self, dir: Vec3) {
+        let dir = dir.normalize_or_zero();
+        if dir == Vec3::ZERO {
+            return;
+        }
+        self.set_yaw(dir.z.atan2(dir.x).to_degrees());
+        self.set_pitch(dir.y.asin().to_degrees());
+    }
+    /// Set the vertical field of view as is, e.g. from a model's camera. Only zooming keeps it
+    /// within its limits.
+    pub fn set_fov(&mut self, degrees: f32) {
+        self.fov = degrees;
+    }
+    pub fn set_clip_planes(&mut self, near: f32, far: f32) {
+        self.near = near;
+        self.far = far;
+    }
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+    pub fn adjust_zoom(&mut self, degrees: f32) {
+        self.fov = (self.fov + degrees).clamp(5.0, 80.0);
+    }
     fn get_right_dir(&self) -> Vec3 {
         -self.dir.euler().cross(self.up).normalize()
     }
@@ -86,6 +122,9 @@ impl Default for Camera {
             pos: camera_pos,
             dir: camera_dir,
             up: camera_up,
+            fov: FOV_DEFAULT,
+            near: NEAR_DEFAULT,
+            far: FAR_DEFAULT,
         }
     }
 }
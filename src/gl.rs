@@ -26,6 +26,15 @@ impl Gl {
 
         (width - x) as f32 / (height - y) as f32
     }
+
+    /// Width and height of the current viewport in pixels.
+    pub fn get_viewport_size(&self) -> (f32, f32) {
+        let mut data: [types::GLint; 4] = [0, 0, 0, 0];
+
+        unsafe { self.GetIntegerv(VIEWPORT, data.as_mut_ptr()) };
+
+        (data[2] as f32, data[3] as f32)
+    }
 }
 
 pub fn get_gl_string(gl: &Gl, variant: GLenum) -> Option<&'static CStr> {
@@ -35,6 +44,32 @@ pub fn get_gl_string(gl: &Gl, variant: GLenum) -> Option<&'static CStr> {
     }
 }
 
+/// Driver identification strings of the current context.
+#[derive(Clone, Debug, Default)]
+pub struct GlInfo {
+    pub vendor: String,
+    pub renderer: String,
+    pub version: String,
+    pub shading_language_version: String,
+}
+
+impl GlInfo {
+    pub fn query(gl: &Gl) -> Self {
+        let query = |variant| {
+            get_gl_string(gl, variant)
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+
+        Self {
+            vendor: query(VENDOR),
+            renderer: query(RENDERER),
+            version: query(VERSION),
+            shading_language_version: query(SHADING_LANGUAGE_VERSION),
+        }
+    }
+}
+
 pub unsafe fn create_shader(gl: &Gl, shader: GLenum, source: &[u8]) -> GLuint {
     let shader = gl.CreateShader(shader);
     gl.ShaderSource(
@@ -47,6 +82,44 @@ pub unsafe fn create_shader(gl: &Gl, shader: GLenum, source: &[u8]) -> GLuint {
     shader
 }
 
+pub fn get_shader_info_log(gl: &Gl, shader: GLuint) -> String {
+    let mut length = 0;
+    unsafe { gl.GetShaderiv(shader, INFO_LOG_LENGTH, &mut length) };
+
+    let mut log = vec![0u8; length.max(1) as usize];
+    unsafe {
+        gl.GetShaderInfoLog(
+            shader,
+            length,
+            std::ptr::null_mut(),
+            log.as_mut_ptr().cast(),
+        )
+    };
+
+    String::from_utf8_lossy(&log)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+pub fn get_program_info_log(gl: &Gl, program: GLuint) -> String {
+    let mut length = 0;
+    unsafe { gl.GetProgramiv(program, INFO_LOG_LENGTH, &mut length) };
+
+    let mut log = vec![0u8; length.max(1) as usize];
+    unsafe {
+        gl.GetProgramInfoLog(
+            program,
+            length,
+            std::ptr::null_mut(),
+            log.as_mut_ptr().cast(),
+        )
+    };
+
+    String::from_utf8_lossy(&log)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
 pub fn create_gl_context(window: &Window, gl_config: &Config) -> NotCurrentContext {
     let raw_window_handle = window.window_handle().ok().map(|wh| wh.as_raw());
 
@@ -1,10 +1,14 @@
+pub mod animation;
+pub mod assets;
 pub mod camera;
 pub mod gl;
 pub mod helper;
 pub mod logging;
 pub mod mesh;
+pub mod model;
 pub mod object;
 pub mod renderer;
+pub mod scene;
 pub mod shader;
 pub mod state;
 pub mod timer;
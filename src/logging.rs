@@ -1,6 +1,6 @@
 use std::ptr::{null, slice_from_raw_parts};
 
-use crate::gl::{self, get_gl_string, Gl};
+use crate::gl::{self, Gl, GlInfo};
 
 extern "system" fn handle_log(
     _source: u32,
@@ -27,18 +27,16 @@ extern "system" fn handle_log(
     }
 }
 
-pub fn setup_logging(gl: &Gl) {
-    if let Some(renderer) = get_gl_string(gl, gl::RENDERER) {
-        println!("Running on {}", renderer.to_string_lossy());
-    }
-    if let Some(version) = get_gl_string(gl, gl::VERSION) {
-        println!("OpenGL Version {}", version.to_string_lossy());
-    }
-    if let Some(shaders_version) = get_gl_string(gl, gl::SHADING_LANGUAGE_VERSION) {
-        println!("Shaders version on {}", shaders_version.to_string_lossy());
-    }
+pub fn setup_logging(gl: &Gl) -> GlInfo {
+    let info = GlInfo::query(gl);
+    println!("Running on {} ({})", info.renderer, info.vendor);
+    println!("OpenGL Version {}", info.version);
+    println!("Shaders version on {}", info.shading_language_version);
+
     unsafe {
         gl.Enable(gl::DEBUG_OUTPUT);
         gl.DebugMessageCallback(Some(handle_log), null());
     };
+
+    info
 }
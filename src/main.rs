@@ -5,9 +5,36 @@ use winit::{
     window::Window,
 };
 
-use learn_ogl_rs::state::App;
+use std::path::PathBuf;
+
+use learn_ogl_rs::{
+    assets::{self, AssetResolver, ASSET_PATH_ENV},
+    state::App,
+};
+
+/// Collect every `--asset-path <dir>` / `--asset-path=<dir>` argument.
+fn parse_asset_paths() -> Vec<PathBuf> {
+    let mut asset_paths = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--asset-path" {
+            match args.next() {
+                Some(path) => asset_paths.push(PathBuf::from(path)),
+                None => eprintln!("--asset-path needs a directory"),
+            }
+        } else if let Some(path) = arg.strip_prefix("--asset-path=") {
+            asset_paths.push(PathBuf::from(path));
+        } else {
+            eprintln!("Ignoring unknown argument {arg}");
+            eprintln!("Usage: learn_ogl_rs [--asset-path <dir>]... (or set {ASSET_PATH_ENV})");
+        }
+    }
+    asset_paths
+}
 
 fn main() {
+    assets::init(AssetResolver::from_env(parse_asset_paths()));
+
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
 
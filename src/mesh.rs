@@ -1,123 +1,517 @@
-use std::ffi::CString;
+pub mod bounds;
+pub mod layout;
+pub mod lod;
+pub mod primitives;
+pub mod simplify;
+pub mod tangents;
 
-use glam::{vec3, Mat4, Vec3};
+use std::rc::Rc;
+
+use glam::{vec3, Quat, Vec3};
 
 use crate::{
     gl::{
         self,
-        types::{GLfloat, GLint, GLuint},
+        types::{GLboolean, GLenum, GLfloat, GLint, GLsizei, GLuint},
         Gl,
     },
     helper::get_rand_angle,
-    shader::{Shader, ShaderTrait},
+    renderer::{asset_cache::MeshAsset, stats::FrameStats},
+    scene::Transform,
+    shader::{reflection::Reflection, Shader, ShaderTrait},
 };
 
-struct Transform {
-    rotation: GLfloat,
-    scale: Vec3,
-    translation: Vec3,
-}
+use self::{
+    bounds::Bounds,
+    layout::{ComponentType, Vertex, VertexLayout},
+};
+
+/// Axis objects spin around while drawn.
+pub const SPIN_AXIS: Vec3 = vec3(0.5, 1.0, 0.0);
 
 pub struct Mesh {
-    vertex_buffer: VertexBuffer,
+    vertex_buffer: Rc<MeshAsset>,
     transform: Transform,
-    fov: f32,
     texture_blend: GLfloat,
+    /// Turn around `SPIN_AXIS` a little every draw.
+    spinning: bool,
+}
+
+/// How the vertices of a buffer are assembled into primitives.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Topology {
+    #[default]
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+    Lines,
+    LineStrip,
+    Points,
+}
+
+impl Topology {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            Topology::Triangles => gl::TRIANGLES,
+            Topology::TriangleStrip => gl::TRIANGLE_STRIP,
+            Topology::TriangleFan => gl::TRIANGLE_FAN,
+            Topology::Lines => gl::LINES,
+            Topology::LineStrip => gl::LINE_STRIP,
+            Topology::Points => gl::POINTS,
+        }
+    }
+}
+
+/// An integer type an element buffer can hold.
+pub trait IndexType: Copy {
+    const GL_TYPE: GLenum;
+}
+
+impl IndexType for u16 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_SHORT;
+}
+
+impl IndexType for u32 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_INT;
+}
+
+#[derive(Clone, Copy)]
+struct ElementBuffer {
+    ebo: GLuint,
+    index_type: GLenum,
 }
 
 pub struct VertexBuffer {
-    vbo: u32,
+    /// One buffer per binding of the layout.
+    vbos: Vec<GLuint>,
     vao: u32,
-    bindingindex: u32,
+    ebo: Option<ElementBuffer>,
+    layout: VertexLayout,
+    topology: Topology,
+    vertex_count: u32,
+    index_count: u32,
+    /// Leading `vbos` belong to another vertex buffer.
+    shared_buffers: usize,
+    /// The element buffer belongs to another vertex buffer.
+    shares_elements: bool,
+    /// Of the positions in the first buffer, as of the last upload.
+    bounds: Option<Bounds>,
 }
 
 impl VertexBuffer {
-    pub fn new(gl: &Gl, buffer: &[f32], stride: i32) -> Self {
+    /// Vertices read as `layout` describes, with `data` in its first buffer. Other buffers
+    /// start empty and are filled with `set_buffer_data`.
+    pub fn new<T: Copy>(gl: &Gl, data: &[T], layout: VertexLayout) -> Self {
         let mut vertex_buffer = Self {
-            vbo: 0,
+            vbos: vec![0; layout.buffer_count()],
             vao: 0,
-            bindingindex: 0,
+            ebo: None,
+            vertex_count: vertex_count(data, layout.stride(0)),
+            bounds: Bounds::from_vertex_data(data, &layout),
+            layout,
+            topology: Topology::default(),
+            index_count: 0,
+            shared_buffers: 0,
+            shares_elements: false,
         };
 
         unsafe {
-            gl.CreateBuffers(1, &mut vertex_buffer.vbo);
-
-            gl.NamedBufferData(
-                vertex_buffer.vbo,
-                (std::mem::size_of_val(buffer)) as gl::types::GLsizeiptr,
-                buffer.as_ptr() as *const _,
-                gl::STATIC_DRAW,
+            gl.CreateBuffers(
+                vertex_buffer.vbos.len() as GLsizei,
+                vertex_buffer.vbos.as_mut_ptr(),
             );
+            gl.CreateVertexArrays(1, &mut vertex_buffer.vao);
         };
+        vertex_buffer.attach_buffers(gl);
+        vertex_buffer.upload(gl, 0, data, gl::STATIC_DRAW);
+
+        vertex_buffer
+    }
 
+    /// A vertex array drawing these vertices many times, with the per-instance data
+    /// `instance_layout` describes read from new buffers that start empty. The vertex and
+    /// element buffers are shared, not copied.
+    pub fn instanced(&self, gl: &Gl, instance_layout: VertexLayout) -> Self {
+        let layout = self.layout.clone().with_instances(instance_layout);
+        let mut vbos = self.vbos.clone();
+        let first_instance_buffer = vbos.len();
+        vbos.resize(layout.buffer_count(), 0);
+
+        let mut vertex_buffer = Self {
+            vbos,
+            vao: 0,
+            ebo: self.ebo,
+            layout,
+            topology: self.topology,
+            vertex_count: self.vertex_count,
+            index_count: self.index_count,
+            shared_buffers: first_instance_buffer,
+            shares_elements: true,
+            bounds: self.bounds,
+        };
         unsafe {
+            let instance_buffers = &mut vertex_buffer.vbos[first_instance_buffer..];
+            gl.CreateBuffers(
+                instance_buffers.len() as GLsizei,
+                instance_buffers.as_mut_ptr(),
+            );
             gl.CreateVertexArrays(1, &mut vertex_buffer.vao);
+        }
+        vertex_buffer.attach_buffers(gl);
+        if let Some(element_buffer) = vertex_buffer.ebo {
+            unsafe { gl.VertexArrayElementBuffer(vertex_buffer.vao, element_buffer.ebo) };
+        }
 
-            gl.VertexArrayVertexBuffer(
-                vertex_buffer.vao(),
-                vertex_buffer.bindingindex,
-                vertex_buffer.vbo(),
-                0,
-                stride * std::mem::size_of::<f32>() as gl::types::GLsizei,
-            );
+        vertex_buffer
+    }
+
+    /// A vertex array drawing these vertices through `indices` instead, e.g. a simplified level
+    /// of detail. The vertex buffers are shared, not copied, and the element buffer is its own.
+    pub fn with_shared_vertices<I: IndexType>(&self, gl: &Gl, indices: &[I]) -> Self {
+        let mut vertex_buffer = Self {
+            vbos: self.vbos.clone(),
+            vao: 0,
+            ebo: None,
+            layout: self.layout.clone(),
+            topology: self.topology,
+            vertex_count: self.vertex_count,
+            index_count: 0,
+            shared_buffers: self.vbos.len(),
+            shares_elements: false,
+            bounds: self.bounds,
         };
+        unsafe { gl.CreateVertexArrays(1, &mut vertex_buffer.vao) };
+        vertex_buffer.attach_buffers(gl);
+        vertex_buffer.set_indices(gl, indices);
 
         vertex_buffer
     }
 
-    pub fn set_float_attribute_position(
-        &self,
+    fn attach_buffers(&self, gl: &Gl) {
+        for (binding, vbo) in self.vbos.iter().enumerate() {
+            let binding = binding as GLuint;
+            unsafe {
+                gl.VertexArrayVertexBuffer(self.vao, binding, *vbo, 0, self.layout.stride(binding));
+                gl.VertexArrayBindingDivisor(self.vao, binding, self.layout.divisor(binding));
+            }
+        }
+    }
+
+    /// Vertices of a `#[repr(C)]` vertex struct, laid out like its `Vertex` implementation
+    /// says.
+    pub fn from_vertices<V: Vertex>(gl: &Gl, vertices: &[V]) -> Self {
+        Self::new(gl, vertices, V::layout())
+    }
+
+    /// Vertices drawn through `indices`, so shared corners are stored once. Use u16 indices
+    /// for buffers of up to 65536 vertices to halve the element buffer.
+    pub fn with_indices<T: Copy, I: IndexType>(
         gl: &Gl,
-        shader_attribute_name: &str,
-        program: u32,
-        start: u32,
-        length: u32,
-    ) {
+        data: &[T],
+        indices: &[I],
+        layout: VertexLayout,
+    ) -> Self {
+        let mut vertex_buffer = Self::new(gl, data, layout);
+        vertex_buffer.set_indices(gl, indices);
+        vertex_buffer
+    }
+
+    /// Replace the element buffer, creating it if the geometry wasn't indexed yet.
+    pub fn set_indices<I: IndexType>(&mut self, gl: &Gl, indices: &[I]) {
+        let ebo = match &self.ebo {
+            Some(element_buffer) => element_buffer.ebo,
+            None => {
+                let mut ebo = 0;
+                unsafe {
+                    gl.CreateBuffers(1, &mut ebo);
+                    gl.VertexArrayElementBuffer(self.vao(), ebo);
+                }
+                ebo
+            }
+        };
         unsafe {
-            let c_shader_attribute_name = CString::new(shader_attribute_name).unwrap();
-            let attrib = gl.GetAttribLocation(
-                program,
-                c_shader_attribute_name.as_ptr() as *const gl::types::GLchar,
+            gl.NamedBufferData(
+                ebo,
+                (std::mem::size_of_val(indices)) as gl::types::GLsizeiptr,
+                indices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
             );
-            if attrib == -1 {
-                panic!("\nAttribute not found! Attribute Name: {shader_attribute_name}\n")
-            }
+        }
+
+        self.ebo = Some(ElementBuffer {
+            ebo,
+            index_type: I::GL_TYPE,
+        });
+        self.index_count = indices.len() as u32;
+    }
+
+    /// Point the vertex array's attributes at the shader inputs they feed, after checking the
+    /// layout against the shader's active attributes.
+    pub fn bind_attributes(&self, gl: &Gl, shader: &Shader) -> Result<(), String> {
+        bind_attributes(gl, self.vao, &self.layout, shader)
+    }
+
+    /// Replace the contents of the first buffer, for data that changes every frame.
+    pub fn set_data<T: Copy>(&mut self, gl: &Gl, data: &[T]) {
+        self.vertex_count = vertex_count(data, self.layout.stride(0));
+        self.bounds = Bounds::from_vertex_data(data, &self.layout);
+        self.upload(gl, 0, data, gl::DYNAMIC_DRAW);
+    }
+
+    /// Replace the contents of the buffer of `binding`.
+    pub fn set_buffer_data<T: Copy>(&mut self, gl: &Gl, binding: u32, data: &[T]) {
+        if binding == 0 {
+            self.set_data(gl, data);
+        } else {
+            self.upload(gl, binding, data, gl::DYNAMIC_DRAW);
+        }
+    }
 
-            gl.EnableVertexArrayAttrib(self.vao(), attrib as u32);
-            gl.VertexArrayAttribFormat(
-                self.vao(),
-                attrib as u32,
-                length as GLint,
-                gl::FLOAT,
-                gl::FALSE,
-                (start as usize * std::mem::size_of::<f32>()) as gl::types::GLuint,
+    fn upload<T: Copy>(&self, gl: &Gl, binding: u32, data: &[T], usage: GLenum) {
+        unsafe {
+            gl.NamedBufferData(
+                self.vbos[binding as usize],
+                (std::mem::size_of_val(data)) as gl::types::GLsizeiptr,
+                data.as_ptr() as *const _,
+                usage,
             );
-            gl.VertexArrayAttribBinding(self.vao(), attrib as u32, self.bindingindex);
         }
     }
 
+    /// The vertex array and the buffers it owns. Buffers shared with the vertex array this one
+    /// was made from by `instanced` or `with_shared_vertices` are left out.
+    pub fn gl_objects(&self) -> (GLuint, Vec<GLuint>) {
+        let mut buffers = self.vbos[self.shared_buffers..].to_vec();
+        if let Some(element_buffer) = self.ebo.filter(|_| !self.shares_elements) {
+            buffers.push(element_buffer.ebo);
+        }
+        (self.vao, buffers)
+    }
+
+    /// Local bounds of the vertex positions, `None` without vertices or float positions.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Buffer of the first binding.
     pub fn vbo(&self) -> GLuint {
-        self.vbo
+        self.vbos[0]
+    }
+    pub fn ebo(&self) -> Option<GLuint> {
+        self.ebo.map(|element_buffer| element_buffer.ebo)
     }
     pub fn vao(&self) -> GLuint {
         self.vao
     }
+
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+
+    pub fn index_count(&self) -> u32 {
+        self.index_count
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.ebo.is_some()
+    }
+
+    /// Draw the whole buffer `instance_count` times. The vertex array has to be bound and the
+    /// per-instance buffers filled.
+    pub fn draw_instanced(&self, gl: &Gl, instance_count: u32, stats: &mut FrameStats) {
+        let mode = self.topology.gl_enum();
+        unsafe {
+            match &self.ebo {
+                Some(element_buffer) => {
+                    gl.DrawElementsInstanced(
+                        mode,
+                        self.index_count as i32,
+                        element_buffer.index_type,
+                        std::ptr::null(),
+                        instance_count as i32,
+                    );
+                    stats.record_instanced_draw(mode, self.index_count, instance_count);
+                }
+                None => {
+                    gl.DrawArraysInstanced(
+                        mode,
+                        0,
+                        self.vertex_count as i32,
+                        instance_count as i32,
+                    );
+                    stats.record_instanced_draw(mode, self.vertex_count, instance_count);
+                }
+            }
+        }
+    }
+
+    /// Issue the draw call for the whole buffer. The vertex array has to be bound.
+    pub fn draw(&self, gl: &Gl, stats: &mut FrameStats) {
+        let mode = self.topology.gl_enum();
+        unsafe {
+            match &self.ebo {
+                Some(element_buffer) => {
+                    gl.DrawElements(
+                        mode,
+                        self.index_count as i32,
+                        element_buffer.index_type,
+                        std::ptr::null(),
+                    );
+                    stats.record_draw(mode, self.index_count);
+                }
+                None => {
+                    gl.DrawArrays(mode, 0, self.vertex_count as i32);
+                    stats.record_draw(mode, self.vertex_count);
+                }
+            }
+        }
+    }
+}
+
+/// Point the attributes of `vao` at the shader inputs they feed, after checking `layout`
+/// against the shader's active attributes.
+pub(crate) fn bind_attributes(
+    gl: &Gl,
+    vao: GLuint,
+    layout: &VertexLayout,
+    shader: &Shader,
+) -> Result<(), String> {
+    for format in attribute_formats(layout, &shader.reflection())? {
+        format.apply_named(gl, vao);
+    }
+    Ok(())
+}
+
+/// Where one location of a shader input reads from a vertex buffer binding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct AttributeFormat {
+    pub location: GLuint,
+    pub count: u32,
+    pub component_type: ComponentType,
+    pub offset: u32,
+    pub binding: u32,
+}
+
+impl AttributeFormat {
+    /// Set the format on `vao` through the named vertex array calls (4.5).
+    pub fn apply_named(&self, gl: &Gl, vao: GLuint) {
+        let component_type = self.component_type;
+        unsafe {
+            gl.EnableVertexArrayAttrib(vao, self.location);
+            if component_type.is_integer() {
+                gl.VertexArrayAttribIFormat(
+                    vao,
+                    self.location,
+                    self.count as GLint,
+                    component_type.gl_enum(),
+                    self.offset,
+                );
+            } else {
+                gl.VertexArrayAttribFormat(
+                    vao,
+                    self.location,
+                    self.count as GLint,
+                    component_type.gl_enum(),
+                    component_type.normalized() as GLboolean,
+                    self.offset,
+                );
+            }
+            gl.VertexArrayAttribBinding(vao, self.location, self.binding);
+        }
+    }
+
+    /// Set the format on the bound vertex array, for contexts without the named calls (4.3).
+    pub fn apply_bound(&self, gl: &Gl) {
+        let component_type = self.component_type;
+        unsafe {
+            gl.EnableVertexAttribArray(self.location);
+            if component_type.is_integer() {
+                gl.VertexAttribIFormat(
+                    self.location,
+                    self.count as GLint,
+                    component_type.gl_enum(),
+                    self.offset,
+                );
+            } else {
+                gl.VertexAttribFormat(
+                    self.location,
+                    self.count as GLint,
+                    component_type.gl_enum(),
+                    component_type.normalized() as GLboolean,
+                    self.offset,
+                );
+            }
+            gl.VertexAttribBinding(self.location, self.binding);
+        }
+    }
+}
+
+/// The formats feeding the inputs `reflection` found from `layout`, after checking the two
+/// against each other.
+pub(crate) fn attribute_formats(
+    layout: &VertexLayout,
+    reflection: &Reflection,
+) -> Result<Vec<AttributeFormat>, String> {
+    layout.check(reflection.attributes())?;
+
+    let mut formats = vec![];
+    for attribute in layout.attributes() {
+        // Inputs the shader doesn't use are optimized out.
+        let Some(info) = reflection.attribute(attribute.semantic.attribute_name()) else {
+            continue;
+        };
+        let column_size = attribute.count * attribute.component_type.size() as u32;
+
+        // Every column of a matrix is a location of its own.
+        formats.extend((0..attribute.columns).map(|column| AttributeFormat {
+            location: info.location as GLuint + column,
+            count: attribute.count,
+            component_type: attribute.component_type,
+            offset: attribute.offset + column * column_size,
+            binding: attribute.binding,
+        }));
+    }
+    Ok(formats)
+}
+
+fn vertex_count<T>(data: &[T], stride: i32) -> u32 {
+    (std::mem::size_of_val(data) / stride.max(1) as usize) as u32
+}
+
+/// A random rotation around `SPIN_AXIS`, so objects don't all spin in sync.
+pub fn random_spin() -> Quat {
+    Quat::from_axis_angle(SPIN_AXIS.normalize(), get_rand_angle().to_radians())
 }
 
 impl Mesh {
-    pub fn new(translation: Vec3, vertex_buffer: VertexBuffer) -> Self {
+    pub fn new(translation: Vec3, vertex_buffer: impl Into<Rc<MeshAsset>>) -> Self {
         Mesh {
-            vertex_buffer,
+            vertex_buffer: vertex_buffer.into(),
             transform: Transform {
-                rotation: get_rand_angle(),
-                translation,
-                scale: vec3(1.0, 1.0, 1.0),
+                rotation: random_spin(),
+                ..Transform::from_translation(translation)
             },
-            fov: 80.0,
             texture_blend: 0.2,
+            spinning: true,
         }
     }
 
+    pub fn set_spinning(&mut self, spinning: bool) {
+        self.spinning = spinning;
+    }
+
     pub fn adjust_blend(&mut self, percent: f32) {
         self.texture_blend = (self.texture_blend + percent).clamp(0.0, 1.0);
     }
@@ -126,12 +520,7 @@ impl Mesh {
     }
 
     pub fn rotate_by(&mut self, degrees: GLfloat) {
-        let transform = &mut self.transform;
-        transform.rotation += degrees;
-    }
-
-    pub fn adjust_zoom(&mut self, degrees: GLfloat) {
-        self.fov = (self.fov + degrees).clamp(5.0, 80.0);
+        self.transform.rotate_local(SPIN_AXIS.normalize(), degrees);
     }
 
     pub fn adjust_scale(&mut self, scale: Vec3) {
@@ -143,6 +532,14 @@ impl Mesh {
         self.transform.translation
     }
 
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
     pub fn set_pos(&mut self, pos: Vec3) {
         self.transform.translation = pos;
     }
@@ -151,31 +548,34 @@ impl Mesh {
         self.vertex_buffer.vao()
     }
 
-    pub fn draw(&mut self, gl: &Gl, view_matrix: Mat4, shader: &Shader) {
-        self.rotate_by(1.0);
-        let transform = &self.transform;
-
-        let model_matrix = Mat4::IDENTITY
-            * Mat4::from_translation(transform.translation)
-            * Mat4::from_rotation_x((transform.rotation / 2.0).to_radians())
-            * Mat4::from_rotation_y(transform.rotation.to_radians())
-            * Mat4::from_scale(transform.scale);
+    pub fn local_bounds(&self) -> Option<Bounds> {
+        self.vertex_buffer.bounds()
+    }
 
-        let projection_matrix =
-            Mat4::perspective_rh_gl(self.fov.to_radians(), gl.get_aspect_ratio(), 0.1, 100.0);
+    /// The local bounds moved by the mesh's transform.
+    pub fn world_bounds(&self) -> Option<Bounds> {
+        let matrix = self.transform.matrix();
+        self.local_bounds()
+            .map(|bounds| bounds.transformed(&matrix))
+    }
 
-        shader.set_mat4(gl, "model", model_matrix).unwrap();
+    /// Draw the mesh. View and projection come from the shared `Frame` uniform block.
+    pub fn draw(&mut self, gl: &Gl, shader: &Shader, stats: &mut FrameStats) {
+        if self.spinning {
+            self.rotate_by(1.0);
+        }
+        let model_matrix = self.transform.matrix();
 
-        shader.set_mat4(gl, "view", view_matrix).unwrap();
+        shader.set_uniform(gl, "model", &model_matrix).unwrap();
 
-        shader
-            .set_mat4(gl, "projection", projection_matrix)
-            .unwrap();
+        shader.enable(gl);
+        stats.record_state_change();
 
         unsafe {
             gl.BindVertexArray(self.vao());
-            gl.DrawArrays(gl::TRIANGLES, 0, 36);
         }
+        stats.record_state_change();
+        self.vertex_buffer.draw(gl, stats);
     }
 }
 
@@ -183,6 +583,16 @@ impl Mesh {
 mod test {
     use glam::{vec3, vec4, Mat4};
 
+    use super::*;
+
+    #[test]
+    fn test_topology_draw_modes() {
+        assert_eq!(Topology::default().gl_enum(), gl::TRIANGLES);
+        assert_eq!(Topology::LineStrip.gl_enum(), gl::LINE_STRIP);
+        assert_eq!(u16::GL_TYPE, gl::UNSIGNED_SHORT);
+        assert_eq!(u32::GL_TYPE, gl::UNSIGNED_INT);
+    }
+
     #[test]
     fn test_translate() {
         let mut vec = vec4(1.0, 0.0, 0.0, 1.0);
@@ -0,0 +1,284 @@
+use glam::{Mat3, Mat4, Vec3};
+
+use super::layout::{ComponentType, Semantic, VertexLayout};
+
+/// An axis aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// The smallest box around `points`, or `None` without any.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |aabb, point| {
+            Self::new(aabb.min.min(point), aabb.max.max(point))
+        }))
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// The 8 corners, with bit 0, 1 and 2 of the index selecting the max x, y and z.
+    pub fn corners(&self) -> [Vec3; 8] {
+        std::array::from_fn(|i| {
+            Vec3::select(
+                glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+                self.max,
+                self.min,
+            )
+        })
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    /// The box around this one after `matrix`, which is larger than the transformed box
+    /// itself when rotated.
+    pub fn transformed(&self, matrix: &Mat4) -> Aabb {
+        // Each axis of the new half extents is the sum of the absolute contributions of the
+        // old axes.
+        let linear = Mat3::from_mat4(*matrix);
+        let abs = Mat3::from_cols(
+            linear.x_axis.abs(),
+            linear.y_axis.abs(),
+            linear.z_axis.abs(),
+        );
+        let center = matrix.transform_point3(self.center());
+        let half_extents = abs * self.half_extents();
+        Self::new(center - half_extents, center + half_extents)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// A sphere around `points` centered on their bounding box. Not the smallest one, but
+    /// close for most meshes and cheap to find.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3> + Clone) -> Option<Self> {
+        let center = Aabb::from_points(points.clone())?.center();
+        let radius = points
+            .into_iter()
+            .map(|point| point.distance_squared(center))
+            .fold(0.0, f32::max)
+            .sqrt();
+        Some(Self::new(center, radius))
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.distance_squared(self.center) <= self.radius * self.radius
+    }
+
+    /// The smallest sphere around both.
+    pub fn union(&self, other: &BoundingSphere) -> BoundingSphere {
+        let offset = other.center - self.center;
+        let distance = offset.length();
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+        let radius = (distance + self.radius + other.radius) * 0.5;
+        let center = self.center + offset * ((radius - self.radius) / distance);
+        Self::new(center, radius)
+    }
+
+    /// The sphere around this one after `matrix`, scaled by its largest axis scale.
+    pub fn transformed(&self, matrix: &Mat4) -> BoundingSphere {
+        let scale = matrix
+            .x_axis
+            .truncate()
+            .length_squared()
+            .max(matrix.y_axis.truncate().length_squared())
+            .max(matrix.z_axis.truncate().length_squared())
+            .sqrt();
+        Self::new(matrix.transform_point3(self.center), self.radius * scale)
+    }
+}
+
+/// A box and a sphere around the same geometry. Culling tests use whichever is cheaper or
+/// tighter for them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl Bounds {
+    pub fn from_points(points: impl IntoIterator<Item = Vec3> + Clone) -> Option<Self> {
+        Some(Self {
+            aabb: Aabb::from_points(points.clone())?,
+            sphere: BoundingSphere::from_points(points)?,
+        })
+    }
+
+    /// Bounds of the `Semantic::Position` attribute of the vertices in `data`, which is the
+    /// first buffer of `layout`. 2D positions get z = 0. `None` without float positions or
+    /// vertices.
+    pub fn from_vertex_data<T: Copy>(data: &[T], layout: &VertexLayout) -> Option<Self> {
+        let position = layout.attributes().iter().find(|attribute| {
+            attribute.semantic == Semantic::Position
+                && attribute.binding == 0
+                && attribute.component_type == ComponentType::F32
+        })?;
+        let count = position.count.min(3) as usize;
+        let stride = layout.stride(0) as usize;
+        let size = std::mem::size_of_val(data);
+        if stride == 0 || position.offset as usize + count * 4 > stride {
+            return None;
+        }
+
+        let base = data.as_ptr().cast::<u8>();
+        let points = (0..size / stride).map(move |vertex| {
+            let mut point = [0.0; 3];
+            for (component, value) in point.iter_mut().enumerate().take(count) {
+                let offset = vertex * stride + position.offset as usize + component * 4;
+                // In bounds as the attribute fits into each of the `size / stride` vertices,
+                // and the position bytes are the floats the layout says are there.
+                *value = unsafe { base.add(offset).cast::<f32>().read_unaligned() };
+            }
+            Vec3::from_array(point)
+        });
+        Self::from_points(points)
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Self {
+            aabb: self.aabb.union(&other.aabb),
+            sphere: self.sphere.union(&other.sphere),
+        }
+    }
+
+    pub fn transformed(&self, matrix: &Mat4) -> Bounds {
+        Self {
+            aabb: self.aabb.transformed(matrix),
+            sphere: self.sphere.transformed(matrix),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::{Quat, Vec2};
+
+    use super::*;
+    use crate::mesh::{
+        layout::{StandardVertex, Vertex},
+        primitives,
+    };
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{a} != {b}");
+    }
+
+    #[test]
+    fn test_from_vertex_data() {
+        let vertices = [
+            StandardVertex {
+                position: Vec3::new(-1.0, 0.0, 2.0),
+                normal: Vec3::Y,
+                tex_coords: Vec2::ZERO,
+            },
+            StandardVertex {
+                position: Vec3::new(3.0, 2.0, 0.0),
+                normal: Vec3::Y,
+                tex_coords: Vec2::ONE,
+            },
+        ];
+        let bounds = Bounds::from_vertex_data(&vertices, &StandardVertex::layout()).unwrap();
+        assert_eq!(
+            bounds.aabb,
+            Aabb::new(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(3.0, 2.0, 2.0))
+        );
+        assert_eq!(bounds.sphere.center, Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(bounds.sphere.radius, 6f32.sqrt());
+
+        assert!(Bounds::from_vertex_data::<f32>(&[], &StandardVertex::layout()).is_none());
+        assert!(Bounds::from_vertex_data(&[0.0f32; 8], &VertexLayout::new()).is_none());
+
+        let sphere = primitives::sphere(2.0, 16, 8);
+        let bounds =
+            Bounds::from_vertex_data(&sphere.vertices, &primitives::PrimitiveVertex::layout())
+                .unwrap();
+        assert_near(bounds.aabb.half_extents(), Vec3::splat(2.0));
+        assert!((bounds.sphere.radius - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_transformed() {
+        let unit = Bounds::from_points(Aabb::new(Vec3::splat(-1.0), Vec3::ONE).corners()).unwrap();
+
+        let matrix = Mat4::from_scale_rotation_translation(
+            Vec3::new(1.0, 2.0, 1.0),
+            Quat::from_rotation_z(45f32.to_radians()),
+            Vec3::new(10.0, 0.0, 0.0),
+        );
+        let transformed = unit.transformed(&matrix);
+
+        // Every transformed corner is inside both volumes.
+        let margin = Vec3::splat(1e-4);
+        let aabb = Aabb::new(transformed.aabb.min - margin, transformed.aabb.max + margin);
+        for corner in unit.aabb.corners() {
+            let corner = matrix.transform_point3(corner);
+            assert!(aabb.contains(corner));
+            assert!(transformed.sphere.radius + 1e-4 >= corner.distance(transformed.sphere.center));
+        }
+        assert_near(transformed.aabb.center(), Vec3::new(10.0, 0.0, 0.0));
+        let half = (1.0 + 2.0) * 45f32.to_radians().cos();
+        assert_near(transformed.aabb.half_extents(), Vec3::new(half, half, 1.0));
+        assert!((transformed.sphere.radius - 2.0 * 3f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_union() {
+        let a = BoundingSphere::new(Vec3::ZERO, 1.0);
+        let b = BoundingSphere::new(Vec3::new(4.0, 0.0, 0.0), 1.0);
+        assert_eq!(
+            a.union(&b),
+            BoundingSphere::new(Vec3::new(2.0, 0.0, 0.0), 3.0)
+        );
+        let inner = BoundingSphere::new(Vec3::new(0.5, 0.0, 0.0), 0.25);
+        assert_eq!(a.union(&inner), a);
+        assert_eq!(inner.union(&a), a);
+
+        let boxes =
+            Aabb::new(Vec3::ZERO, Vec3::ONE).union(&Aabb::new(Vec3::splat(-1.0), Vec3::ZERO));
+        assert_eq!(boxes, Aabb::new(Vec3::splat(-1.0), Vec3::ONE));
+        assert!(boxes.intersects(&Aabb::new(Vec3::ONE, Vec3::splat(2.0))));
+        assert!(!boxes.intersects(&Aabb::new(Vec3::splat(1.5), Vec3::splat(2.0))));
+    }
+}
@@ -0,0 +1,467 @@
+use glam::{IVec2, IVec3, IVec4, Mat3, Mat4, Vec2, Vec3, Vec4};
+
+use crate::{
+    gl::{self, types::GLenum},
+    shader::reflection::{
+        float_components, float_matrix_columns, glsl_type_name, int_components, AttributeInfo,
+    },
+};
+
+/// What an attribute holds, which names the shader input it feeds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Semantic {
+    Position,
+    Normal,
+    TexCoords,
+    /// Tangent with the bitangent's handedness in w.
+    Tangent,
+    Color,
+    /// Indices of the joints moving a skinned vertex, read as integers.
+    Joints,
+    /// How much each of the `Joints` moves a skinned vertex.
+    Weights,
+    /// Any other input, by its GLSL name.
+    Custom(&'static str),
+}
+
+impl Semantic {
+    /// Name of the vertex shader input, following the `aPos` / `aNormal` convention.
+    pub fn attribute_name(self) -> &'static str {
+        match self {
+            Semantic::Position => "aPos",
+            Semantic::Normal => "aNormal",
+            Semantic::TexCoords => "aTexCoords",
+            Semantic::Tangent => "aTangent",
+            Semantic::Color => "aColor",
+            Semantic::Joints => "aJoints",
+            Semantic::Weights => "aWeights",
+            Semantic::Custom(name) => name,
+        }
+    }
+}
+
+/// How each component of an attribute is stored in the buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ComponentType {
+    F32,
+    /// Read as a float in 0..1.
+    U8Norm,
+    /// Read as a float in 0..1.
+    U16Norm,
+    /// Read as an integer, for `int` / `ivecN` inputs.
+    I32,
+}
+
+impl ComponentType {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            ComponentType::F32 => gl::FLOAT,
+            ComponentType::U8Norm => gl::UNSIGNED_BYTE,
+            ComponentType::U16Norm => gl::UNSIGNED_SHORT,
+            ComponentType::I32 => gl::INT,
+        }
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            ComponentType::U8Norm => 1,
+            ComponentType::U16Norm => 2,
+            ComponentType::F32 | ComponentType::I32 => 4,
+        }
+    }
+
+    pub fn normalized(self) -> bool {
+        matches!(self, ComponentType::U8Norm | ComponentType::U16Norm)
+    }
+
+    pub fn is_integer(self) -> bool {
+        self == ComponentType::I32
+    }
+}
+
+/// A Rust type that can be a field of a vertex struct.
+pub trait VertexComponent {
+    const COMPONENT_TYPE: ComponentType;
+    /// Components per column.
+    const COUNT: u32;
+    /// Matrices take one attribute location per column.
+    const COLUMNS: u32 = 1;
+}
+
+macro_rules! vertex_component {
+    ($($ty:ty => $component_type:ident, $count:expr;)+) => {
+        $(
+            impl VertexComponent for $ty {
+                const COMPONENT_TYPE: ComponentType = ComponentType::$component_type;
+                const COUNT: u32 = $count;
+            }
+        )+
+    };
+}
+
+vertex_component! {
+    f32 => F32, 1;
+    Vec2 => F32, 2;
+    Vec3 => F32, 3;
+    Vec4 => F32, 4;
+    i32 => I32, 1;
+    IVec2 => I32, 2;
+    IVec3 => I32, 3;
+    IVec4 => I32, 4;
+}
+
+impl VertexComponent for Mat3 {
+    const COMPONENT_TYPE: ComponentType = ComponentType::F32;
+    const COUNT: u32 = 3;
+    const COLUMNS: u32 = 3;
+}
+
+impl VertexComponent for Mat4 {
+    const COMPONENT_TYPE: ComponentType = ComponentType::F32;
+    const COUNT: u32 = 4;
+    const COLUMNS: u32 = 4;
+}
+
+impl<const N: usize> VertexComponent for [f32; N] {
+    const COMPONENT_TYPE: ComponentType = ComponentType::F32;
+    const COUNT: u32 = N as u32;
+}
+
+impl<const N: usize> VertexComponent for [u8; N] {
+    const COMPONENT_TYPE: ComponentType = ComponentType::U8Norm;
+    const COUNT: u32 = N as u32;
+}
+
+impl<const N: usize> VertexComponent for [u16; N] {
+    const COMPONENT_TYPE: ComponentType = ComponentType::U16Norm;
+    const COUNT: u32 = N as u32;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VertexAttribute {
+    pub semantic: Semantic,
+    pub component_type: ComponentType,
+    pub count: u32,
+    /// Consecutive columns of `count` components, more than one for matrices.
+    pub columns: u32,
+    /// Byte offset from the start of a vertex.
+    pub offset: u32,
+    /// Index of the buffer the attribute is read from.
+    pub binding: u32,
+}
+
+/// Where each attribute of a vertex lives, in one interleaved buffer or spread over several.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+    /// Byte stride of each buffer.
+    strides: Vec<i32>,
+    /// Instances each element of a buffer is used for, 0 for per-vertex buffers.
+    divisors: Vec<u32>,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start the next buffer, whose vertices are `stride` bytes apart. Attributes added after
+    /// this are read from it.
+    pub fn buffer(mut self, stride: usize) -> Self {
+        self.strides.push(stride as i32);
+        self.divisors.push(0);
+        self
+    }
+
+    /// Start the next buffer, which holds one element per instance instead of per vertex.
+    pub fn instance_buffer(mut self, stride: usize) -> Self {
+        self.strides.push(stride as i32);
+        self.divisors.push(1);
+        self
+    }
+
+    /// This layout followed by the buffers of `instances`, which become per-instance
+    /// buffers.
+    pub fn with_instances(mut self, instances: VertexLayout) -> Self {
+        let first_binding = self.strides.len() as u32;
+        self.attributes.extend(
+            instances
+                .attributes
+                .into_iter()
+                .map(|attribute| VertexAttribute {
+                    binding: attribute.binding + first_binding,
+                    ..attribute
+                }),
+        );
+        self.divisors
+            .extend(instances.divisors.iter().map(|divisor| (*divisor).max(1)));
+        self.strides.extend(instances.strides);
+        self
+    }
+
+    pub fn attribute(
+        self,
+        semantic: Semantic,
+        component_type: ComponentType,
+        count: u32,
+        offset: usize,
+    ) -> Self {
+        self.matrix_attribute(semantic, component_type, count, 1, offset)
+    }
+
+    /// An attribute of `columns` consecutive columns, like a `mat4` input.
+    pub fn matrix_attribute(
+        mut self,
+        semantic: Semantic,
+        component_type: ComponentType,
+        count: u32,
+        columns: u32,
+        offset: usize,
+    ) -> Self {
+        assert!(
+            !self.strides.is_empty(),
+            "Vertex attributes have to follow a buffer"
+        );
+        self.attributes.push(VertexAttribute {
+            semantic,
+            component_type,
+            count,
+            columns,
+            offset: offset as u32,
+            binding: self.strides.len() as u32 - 1,
+        });
+        self
+    }
+
+    /// Tightly packed float attributes interleaved in a single buffer.
+    pub fn interleaved_f32(attributes: &[(Semantic, u32)]) -> Self {
+        let stride = attributes
+            .iter()
+            .map(|(_, count)| *count as usize)
+            .sum::<usize>();
+        let mut layout = Self::new().buffer(stride * ComponentType::F32.size());
+        let mut offset = 0;
+        for (semantic, count) in attributes {
+            layout = layout.attribute(*semantic, ComponentType::F32, *count, offset);
+            offset += *count as usize * ComponentType::F32.size();
+        }
+        layout
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    pub fn buffer_count(&self) -> usize {
+        self.strides.len()
+    }
+
+    /// Byte stride of buffer `binding`.
+    pub fn stride(&self, binding: u32) -> i32 {
+        self.strides[binding as usize]
+    }
+
+    /// Instance divisor of buffer `binding`, 0 if it is read per vertex.
+    pub fn divisor(&self, binding: u32) -> u32 {
+        self.divisors[binding as usize]
+    }
+
+    /// Check the layout feeds every input in `attributes`, the active attributes of a program,
+    /// with the matching type. Layout attributes the program doesn't use are fine.
+    pub fn check<'a>(
+        &self,
+        attributes: impl IntoIterator<Item = &'a AttributeInfo>,
+    ) -> Result<(), String> {
+        for input in attributes {
+            // Built-ins like gl_VertexID aren't fed by buffers.
+            if input.name.starts_with("gl_") {
+                continue;
+            }
+            let Some(attribute) = self
+                .attributes
+                .iter()
+                .find(|attribute| attribute.semantic.attribute_name() == input.name)
+            else {
+                return Err(format!(
+                    "Attribute {} is missing from the vertex layout",
+                    input.name
+                ));
+            };
+
+            let shape = if attribute.component_type.is_integer() {
+                int_components(input.gl_type).map(|components| (components, 1))
+            } else {
+                float_components(input.gl_type)
+                    .map(|components| (components, 1))
+                    .or_else(|| float_matrix_columns(input.gl_type))
+            };
+            if shape != Some((attribute.count, attribute.columns)) {
+                let columns = match attribute.columns {
+                    1 => String::new(),
+                    columns => format!(" x {columns}"),
+                };
+                return Err(format!(
+                    "Attribute {} is a {}, not {}{columns} {:?} components",
+                    input.name,
+                    glsl_type_name(input.gl_type),
+                    attribute.count,
+                    attribute.component_type,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `#[repr(C)]` struct stored as is in a vertex buffer. Implement it with `vertex_struct!`.
+pub trait Vertex: Copy {
+    fn layout() -> VertexLayout;
+}
+
+/// The attribute for a field, with its type and count taken from the field's type.
+pub fn field_attribute<V, C: VertexComponent>(
+    layout: VertexLayout,
+    semantic: Semantic,
+    offset: usize,
+    _field: impl Fn(&V) -> &C,
+) -> VertexLayout {
+    layout.matrix_attribute(semantic, C::COMPONENT_TYPE, C::COUNT, C::COLUMNS, offset)
+}
+
+/// Implement `Vertex` for a `#[repr(C)]` struct, interleaving the listed fields in one buffer.
+/// Fields left out of the list are padding as far as the shader is concerned.
+///
+/// ```ignore
+/// vertex_struct!(ColorVertex { position: Semantic::Position, color: Semantic::Color });
+/// ```
+macro_rules! vertex_struct {
+    ($ty:ty { $($field:ident: $semantic:expr),+ $(,)? }) => {
+        impl $crate::mesh::layout::Vertex for $ty {
+            fn layout() -> $crate::mesh::layout::VertexLayout {
+                let layout = $crate::mesh::layout::VertexLayout::new()
+                    .buffer(std::mem::size_of::<$ty>());
+                $(
+                    let layout = $crate::mesh::layout::field_attribute(
+                        layout,
+                        $semantic,
+                        std::mem::offset_of!($ty, $field),
+                        |vertex: &$ty| &vertex.$field,
+                    );
+                )+
+                layout
+            }
+        }
+    };
+}
+pub(crate) use vertex_struct;
+
+/// Position, normal and texture coordinates, the layout of the cube data and loaded models.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StandardVertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub tex_coords: Vec2,
+}
+
+vertex_struct!(StandardVertex {
+    position: Semantic::Position,
+    normal: Semantic::Normal,
+    tex_coords: Semantic::TexCoords,
+});
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn input(name: &str, gl_type: GLenum) -> AttributeInfo {
+        AttributeInfo {
+            name: name.to_string(),
+            gl_type,
+            size: 1,
+            location: 0,
+        }
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct PackedVertex {
+        position: Vec3,
+        color: [u8; 4],
+        bone: i32,
+    }
+
+    vertex_struct!(PackedVertex {
+        position: Semantic::Position,
+        color: Semantic::Color,
+        bone: Semantic::Custom("aBone"),
+    });
+
+    #[test]
+    fn test_vertex_struct_layout() {
+        let layout = PackedVertex::layout();
+        assert_eq!(layout.stride(0), 20);
+        assert_eq!(
+            layout.attributes()[1],
+            VertexAttribute {
+                semantic: Semantic::Color,
+                component_type: ComponentType::U8Norm,
+                count: 4,
+                columns: 1,
+                offset: 12,
+                binding: 0,
+            }
+        );
+        assert_eq!(layout.attributes()[2].offset, 16);
+
+        assert_eq!(
+            StandardVertex::layout(),
+            VertexLayout::interleaved_f32(&[
+                (Semantic::Position, 3),
+                (Semantic::Normal, 3),
+                (Semantic::TexCoords, 2),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_separate_buffers() {
+        let layout = VertexLayout::new()
+            .buffer(12)
+            .attribute(Semantic::Position, ComponentType::F32, 3, 0)
+            .buffer(4)
+            .attribute(Semantic::Color, ComponentType::U8Norm, 4, 0);
+        assert_eq!(layout.buffer_count(), 2);
+        assert_eq!(layout.stride(1), 4);
+        assert_eq!(layout.attributes()[1].binding, 1);
+        assert_eq!(layout.divisor(1), 0);
+
+        let instanced = StandardVertex::layout().with_instances(layout);
+        assert_eq!(instanced.buffer_count(), 3);
+        assert_eq!(instanced.divisor(0), 0);
+        assert_eq!(instanced.divisor(2), 1);
+        assert_eq!(instanced.attributes()[4].binding, 2);
+    }
+
+    #[test]
+    fn test_check() {
+        let layout = PackedVertex::layout();
+        assert!(layout
+            .check(&[
+                input("aPos", gl::FLOAT_VEC3),
+                input("aColor", gl::FLOAT_VEC4),
+                input("aBone", gl::INT),
+                input("gl_VertexID", gl::INT),
+            ])
+            .is_ok());
+
+        assert_eq!(
+            layout.check(&[input("aNormal", gl::FLOAT_VEC3)]),
+            Err("Attribute aNormal is missing from the vertex layout".to_string())
+        );
+        assert_eq!(
+            layout.check(&[input("aBone", gl::FLOAT)]),
+            Err("Attribute aBone is a float, not 1 I32 components".to_string())
+        );
+    }
+}
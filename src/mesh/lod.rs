@@ -0,0 +1,219 @@
+use glam::Vec3;
+
+use super::bounds::BoundingSphere;
+
+/// When a level of detail is still good enough.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LodThreshold {
+    /// Up to this distance from the camera.
+    Distance(f32),
+    /// While the bounding sphere's diameter covers at least this fraction of the screen
+    /// height.
+    ScreenSize(f32),
+}
+
+impl LodThreshold {
+    /// `margin` makes the threshold more lenient when positive and stricter when negative, as
+    /// a fraction of it.
+    fn passes(&self, view: &LodView, margin: f32) -> bool {
+        match *self {
+            LodThreshold::Distance(distance) => view.distance <= distance * (1.0 + margin),
+            LodThreshold::ScreenSize(size) => view.screen_size >= size * (1.0 - margin),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LodLevel<M> {
+    pub mesh: M,
+    pub threshold: LodThreshold,
+}
+
+/// How an object is seen from the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodView {
+    pub distance: f32,
+    /// Fraction of the screen height the bounding sphere's diameter covers.
+    pub screen_size: f32,
+}
+
+impl LodView {
+    /// `fov` is the vertical field of view in degrees.
+    pub fn new(bounds: &BoundingSphere, camera_pos: Vec3, fov: f32) -> Self {
+        let distance = bounds.center.distance(camera_pos);
+        let half_height = (distance * (fov.to_radians() * 0.5).tan()).max(f32::EPSILON);
+        Self {
+            distance,
+            screen_size: bounds.radius / half_height,
+        }
+    }
+}
+
+/// The level of detail an object currently uses, kept between frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LodState {
+    level: usize,
+    /// The level faded out and how far along the fade is, from 0 to 1.
+    fade: Option<(usize, f32)>,
+}
+
+impl LodState {
+    pub fn level(&self) -> usize {
+        self.level
+    }
+}
+
+/// The levels to draw this frame. During a cross-fade both are drawn, each with a dither
+/// pattern the other fills in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodSelection {
+    pub level: usize,
+    /// The previous level and the fade progress, from 0 to 1. `level` covers that fraction
+    /// of the pixels and the previous level the rest.
+    pub fading_from: Option<(usize, f32)>,
+}
+
+/// Meshes of decreasing detail for one object, finest first.
+///
+/// Levels switch when the finest level whose threshold passes changes, with `hysteresis`
+/// keeping an object near a threshold from popping back and forth: a level is kept until it
+/// fails by that fraction of its threshold, and a finer one is only picked once it passes by
+/// as much.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LodGroup<M> {
+    levels: Vec<LodLevel<M>>,
+    hysteresis: f32,
+    /// Seconds a cross-fade between two levels takes. `None` switches at once.
+    fade_duration: Option<f32>,
+}
+
+impl<M> LodGroup<M> {
+    /// The last level is used whenever no threshold passes, so its threshold is ignored.
+    pub fn new(levels: Vec<LodLevel<M>>) -> Self {
+        assert!(!levels.is_empty(), "A LOD group needs at least one level");
+        Self {
+            levels,
+            hysteresis: 0.1,
+            fade_duration: None,
+        }
+    }
+
+    pub fn with_hysteresis(mut self, hysteresis: f32) -> Self {
+        self.hysteresis = hysteresis.max(0.0);
+        self
+    }
+
+    /// Cross-fade between levels over `seconds` instead of switching at once.
+    pub fn with_fade(mut self, seconds: f32) -> Self {
+        self.fade_duration = Some(seconds).filter(|seconds| *seconds > 0.0);
+        self
+    }
+
+    pub fn levels(&self) -> &[LodLevel<M>] {
+        &self.levels
+    }
+
+    pub fn mesh(&self, level: usize) -> &M {
+        &self.levels[level].mesh
+    }
+
+    /// Pick the level for `view`, advancing a running cross-fade by `delta_time` seconds.
+    pub fn select(&self, state: &mut LodState, view: &LodView, delta_time: f32) -> LodSelection {
+        let last = self.levels.len() - 1;
+        let mut level = state.level.min(last);
+        while level > 0
+            && self.levels[level - 1]
+                .threshold
+                .passes(view, -self.hysteresis)
+        {
+            level -= 1;
+        }
+        while level < last && !self.levels[level].threshold.passes(view, self.hysteresis) {
+            level += 1;
+        }
+
+        if level != state.level {
+            state.fade = self.fade_duration.map(|_| (state.level.min(last), 0.0));
+            state.level = level;
+        } else if let (Some((from, progress)), Some(duration)) = (state.fade, self.fade_duration) {
+            let progress = progress + delta_time / duration;
+            state.fade = (progress < 1.0).then_some((from, progress));
+        }
+
+        LodSelection {
+            level,
+            fading_from: state.fade,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn group() -> LodGroup<&'static str> {
+        LodGroup::new(vec![
+            LodLevel {
+                mesh: "high",
+                threshold: LodThreshold::Distance(10.0),
+            },
+            LodLevel {
+                mesh: "medium",
+                threshold: LodThreshold::ScreenSize(0.05),
+            },
+            LodLevel {
+                mesh: "low",
+                threshold: LodThreshold::Distance(f32::INFINITY),
+            },
+        ])
+    }
+
+    fn at(distance: f32) -> LodView {
+        LodView::new(
+            &BoundingSphere::new(Vec3::ZERO, 1.0),
+            Vec3::Z * distance,
+            90.0,
+        )
+    }
+
+    #[test]
+    fn test_screen_size() {
+        let view = at(2.0);
+        assert!((view.screen_size - 0.5).abs() < 1e-6);
+        assert_eq!(view.distance, 2.0);
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        let group = group();
+        let mut state = LodState::default();
+        let mut level_at = |distance| group.select(&mut state, &at(distance), 0.0).level;
+
+        assert_eq!(level_at(5.0), 0);
+        // Kept until 10% past the threshold.
+        assert_eq!(level_at(10.5), 0);
+        assert_eq!(level_at(11.5), 1);
+        // And only picked again once 10% within it.
+        assert_eq!(level_at(9.5), 1);
+        assert_eq!(level_at(8.5), 0);
+        // Screen size 0.05 is at distance 20, so the last level follows past 22.
+        assert_eq!(level_at(50.0), 2);
+        assert_eq!(level_at(0.0), 0);
+    }
+
+    #[test]
+    fn test_cross_fade() {
+        let group = group().with_fade(0.5);
+        let mut state = LodState::default();
+        group.select(&mut state, &at(1.0), 0.1);
+
+        let selection = group.select(&mut state, &at(12.0), 0.1);
+        assert_eq!(selection.level, 1);
+        assert_eq!(selection.fading_from, Some((0, 0.0)));
+        let selection = group.select(&mut state, &at(12.0), 0.25);
+        assert_eq!(selection.fading_from, Some((0, 0.5)));
+        let selection = group.select(&mut state, &at(12.0), 0.25);
+        assert_eq!(selection.fading_from, None);
+        assert_eq!(*group.mesh(state.level()), "medium");
+    }
+}
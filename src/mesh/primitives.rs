@@ -0,0 +1,381 @@
+use std::f32::consts::{PI, TAU};
+
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::gl::Gl;
+
+use super::{
+    layout::{vertex_struct, Semantic, Vertex},
+    VertexBuffer,
+};
+
+/// Floats per generated vertex: position, normal, texture coordinates and a tangent whose w
+/// is the handedness of the bitangent. The first 8 match the layout `Cube::with_geometry`
+/// expects.
+pub const PRIMITIVE_VERTEX_STRIDE: i32 = 12;
+
+/// One generated vertex, the layout of `Geometry::vertices`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PrimitiveVertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub tex_coords: Vec2,
+    pub tangent: Vec4,
+}
+
+vertex_struct!(PrimitiveVertex {
+    position: Semantic::Position,
+    normal: Semantic::Normal,
+    tex_coords: Semantic::TexCoords,
+    tangent: Semantic::Tangent,
+});
+
+/// Indexed triangles, counter-clockwise seen from the side the normals point to.
+#[derive(Clone, Debug, Default)]
+pub struct Geometry {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl Geometry {
+    pub fn vertex_buffer(&self, gl: &Gl) -> VertexBuffer {
+        VertexBuffer::with_indices(gl, &self.vertices, &self.indices, PrimitiveVertex::layout())
+    }
+
+    pub fn vertex_count(&self) -> u32 {
+        (self.vertices.len() / PRIMITIVE_VERTEX_STRIDE as usize) as u32
+    }
+
+    /// `bitangent` only decides the handedness stored in the tangent's w.
+    fn push_vertex(
+        &mut self,
+        position: Vec3,
+        normal: Vec3,
+        tex_coords: Vec2,
+        tangent: Vec3,
+        bitangent: Vec3,
+    ) -> u32 {
+        let index = self.vertex_count();
+        let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        self.vertices.extend(position.to_array());
+        self.vertices.extend(normal.to_array());
+        self.vertices.extend(tex_coords.to_array());
+        self.vertices.extend(tangent.to_array());
+        self.vertices.push(handedness);
+        index
+    }
+
+    /// Two triangles for a quad given counter-clockwise from its lower left corner.
+    fn push_quad(&mut self, a: u32, b: u32, c: u32, d: u32) {
+        self.indices.extend([a, b, c, a, c, d]);
+    }
+
+    /// A `columns + 1` by `rows + 1` lattice of vertices from `vertex(u, v)`, joined by quads.
+    /// A collapsed bottom or top row meets in a single point, e.g. at a pole, so it only gets
+    /// one triangle per quad.
+    fn push_lattice(
+        &mut self,
+        columns: u32,
+        rows: u32,
+        mut vertex: impl FnMut(&mut Self, f32, f32) -> u32,
+        collapsed_bottom: bool,
+        collapsed_top: bool,
+    ) {
+        let first = self.vertex_count();
+        for row in 0..=rows {
+            for column in 0..=columns {
+                vertex(
+                    self,
+                    column as f32 / columns as f32,
+                    row as f32 / rows as f32,
+                );
+            }
+        }
+
+        let index = |column: u32, row: u32| first + row * (columns + 1) + column;
+        for row in 0..rows {
+            for column in 0..columns {
+                let a = index(column, row);
+                let b = index(column + 1, row);
+                let c = index(column + 1, row + 1);
+                let d = index(column, row + 1);
+                if row == 0 && collapsed_bottom {
+                    self.indices.extend([a, c, d]);
+                } else if row == rows - 1 && collapsed_top {
+                    self.indices.extend([a, b, c]);
+                } else {
+                    self.push_quad(a, b, c, d);
+                }
+            }
+        }
+    }
+
+    /// A disc in the XZ plane at `y`, facing up or down.
+    fn push_cap(&mut self, radius: f32, y: f32, segments: u32, up: bool) {
+        let normal = if up { Vec3::Y } else { Vec3::NEG_Y };
+        // Seen from outside, +X is right and the far edge is up in texture space.
+        let bitangent = if up { Vec3::NEG_Z } else { Vec3::Z };
+        let tex_coords = |position: Vec3| {
+            Vec2::new(
+                0.5 + 0.5 * position.x / radius,
+                0.5 + 0.5 * position.dot(bitangent) / radius,
+            )
+        };
+
+        let center = Vec3::new(0.0, y, 0.0);
+        let center_index = self.push_vertex(center, normal, tex_coords(center), Vec3::X, bitangent);
+        let rim = (0..=segments)
+            .map(|segment| {
+                let angle = segment as f32 / segments as f32 * TAU;
+                let position = Vec3::new(radius * angle.sin(), y, radius * angle.cos());
+                self.push_vertex(position, normal, tex_coords(position), Vec3::X, bitangent)
+            })
+            .collect::<Vec<_>>();
+
+        for pair in rim.windows(2) {
+            if up {
+                self.indices.extend([center_index, pair[0], pair[1]]);
+            } else {
+                self.indices.extend([center_index, pair[1], pair[0]]);
+            }
+        }
+    }
+}
+
+/// Direction around the Y axis for `angle`, 0 pointing to +Z and a quarter turn to +X.
+fn around_y(angle: f32) -> (Vec3, Vec3) {
+    let radial = Vec3::new(angle.sin(), 0.0, angle.cos());
+    let tangent = Vec3::new(angle.cos(), 0.0, -angle.sin());
+    (radial, tangent)
+}
+
+/// A UV sphere centered on the origin. `segments` go around the Y axis and `rings` from pole
+/// to pole.
+pub fn sphere(radius: f32, segments: u32, rings: u32) -> Geometry {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+    let mut geometry = Geometry::default();
+    geometry.push_lattice(
+        segments,
+        rings,
+        |geometry, u, v| {
+            let (radial, tangent) = around_y(u * TAU);
+            // From the south pole up.
+            let polar = PI * (1.0 - v);
+            let normal = radial * polar.sin() + Vec3::Y * polar.cos();
+            let bitangent = radial * -polar.cos() + Vec3::Y * polar.sin();
+            geometry.push_vertex(normal * radius, normal, Vec2::new(u, v), tangent, bitangent)
+        },
+        true,
+        true,
+    );
+    geometry
+}
+
+/// A single quad in the XZ plane facing +Y.
+pub fn plane(width: f32, depth: f32) -> Geometry {
+    grid(width, depth, 1, 1)
+}
+
+/// A plane in the XZ plane facing +Y, split into `columns` along X and `rows` along Z. The
+/// texture covers it once.
+pub fn grid(width: f32, depth: f32, columns: u32, rows: u32) -> Geometry {
+    let (columns, rows) = (columns.max(1), rows.max(1));
+    let mut geometry = Geometry::default();
+    geometry.push_lattice(
+        columns,
+        rows,
+        |geometry, u, v| {
+            let position = Vec3::new((u - 0.5) * width, 0.0, (0.5 - v) * depth);
+            geometry.push_vertex(position, Vec3::Y, Vec2::new(u, v), Vec3::X, Vec3::NEG_Z)
+        },
+        false,
+        false,
+    );
+    geometry
+}
+
+/// A capped cylinder around the Y axis, centered on the origin. `rings` split the side along
+/// its height.
+pub fn cylinder(radius: f32, height: f32, segments: u32, rings: u32) -> Geometry {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let mut geometry = Geometry::default();
+    geometry.push_lattice(
+        segments,
+        rings,
+        |geometry, u, v| {
+            let (radial, tangent) = around_y(u * TAU);
+            let position = radial * radius + Vec3::Y * (v - 0.5) * height;
+            geometry.push_vertex(position, radial, Vec2::new(u, v), tangent, Vec3::Y)
+        },
+        false,
+        false,
+    );
+    geometry.push_cap(radius, -0.5 * height, segments, false);
+    geometry.push_cap(radius, 0.5 * height, segments, true);
+    geometry
+}
+
+/// A cone around the Y axis with its base centered at `-height / 2` and its apex at
+/// `height / 2`.
+pub fn cone(radius: f32, height: f32, segments: u32, rings: u32) -> Geometry {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let mut geometry = Geometry::default();
+    geometry.push_lattice(
+        segments,
+        rings,
+        |geometry, u, v| {
+            let (radial, tangent) = around_y(u * TAU);
+            let position = radial * radius * (1.0 - v) + Vec3::Y * (v - 0.5) * height;
+            let normal = (radial * height + Vec3::Y * radius).normalize();
+            let bitangent = (Vec3::Y * height - radial * radius).normalize();
+            geometry.push_vertex(position, normal, Vec2::new(u, v), tangent, bitangent)
+        },
+        false,
+        true,
+    );
+    geometry.push_cap(radius, -0.5 * height, segments, false);
+    geometry
+}
+
+/// A torus around the Y axis. `segments` go around the Y axis and `rings` around the tube.
+pub fn torus(major_radius: f32, minor_radius: f32, segments: u32, rings: u32) -> Geometry {
+    let (segments, rings) = (segments.max(3), rings.max(3));
+    let mut geometry = Geometry::default();
+    geometry.push_lattice(
+        segments,
+        rings,
+        |geometry, u, v| {
+            let (radial, tangent) = around_y(u * TAU);
+            // Starting at the outer equator, going over the top.
+            let tube = v * TAU;
+            let normal = radial * tube.cos() + Vec3::Y * tube.sin();
+            let bitangent = radial * -tube.sin() + Vec3::Y * tube.cos();
+            let position = radial * major_radius + normal * minor_radius;
+            geometry.push_vertex(position, normal, Vec2::new(u, v), tangent, bitangent)
+        },
+        false,
+        false,
+    );
+    geometry
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const STRIDE: usize = PRIMITIVE_VERTEX_STRIDE as usize;
+
+    fn attribute(geometry: &Geometry, index: u32, offset: usize) -> Vec3 {
+        Vec3::from_slice(&geometry.vertices[index as usize * STRIDE + offset..])
+    }
+
+    fn check_vertices(geometry: &Geometry) {
+        assert_eq!(geometry.vertices.len() % STRIDE, 0);
+        for vertex in geometry.vertices.chunks_exact(STRIDE) {
+            let normal = Vec3::from_slice(&vertex[3..6]);
+            let tangent = Vec3::from_slice(&vertex[8..11]);
+            assert!((normal.length() - 1.0).abs() < 1e-5, "{normal}");
+            assert!((tangent.length() - 1.0).abs() < 1e-5, "{tangent}");
+            assert!(normal.dot(tangent).abs() < 1e-5);
+            assert!(vertex[11] == 1.0 || vertex[11] == -1.0);
+        }
+    }
+
+    /// Every triangle faces the way its vertex normals point.
+    fn check_winding(geometry: &Geometry) {
+        for triangle in geometry.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| attribute(geometry, triangle[i], 0));
+            let face_normal = (b - a).cross(c - a);
+            assert!(
+                face_normal.length() > 1e-7,
+                "degenerate triangle {triangle:?}"
+            );
+
+            let normal = triangle
+                .iter()
+                .map(|index| attribute(geometry, *index, 3))
+                .sum::<Vec3>();
+            assert!(
+                face_normal.dot(normal) > 0.0,
+                "{triangle:?} is wound backwards"
+            );
+        }
+    }
+
+    /// After welding vertices at the same position, every edge is shared by exactly two
+    /// triangles that traverse it in opposite directions.
+    fn check_closed(geometry: &Geometry) {
+        let mut welded = HashMap::new();
+        let ids = (0..geometry.vertex_count())
+            .map(|index| {
+                let key = (attribute(geometry, index, 0) * 1e4)
+                    .round()
+                    .as_ivec3()
+                    .to_array();
+                let next_id = welded.len();
+                *welded.entry(key).or_insert(next_id)
+            })
+            .collect::<Vec<_>>();
+
+        let mut edges = HashMap::new();
+        for triangle in geometry.indices.chunks_exact(3) {
+            for i in 0..3 {
+                let edge = (
+                    ids[triangle[i] as usize],
+                    ids[triangle[(i + 1) % 3] as usize],
+                );
+                *edges.entry(edge).or_insert(0) += 1;
+            }
+        }
+        for ((from, to), count) in &edges {
+            assert_eq!(*count, 1, "edge {from}-{to} is used {count} times");
+            assert_eq!(
+                edges.get(&(*to, *from)),
+                Some(&1),
+                "edge {from}-{to} is open"
+            );
+        }
+    }
+
+    #[test]
+    fn test_closed_primitives() {
+        for geometry in [
+            sphere(1.0, 16, 8),
+            cylinder(0.5, 2.0, 12, 3),
+            cone(1.0, 1.5, 10, 2),
+            torus(1.0, 0.25, 24, 12),
+        ] {
+            check_vertices(&geometry);
+            check_winding(&geometry);
+            check_closed(&geometry);
+        }
+    }
+
+    #[test]
+    fn test_grid() {
+        let geometry = grid(2.0, 4.0, 4, 2);
+        check_vertices(&geometry);
+        check_winding(&geometry);
+        assert_eq!(geometry.vertex_count(), 15);
+        assert_eq!(geometry.indices.len(), 4 * 2 * 6);
+
+        let corner = attribute(&geometry, 0, 0);
+        assert_eq!(corner, Vec3::new(-1.0, 0.0, 2.0));
+        assert_eq!(plane(1.0, 1.0).indices.len(), 6);
+    }
+
+    #[test]
+    fn test_sphere_radius() {
+        let geometry = sphere(2.0, 8, 6);
+        for index in 0..geometry.vertex_count() {
+            assert!((attribute(&geometry, index, 0).length() - 2.0).abs() < 1e-5);
+        }
+    }
+}
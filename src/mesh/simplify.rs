@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use glam::{DVec3, Vec3};
+
+/// Triangles of a simplified mesh, indexing the vertices of the original.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Simplified {
+    pub indices: Vec<u32>,
+    /// Conservative estimate of the largest distance between the simplified and the original
+    /// surface, in the units of the positions.
+    pub error: f32,
+}
+
+/// Sum of squared distances to a set of planes, as a symmetric 4x4 matrix.
+#[derive(Clone, Copy, Debug, Default)]
+struct Quadric {
+    aa: f64,
+    ab: f64,
+    ac: f64,
+    ad: f64,
+    bb: f64,
+    bc: f64,
+    bd: f64,
+    cc: f64,
+    cd: f64,
+    dd: f64,
+}
+
+impl Quadric {
+    /// The plane through `point` facing `normal`, which has to be normalized.
+    fn from_plane(normal: DVec3, point: DVec3) -> Self {
+        let (a, b, c) = (normal.x, normal.y, normal.z);
+        let d = -normal.dot(point);
+        Self {
+            aa: a * a,
+            ab: a * b,
+            ac: a * c,
+            ad: a * d,
+            bb: b * b,
+            bc: b * c,
+            bd: b * d,
+            cc: c * c,
+            cd: c * d,
+            dd: d * d,
+        }
+    }
+
+    fn add(&self, other: &Quadric) -> Quadric {
+        Quadric {
+            aa: self.aa + other.aa,
+            ab: self.ab + other.ab,
+            ac: self.ac + other.ac,
+            ad: self.ad + other.ad,
+            bb: self.bb + other.bb,
+            bc: self.bc + other.bc,
+            bd: self.bd + other.bd,
+            cc: self.cc + other.cc,
+            cd: self.cd + other.cd,
+            dd: self.dd + other.dd,
+        }
+    }
+
+    fn error(&self, point: DVec3) -> f64 {
+        let (x, y, z) = (point.x, point.y, point.z);
+        let error = self.aa * x * x
+            + 2.0 * (self.ab * x * y + self.ac * x * z + self.ad * x)
+            + self.bb * y * y
+            + 2.0 * (self.bc * y * z + self.bd * y)
+            + self.cc * z * z
+            + 2.0 * self.cd * z
+            + self.dd;
+        error.max(0.0)
+    }
+}
+
+/// Smallest cosine between a triangle's normal before and after a collapse. Anything less is
+/// a flipped triangle or a sliver.
+const MIN_NORMAL_ALIGNMENT: f64 = 0.25;
+
+/// Reduce indexed triangles to at most `target_index_count` indices where possible, by
+/// collapsing edges in the order of their quadric error metric (Garland and Heckbert).
+///
+/// Vertices only ever collapse onto other vertices, so the result indexes the original
+/// vertex data and every attribute stays valid. Vertices on open borders, which includes UV
+/// and normal seams where vertices are split, never move, so the outline and seams stay
+/// intact. Collapses that would flip a triangle are skipped, so the target may not be
+/// reached.
+pub fn simplify(positions: &[Vec3], indices: &[u32], target_index_count: usize) -> Simplified {
+    let positions = positions
+        .iter()
+        .map(|position| position.as_dvec3())
+        .collect::<Vec<_>>();
+    let mut triangles = indices
+        .chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+        .filter(|triangle| !is_degenerate(triangle))
+        .collect::<Vec<_>>();
+
+    let mut quadrics = vec![Quadric::default(); positions.len()];
+    for triangle in &triangles {
+        let [p0, p1, p2] = triangle.map(|i| positions[i as usize]);
+        if let Some(normal) = (p1 - p0).cross(p2 - p0).try_normalize() {
+            let plane = Quadric::from_plane(normal, p0);
+            for i in triangle {
+                quadrics[*i as usize] = quadrics[*i as usize].add(&plane);
+            }
+        }
+    }
+    let locked = border_vertices(&triangles, positions.len());
+
+    let target_triangles = target_index_count / 3;
+    let mut max_error: f64 = 0.0;
+    while triangles.len() > target_triangles {
+        let mut adjacent = vec![vec![]; positions.len()];
+        for (t, triangle) in triangles.iter().enumerate() {
+            for i in triangle {
+                adjacent[*i as usize].push(t);
+            }
+        }
+
+        let mut candidates = vec![];
+        for triangle in &triangles {
+            for corner in 0..3 {
+                let (a, b) = (triangle[corner], triangle[(corner + 1) % 3]);
+                for (from, to) in [(a, b), (b, a)] {
+                    if !locked[from as usize] {
+                        let quadric = quadrics[from as usize].add(&quadrics[to as usize]);
+                        candidates.push((quadric.error(positions[to as usize]), from, to));
+                    }
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // Collapses in one pass never share a triangle, so `adjacent` stays valid for them.
+        let mut touched = vec![false; positions.len()];
+        let mut remap = HashMap::new();
+        let mut remaining = triangles.len();
+        for (error, from, to) in candidates {
+            if remaining <= target_triangles {
+                break;
+            }
+            let (from, to) = (from as usize, to as usize);
+            if touched[from] || touched[to] {
+                continue;
+            }
+            let around = &adjacent[from];
+            if flips(&positions, &triangles, around, from, to) {
+                continue;
+            }
+
+            for t in around {
+                for i in triangles[*t] {
+                    touched[i as usize] = true;
+                }
+            }
+            remaining -= around
+                .iter()
+                .filter(|t| triangles[**t].contains(&(to as u32)))
+                .count();
+            quadrics[to] = quadrics[to].add(&quadrics[from]);
+            remap.insert(from as u32, to as u32);
+            max_error = max_error.max(error);
+        }
+        if remap.is_empty() {
+            break;
+        }
+
+        triangles = triangles
+            .into_iter()
+            .map(|triangle| triangle.map(|i| remap.get(&i).copied().unwrap_or(i)))
+            .filter(|triangle| !is_degenerate(triangle))
+            .collect();
+    }
+
+    Simplified {
+        indices: triangles.into_iter().flatten().collect(),
+        error: max_error.sqrt() as f32,
+    }
+}
+
+fn is_degenerate(triangle: &[u32; 3]) -> bool {
+    triangle[0] == triangle[1] || triangle[1] == triangle[2] || triangle[2] == triangle[0]
+}
+
+/// Vertices on an edge only one triangle uses.
+fn border_vertices(triangles: &[[u32; 3]], vertex_count: usize) -> Vec<bool> {
+    let mut edges: HashMap<(u32, u32), u32> = HashMap::new();
+    for triangle in triangles {
+        for corner in 0..3 {
+            let (a, b) = (triangle[corner], triangle[(corner + 1) % 3]);
+            *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+
+    let mut border = vec![false; vertex_count];
+    for ((a, b), count) in edges {
+        if count == 1 {
+            border[a as usize] = true;
+            border[b as usize] = true;
+        }
+    }
+    border
+}
+
+/// Whether moving `from` onto `to` turns any of the triangles `around` it over or into a
+/// sliver. Triangles with both collapse away.
+fn flips(
+    positions: &[DVec3],
+    triangles: &[[u32; 3]],
+    around: &[usize],
+    from: usize,
+    to: usize,
+) -> bool {
+    around.iter().any(|t| {
+        let triangle = triangles[*t];
+        if triangle.contains(&(to as u32)) {
+            return false;
+        }
+        let before = triangle.map(|i| positions[i as usize]);
+        let after = triangle.map(|i| {
+            if i as usize == from {
+                positions[to]
+            } else {
+                positions[i as usize]
+            }
+        });
+        let normal = |[p0, p1, p2]: [DVec3; 3]| (p1 - p0).cross(p2 - p0);
+        let (before, after) = (normal(before), normal(after));
+        before.dot(after) <= MIN_NORMAL_ALIGNMENT * before.length() * after.length()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mesh::primitives::{self, Geometry, PRIMITIVE_VERTEX_STRIDE};
+
+    fn positions(geometry: &Geometry) -> Vec<Vec3> {
+        geometry
+            .vertices
+            .chunks_exact(PRIMITIVE_VERTEX_STRIDE as usize)
+            .map(|vertex| Vec3::from_slice(&vertex[..3]))
+            .collect()
+    }
+
+    #[test]
+    fn test_flat_grid() {
+        let grid = primitives::grid(4.0, 4.0, 8, 8);
+        let simplified = simplify(&positions(&grid), &grid.indices, 0);
+
+        // The interior of a plane collapses without error, only the locked border is left.
+        assert!(simplified.indices.len() < grid.indices.len() / 2);
+        assert!(simplified.error < 1e-4);
+        let border = border_vertices(
+            &grid
+                .indices
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect::<Vec<_>>(),
+            positions(&grid).len(),
+        );
+        assert!(simplified.indices.iter().all(|i| border[*i as usize]));
+    }
+
+    #[test]
+    fn test_sphere() {
+        let sphere = primitives::sphere(1.0, 32, 16);
+        let positions = positions(&sphere);
+        let half = simplify(&positions, &sphere.indices, sphere.indices.len() / 2);
+        let quarter = simplify(&positions, &sphere.indices, sphere.indices.len() / 4);
+
+        assert!(half.indices.len() <= sphere.indices.len() / 2);
+        assert!(quarter.indices.len() < half.indices.len());
+        assert!(half.error > 0.0 && half.error <= quarter.error && quarter.error < 1.0);
+
+        // None turned inside out.
+        for triangle in quarter.indices.chunks_exact(3) {
+            let [p0, p1, p2] = [0, 1, 2].map(|i| positions[triangle[i] as usize]);
+            let normal = (p1 - p0).cross(p2 - p0);
+            assert!(normal.dot(p0 + p1 + p2) >= 0.0);
+        }
+    }
+}
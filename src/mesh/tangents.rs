@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use glam::{Vec2, Vec3, Vec4};
+
+use super::primitives::PRIMITIVE_VERTEX_STRIDE;
+
+/// Floats per vertex of interleaved positions, normals and texture coordinates.
+const STANDARD_VERTEX_STRIDE: usize = 8;
+
+/// Indexed triangles as MikkTSpace reads them, collecting a tangent per corner.
+struct Triangles<'a> {
+    positions: &'a [Vec3],
+    normals: &'a [Vec3],
+    tex_coords: &'a [Vec2],
+    indices: &'a [u32],
+    tangents: Vec<Vec4>,
+}
+
+impl Triangles<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> usize {
+        self.indices[face * 3 + vert] as usize
+    }
+}
+
+impl bevy_mikktspace::Geometry for Triangles<'_> {
+    fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.positions[self.vertex(face, vert)].to_array()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.normals[self.vertex(face, vert)].to_array()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.tex_coords[self.vertex(face, vert)].to_array()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = Vec4::from_array(tangent);
+    }
+}
+
+/// MikkTSpace tangents of indexed triangles, one for each entry of `indices`, so maps baked by
+/// other tools line up. w is the handedness, with the bitangent along +v being
+/// `w * normal.cross(tangent)`.
+///
+/// Corners sharing a vertex can get different tangents, e.g. at mirrored UV seams, so the
+/// vertex has to be split for them, see `with_tangents`. Shaders should rebuild the bitangent
+/// per fragment from the interpolated, unnormalized normal and tangent.
+pub fn generate_tangents(
+    positions: &[Vec3],
+    normals: &[Vec3],
+    tex_coords: &[Vec2],
+    indices: &[u32],
+) -> Vec<Vec4> {
+    let fallback = |i: &u32| {
+        let normal = normals[*i as usize].normalize_or_zero();
+        normal.any_orthonormal_vector().extend(1.0)
+    };
+    let mut triangles = Triangles {
+        positions,
+        normals,
+        tex_coords,
+        indices: &indices[..indices.len() / 3 * 3],
+        tangents: indices.iter().map(fallback).collect(),
+    };
+    // Only fails without any triangles, which leaves nothing to fall back from.
+    bevy_mikktspace::generate_tangents(&mut triangles);
+    triangles.tangents
+}
+
+/// Append generated tangents to vertices of interleaved positions, normals and texture
+/// coordinates, giving the layout of `primitives::PrimitiveVertex`. Vertices whose corners got
+/// different tangents are split, so the indices come back along with the vertices.
+pub fn with_tangents(vertices: &[f32], indices: &[u32]) -> (Vec<f32>, Vec<u32>) {
+    let vertex = |v: &[f32], offset: usize| Vec3::from_slice(&v[offset..offset + 3]);
+    let chunks = vertices.chunks_exact(STANDARD_VERTEX_STRIDE);
+    let positions = chunks.clone().map(|v| vertex(v, 0)).collect::<Vec<_>>();
+    let normals = chunks.clone().map(|v| vertex(v, 3)).collect::<Vec<_>>();
+    let tex_coords = chunks
+        .clone()
+        .map(|v| Vec2::from_slice(&v[6..8]))
+        .collect::<Vec<_>>();
+    let tangents = generate_tangents(&positions, &normals, &tex_coords, indices);
+
+    let mut result = Vec::with_capacity(positions.len() * PRIMITIVE_VERTEX_STRIDE as usize);
+    let mut split = HashMap::new();
+    let indices = indices
+        .iter()
+        .zip(tangents)
+        .map(|(&index, tangent)| {
+            let key = (index, tangent.to_array().map(f32::to_bits));
+            *split.entry(key).or_insert_with(|| {
+                let start = index as usize * STANDARD_VERTEX_STRIDE;
+                result.extend_from_slice(&vertices[start..start + STANDARD_VERTEX_STRIDE]);
+                result.extend(tangent.to_array());
+                (result.len() / PRIMITIVE_VERTEX_STRIDE as usize - 1) as u32
+            })
+        })
+        .collect();
+    (result, indices)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mesh::primitives;
+
+    fn quad(tex_coords: [Vec2; 4]) -> Vec<Vec4> {
+        let positions = [
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(-1.0, 1.0, 0.0),
+        ];
+        generate_tangents(&positions, &[Vec3::Z; 4], &tex_coords, &[0, 1, 2, 2, 3, 0])
+    }
+
+    #[test]
+    fn test_quad_tangents() {
+        let tex_coords = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+        for tangent in quad(tex_coords) {
+            assert!(tangent.abs_diff_eq(Vec4::new(1.0, 0.0, 0.0, 1.0), 1e-6));
+        }
+
+        // Mirrored along u, the tangent flips and so does the handedness, keeping +v up.
+        let mirrored = tex_coords.map(|uv| Vec2::new(1.0 - uv.x, uv.y));
+        for tangent in quad(mirrored) {
+            assert!(tangent.abs_diff_eq(Vec4::new(-1.0, 0.0, 0.0, -1.0), 1e-6));
+        }
+    }
+
+    #[test]
+    fn test_degenerate_tex_coords() {
+        for tangent in quad([Vec2::ZERO; 4]) {
+            assert_eq!(tangent.truncate().dot(Vec3::Z), 0.0);
+            assert!(tangent.truncate().is_normalized());
+        }
+    }
+
+    #[test]
+    fn test_matches_generated_tangents() {
+        let grid = primitives::grid(2.0, 2.0, 3, 3);
+        let stride = PRIMITIVE_VERTEX_STRIDE as usize;
+        let standard = grid
+            .vertices
+            .chunks_exact(stride)
+            .flat_map(|v| v[..STANDARD_VERTEX_STRIDE].to_vec())
+            .collect::<Vec<_>>();
+        let (generated, indices) = with_tangents(&standard, &grid.indices);
+        // Nothing to split on a flat grid.
+        assert_eq!(generated.len(), grid.vertices.len());
+        for (a, b) in indices.iter().zip(&grid.indices) {
+            let a = &generated[*a as usize * stride..][..stride];
+            let b = &grid.vertices[*b as usize * stride..][..stride];
+            for (a, b) in a.iter().zip(b) {
+                assert!((a - b).abs() < 1e-5, "{a} != {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_mirrored_seam_is_split() {
+        // Two quads side by side, with u running back from the middle column to mirror the
+        // right one.
+        let mut vertices = vec![];
+        for (y, v) in [(0.0, 0.0), (1.0, 1.0)] {
+            for (x, u) in [(-1.0, 0.0), (0.0, 1.0), (1.0, 0.0)] {
+                vertices.extend([x, y, 0.0, 0.0, 0.0, 1.0, u, v]);
+            }
+        }
+        let indices = [0, 1, 4, 4, 3, 0, 1, 2, 5, 5, 4, 1];
+        let (generated, split) = with_tangents(&vertices, &indices);
+
+        // Each half of the shared middle column gets its own copy.
+        let stride = PRIMITIVE_VERTEX_STRIDE as usize;
+        assert_eq!(generated.len(), 8 * stride);
+        let tangent = |corner: usize| {
+            Vec4::from_slice(&generated[split[corner] as usize * stride + 8..][..4])
+        };
+        for corner in 0..6 {
+            assert!(tangent(corner).abs_diff_eq(Vec4::new(1.0, 0.0, 0.0, 1.0), 1e-5));
+        }
+        for corner in 6..12 {
+            assert!(tangent(corner).abs_diff_eq(Vec4::new(-1.0, 0.0, 0.0, -1.0), 1e-5));
+        }
+    }
+}
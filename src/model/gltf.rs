@@ -0,0 +1,1143 @@
+use std::collections::HashMap;
+
+use ::gltf::{
+    animation::{util::ReadOutputs, Interpolation as GltfInterpolation},
+    camera::Projection,
+    image::Format,
+    khr_lights_punctual::Kind,
+    mesh::Mode,
+    texture::{MagFilter, MinFilter, WrappingMode},
+    Document,
+};
+use glam::{Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
+use image::{DynamicImage, ImageBuffer};
+
+use crate::{
+    animation::{
+        clip::{Channel, Clip, Interpolation, Keyframes},
+        skeleton::Skeleton,
+        skin::{SkinnedVertex, MAX_INFLUENCES, MAX_JOINTS},
+    },
+    assets::{self, AssetSource},
+    camera::Camera,
+    gl::{self, types::GLenum, Gl},
+    mesh::{
+        layout::{StandardVertex, Vertex},
+        primitives::{PrimitiveVertex, PRIMITIVE_VERTEX_STRIDE},
+        tangents,
+    },
+    object::{
+        cube::{Cube, Material},
+        light::LightAttributes,
+    },
+    renderer::{
+        asset_cache::AssetCache,
+        texture::{TextureManager, TextureSampler},
+    },
+    scene::Transform,
+    shader::{cache::ShaderCache, ShaderDefines},
+};
+
+/// Floats per vertex of imported primitives: position, normal, texture coordinates.
+pub const GLTF_VERTEX_STRIDE: i32 = 8;
+
+/// Reflectance of dielectrics, the specular color of non metals.
+const DIELECTRIC_SPECULAR: f32 = 0.04;
+
+/// A glTF 2.0 file converted to the crate's types. Indices between the parts are the ones
+/// of the file.
+pub struct GltfScene {
+    pub nodes: Vec<GltfNode>,
+    /// Nodes of the default scene that have no parent.
+    pub roots: Vec<usize>,
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub textures: Vec<GltfTexture>,
+    pub cameras: Vec<GltfCamera>,
+    pub lights: Vec<GltfLight>,
+    pub skins: Vec<GltfSkin>,
+}
+
+pub struct GltfNode {
+    pub name: String,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+    pub light: Option<usize>,
+    pub skin: Option<usize>,
+}
+
+pub struct GltfMesh {
+    pub name: String,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+/// Indexed triangles laid out like `GLTF_VERTEX_STRIDE` describes.
+pub struct GltfPrimitive {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+    /// The `TANGENT`s of the file, one per vertex, which its normal maps were baked with.
+    pub tangents: Option<Vec<Vec4>>,
+    /// The `JOINTS_0` and `WEIGHTS_0` of each vertex, the joints named by their place in the
+    /// skin of the node drawing the primitive.
+    pub influences: Option<Vec<[(usize, f32); MAX_INFLUENCES]>>,
+    pub material: Option<usize>,
+}
+
+/// A metallic-roughness material approximated by the lit object shader's material.
+pub struct GltfMaterial {
+    pub name: String,
+    pub material: Material,
+    pub base_color_texture: Option<usize>,
+    /// A tangent space normal map, whose red and green are +u and +v.
+    pub normal_texture: Option<usize>,
+}
+
+pub struct GltfTexture {
+    pub image: DynamicImage,
+    pub sampler: TextureSampler,
+}
+
+pub struct GltfCamera {
+    /// Vertical field of view in degrees.
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+pub struct GltfLight {
+    pub name: String,
+    pub attrs: LightAttributes,
+}
+
+/// The joints of a skin, with the animations moving them.
+pub struct GltfSkin {
+    pub name: String,
+    /// A joint for every joint node and the nodes above them, whose transforms move the joints
+    /// as well. The skinning matrices of the skeleton are in world space.
+    pub skeleton: Skeleton,
+    /// The skeleton joint of each joint of the file's skin, which the vertices name.
+    pub joints: Vec<usize>,
+    /// The animations moving any node of the skeleton.
+    pub clips: Vec<Clip>,
+}
+
+impl GltfNode {
+    pub fn local_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+impl GltfSkin {
+    /// The vertices of `primitive` following the skeleton. Its positions and normals are in
+    /// the bind pose, where the skinning matrices are the identity.
+    pub fn vertices(&self, primitive: &GltfPrimitive) -> Result<Vec<SkinnedVertex>, String> {
+        let influences = primitive
+            .influences
+            .as_ref()
+            .ok_or("primitive has no joints and weights")?;
+        primitive
+            .vertices
+            .chunks_exact(GLTF_VERTEX_STRIDE as usize)
+            .zip(influences)
+            .map(|(vertex, influences)| {
+                let influences = influences
+                    .iter()
+                    .filter(|(_, weight)| *weight > 0.0)
+                    .map(|(joint, weight)| {
+                        let joint = self.joints.get(*joint).ok_or_else(|| {
+                            format!("joint {joint} is out of range of skin {}", self.name)
+                        })?;
+                        Ok((*joint, *weight))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(SkinnedVertex::new(
+                    Vec3::from_slice(&vertex[0..3]),
+                    Vec3::from_slice(&vertex[3..6]),
+                    Vec2::from_slice(&vertex[6..8]),
+                    &influences,
+                ))
+            })
+            .collect()
+    }
+}
+
+impl GltfScene {
+    /// Import a `.gltf` with its buffers and images, or a `.glb`, through the asset resolver.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let imported = match assets::resolver().resolve(path)? {
+            AssetSource::Disk(path) => ::gltf::import(path),
+            AssetSource::Embedded(data) => ::gltf::import_slice(data),
+        };
+        let (document, buffers, images) =
+            imported.map_err(|err| format!("Failed to import {path}: {err}"))?;
+
+        let buffers = buffers
+            .into_iter()
+            .map(|buffer| buffer.0)
+            .collect::<Vec<_>>();
+        let images = images
+            .into_iter()
+            .map(convert_image)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("{path}: {err}"))?;
+
+        Self::from_document(&document, &buffers, &images).map_err(|err| format!("{path}: {err}"))
+    }
+
+    fn from_document(
+        document: &Document,
+        buffers: &[Vec<u8>],
+        images: &[DynamicImage],
+    ) -> Result<Self, String> {
+        let nodes = document
+            .nodes()
+            .map(|node| {
+                let (translation, rotation, scale) = node.transform().decomposed();
+                GltfNode {
+                    name: node.name().unwrap_or_default().to_string(),
+                    translation: Vec3::from_array(translation),
+                    rotation: Quat::from_array(rotation),
+                    scale: Vec3::from_array(scale),
+                    children: node.children().map(|child| child.index()).collect(),
+                    mesh: node.mesh().map(|mesh| mesh.index()),
+                    camera: node.camera().map(|camera| camera.index()),
+                    light: node.light().map(|light| light.index()),
+                    skin: node.skin().map(|skin| skin.index()),
+                }
+            })
+            .collect();
+
+        let roots = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .map(|scene| scene.nodes().map(|node| node.index()).collect())
+            .unwrap_or_default();
+
+        let meshes = document
+            .meshes()
+            .map(|mesh| {
+                let name = mesh.name().unwrap_or_default().to_string();
+                let primitives = mesh
+                    .primitives()
+                    .filter(|primitive| {
+                        let triangles = primitive.mode() == Mode::Triangles;
+                        if !triangles {
+                            eprintln!("Skipping {:?} primitive of mesh {name}", primitive.mode());
+                        }
+                        triangles
+                    })
+                    .map(|primitive| read_primitive(&primitive, buffers))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(GltfMesh { name, primitives })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let materials = document.materials().map(convert_material).collect();
+
+        let textures = document
+            .textures()
+            .map(|texture| {
+                let index = texture.source().index();
+                let image = images
+                    .get(index)
+                    .cloned()
+                    .ok_or_else(|| format!("image {index} is missing"))?;
+                Ok(GltfTexture {
+                    image,
+                    sampler: convert_sampler(&texture.sampler()),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let cameras = document
+            .cameras()
+            .map(|camera| match camera.projection() {
+                Projection::Perspective(perspective) => GltfCamera {
+                    fov: perspective.yfov().to_degrees(),
+                    near: perspective.znear(),
+                    far: perspective.zfar().unwrap_or(1000.0),
+                },
+                Projection::Orthographic(orthographic) => {
+                    eprintln!("Orthographic cameras are imported as perspective ones");
+                    GltfCamera {
+                        fov: 45.0,
+                        near: orthographic.znear(),
+                        far: orthographic.zfar(),
+                    }
+                }
+            })
+            .collect();
+
+        let lights = document
+            .lights()
+            .into_iter()
+            .flatten()
+            .map(|light| {
+                if let Kind::Directional = light.kind() {
+                    eprintln!("Directional lights are imported as point lights");
+                }
+                GltfLight {
+                    name: light.name().unwrap_or_default().to_string(),
+                    attrs: light_attributes(Vec3::from_array(light.color()), light.range()),
+                }
+            })
+            .collect();
+
+        let mut scene = Self {
+            nodes,
+            roots,
+            meshes,
+            materials,
+            textures,
+            cameras,
+            lights,
+            skins: vec![],
+        };
+        scene.skins = document
+            .skins()
+            .map(|skin| scene.convert_skin(&skin, document, buffers))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(scene)
+    }
+
+    /// A skeleton of the joints of `skin` and the nodes above them, parents first, with the
+    /// animations of `document` moving it.
+    fn convert_skin(
+        &self,
+        skin: &::gltf::Skin,
+        document: &Document,
+        buffers: &[Vec<u8>],
+    ) -> Result<GltfSkin, String> {
+        let name = skin.name().unwrap_or_default().to_string();
+        let mut parents = vec![None; self.nodes.len()];
+        for (index, node) in self.nodes.iter().enumerate() {
+            for child in &node.children {
+                parents[*child] = Some(index);
+            }
+        }
+        // Malformed files can have cycles of parents, don't loop forever.
+        let ancestors = |node: usize| {
+            std::iter::successors(Some(node), |node| parents[*node]).take(self.nodes.len())
+        };
+
+        let mut nodes = vec![];
+        for joint in skin.joints() {
+            for node in ancestors(joint.index()) {
+                if !nodes.contains(&node) {
+                    nodes.push(node);
+                }
+            }
+        }
+        if nodes.len() > MAX_JOINTS {
+            return Err(format!(
+                "skin {name} has {} joints with the nodes above them, at most {MAX_JOINTS} are \
+                 supported",
+                nodes.len()
+            ));
+        }
+        // Parents are closer to the root than their children.
+        nodes.sort_by_key(|node| ancestors(*node).count());
+
+        let mut skeleton = Skeleton::new();
+        let mut node_joints = HashMap::new();
+        for node in nodes {
+            let parent = parents[node]
+                .map(|parent| {
+                    node_joints
+                        .get(&parent)
+                        .copied()
+                        .ok_or_else(|| format!("node {node} is among its own ancestors"))
+                })
+                .transpose()?;
+            let gltf_node = &self.nodes[node];
+            let rest = Transform {
+                translation: gltf_node.translation,
+                rotation: gltf_node.rotation,
+                scale: gltf_node.scale,
+            };
+            node_joints.insert(node, skeleton.add_joint(&gltf_node.name, parent, rest));
+        }
+
+        let joints = skin
+            .joints()
+            .map(|joint| node_joints[&joint.index()])
+            .collect::<Vec<_>>();
+        // Without inverse bind matrices the vertices are in the space of each joint.
+        let inverse_binds = skin
+            .reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice))
+            .read_inverse_bind_matrices()
+            .map(|matrices| {
+                matrices
+                    .map(|matrix| Mat4::from_cols_array_2d(&matrix))
+                    .collect()
+            })
+            .unwrap_or_else(|| vec![Mat4::IDENTITY; joints.len()]);
+        if inverse_binds.len() != joints.len() {
+            return Err(format!(
+                "skin {name} has {} inverse bind matrices for {} joints",
+                inverse_binds.len(),
+                joints.len()
+            ));
+        }
+        for (joint, inverse_bind) in joints.iter().zip(inverse_binds) {
+            skeleton.set_inverse_bind(*joint, inverse_bind);
+        }
+
+        let clips = document
+            .animations()
+            .map(|animation| convert_animation(&animation, &node_joints, buffers))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|clip| !clip.channels().is_empty())
+            .collect();
+
+        Ok(GltfSkin {
+            name,
+            skeleton,
+            joints,
+            clips,
+        })
+    }
+
+    /// World matrix of every node reachable from the scene roots, indexed like `nodes`. Other
+    /// nodes get `None`.
+    pub fn world_matrices(&self) -> Vec<Option<Mat4>> {
+        let mut matrices = vec![None; self.nodes.len()];
+        let mut stack = self
+            .roots
+            .iter()
+            .map(|root| (*root, Mat4::IDENTITY))
+            .collect::<Vec<_>>();
+        while let Some((index, parent)) = stack.pop() {
+            let Some(node) = self.nodes.get(index) else {
+                continue;
+            };
+            if matrices[index].is_some() {
+                // Malformed files can reference a node twice, don't loop forever.
+                continue;
+            }
+            let world = parent * node.local_matrix();
+            matrices[index] = Some(world);
+            stack.extend(node.children.iter().map(|child| (*child, world)));
+        }
+        matrices
+    }
+
+    /// A lit object per primitive. The node transforms are baked into the vertices, so every
+    /// object sits at the origin. Skinned primitives stand in their bind pose, which their
+    /// joints place rather than their node.
+    pub fn build_objects(
+        &self,
+        gl: &Gl,
+        shader_cache: &mut ShaderCache,
+        scene_defines: &ShaderDefines,
+        assets: &mut AssetCache,
+    ) -> Vec<Cube> {
+        let mut objects = vec![];
+        for (node, world) in self.nodes.iter().zip(self.world_matrices()) {
+            let (Some(mesh), Some(world)) = (node.mesh, world) else {
+                continue;
+            };
+            let world = if node.skin.is_some() {
+                Mat4::IDENTITY
+            } else {
+                world
+            };
+            for primitive in &self.meshes[mesh].primitives {
+                let vertices = transform_vertices(&primitive.vertices, world);
+                let gltf_material = primitive
+                    .material
+                    .and_then(|material| self.materials.get(material));
+
+                let mut texture_manager = TextureManager::new();
+                let material = match gltf_material {
+                    Some(gltf_material) => {
+                        let mut material = gltf_material.material.clone();
+                        if let Some(texture) = gltf_material
+                            .base_color_texture
+                            .and_then(|texture| self.textures.get(texture))
+                        {
+                            let texture =
+                                assets.texture_from_image(gl, &texture.image, &texture.sampler);
+                            texture_manager.add_texture("material.diffuse", texture, 0);
+                            material.diffuse_map = true;
+                        }
+                        if let Some(texture) = gltf_material
+                            .normal_texture
+                            .and_then(|texture| self.textures.get(texture))
+                        {
+                            let texture =
+                                assets.texture_from_image(gl, &texture.image, &texture.sampler);
+                            texture_manager.add_texture("material.normal", texture, 2);
+                            material.normal_map = true;
+                        }
+                        material
+                    }
+                    None => default_material(),
+                };
+
+                let vertex_buffer = if material.needs_tangents() {
+                    let (vertices, indices) = match &primitive.tangents {
+                        Some(tangents) => (
+                            with_file_tangents(&vertices, tangents, world),
+                            primitive.indices.clone(),
+                        ),
+                        None => tangent_vertices(&vertices, &primitive.indices),
+                    };
+                    assets.indexed_mesh(gl, &vertices, &indices, PrimitiveVertex::layout())
+                } else {
+                    assets.indexed_mesh(gl, &vertices, &primitive.indices, StandardVertex::layout())
+                };
+                objects.push(Cube::with_geometry(
+                    gl,
+                    Vec3::ZERO,
+                    vertex_buffer,
+                    material,
+                    texture_manager,
+                    shader_cache,
+                    scene_defines,
+                ));
+            }
+        }
+        objects
+    }
+
+    /// A camera for every node with one, looking down the node's -Z axis.
+    pub fn cameras(&self) -> Vec<Camera> {
+        self.nodes
+            .iter()
+            .zip(self.world_matrices())
+            .filter_map(|(node, world)| {
+                let gltf_camera = self.cameras.get(node.camera?)?;
+                let world = world?;
+
+                let mut camera = Camera::new();
+                camera.set_pos(world.transform_point3(Vec3::ZERO));
+                camera.look_in(world.transform_vector3(Vec3::NEG_Z));
+                camera.set_fov(gltf_camera.fov);
+                camera.set_clip_planes(gltf_camera.near, gltf_camera.far);
+                Some(camera)
+            })
+            .collect()
+    }
+
+    /// The lights of every node with one, placed at the node's position.
+    pub fn light_attributes(&self) -> Vec<LightAttributes> {
+        self.nodes
+            .iter()
+            .zip(self.world_matrices())
+            .filter_map(|(node, world)| {
+                let light = self.lights.get(node.light?)?;
+                Some(LightAttributes {
+                    position: world?.transform_point3(Vec3::ZERO),
+                    ..light.attrs.clone()
+                })
+            })
+            .collect()
+    }
+}
+
+fn read_primitive(
+    primitive: &::gltf::Primitive,
+    buffers: &[Vec<u8>],
+) -> Result<GltfPrimitive, String> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+
+    let positions = reader
+        .read_positions()
+        .ok_or("primitive has no positions")?
+        .map(Vec3::from_array)
+        .collect::<Vec<_>>();
+    let tex_coords = reader
+        .read_tex_coords(tex_coord_set(&primitive.material())?)
+        .map(|tex_coords| tex_coords.into_f32().map(Vec2::from_array).collect())
+        .unwrap_or_else(|| vec![Vec2::ZERO; positions.len()]);
+    let mut indices = reader
+        .read_indices()
+        .map(|indices| indices.into_u32().collect())
+        .unwrap_or_else(|| (0..positions.len() as u32).collect::<Vec<_>>());
+    if let Some(index) = indices
+        .iter()
+        .find(|index| **index as usize >= positions.len())
+    {
+        return Err(format!("index {index} out of range"));
+    }
+
+    let tangents = reader
+        .read_tangents()
+        .map(|tangents| tangents.map(Vec4::from_array).collect::<Vec<_>>());
+    if tangents
+        .as_ref()
+        .is_some_and(|tangents| tangents.len() != positions.len())
+    {
+        return Err("primitive has a tangent count unlike its position count".to_string());
+    }
+
+    let influences = match (reader.read_joints(0), reader.read_weights(0)) {
+        (Some(joints), Some(weights)) => {
+            let joints = joints.into_u16().collect::<Vec<_>>();
+            let weights = weights.into_f32().collect::<Vec<_>>();
+            if joints.len() != positions.len() || weights.len() != positions.len() {
+                return Err(
+                    "primitive has a joint or weight count unlike its position count".to_string(),
+                );
+            }
+            let influences = joints
+                .into_iter()
+                .zip(weights)
+                .map(|(joints, weights)| std::array::from_fn(|i| (joints[i] as usize, weights[i])))
+                .collect::<Vec<_>>();
+            Some(influences)
+        }
+        _ => None,
+    };
+
+    let (positions, normals, tex_coords, tangents, influences) = match reader.read_normals() {
+        Some(normals) => (
+            positions,
+            normals.map(Vec3::from_array).collect::<Vec<_>>(),
+            tex_coords,
+            tangents,
+            influences,
+        ),
+        // Without normals the spec asks for flat shading, so every triangle gets its own
+        // vertices, and any tangents are to be ignored.
+        None => {
+            let (positions, normals) = flat_normals(&positions, &indices);
+            let tex_coords = indices
+                .iter()
+                .map(|index| tex_coords[*index as usize])
+                .collect();
+            let influences = influences.map(|influences| {
+                indices
+                    .iter()
+                    .map(|index| influences[*index as usize])
+                    .collect()
+            });
+            indices = (0..positions.len() as u32).collect();
+            (positions, normals, tex_coords, None, influences)
+        }
+    };
+
+    let mut vertices = Vec::with_capacity(positions.len() * GLTF_VERTEX_STRIDE as usize);
+    for ((position, normal), tex_coord) in positions.iter().zip(&normals).zip(&tex_coords) {
+        vertices.extend(position.to_array());
+        vertices.extend(normal.to_array());
+        vertices.extend(tex_coord.to_array());
+    }
+
+    Ok(GltfPrimitive {
+        vertices,
+        indices,
+        tangents,
+        influences,
+        material: primitive.material().index(),
+    })
+}
+
+/// A clip of the channels of `animation` moving the nodes of `node_joints`, which maps nodes
+/// to the joints of a skeleton. The other channels are left out.
+fn convert_animation(
+    animation: &::gltf::Animation,
+    node_joints: &HashMap<usize, usize>,
+    buffers: &[Vec<u8>],
+) -> Result<Clip, String> {
+    let name = animation.name().map_or_else(
+        || format!("animation {}", animation.index()),
+        str::to_string,
+    );
+
+    let mut channels = vec![];
+    for channel in animation.channels() {
+        let Some(joint) = node_joints.get(&channel.target().node().index()) else {
+            continue;
+        };
+        let reader = channel.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+        let times = reader
+            .read_inputs()
+            .ok_or_else(|| format!("a channel of {name} has no key times"))?
+            .collect::<Vec<_>>();
+
+        let cubic = channel.sampler().interpolation() == GltfInterpolation::CubicSpline;
+        if cubic {
+            eprintln!("Cubic spline channels of {name} are imported as linear ones");
+        }
+        let keyframes = match reader.read_outputs() {
+            Some(ReadOutputs::Translations(values)) => {
+                Keyframes::Translation(keyframe_values(values.map(Vec3::from_array), cubic))
+            }
+            Some(ReadOutputs::Rotations(values)) => Keyframes::Rotation(keyframe_values(
+                values.into_f32().map(Quat::from_array),
+                cubic,
+            )),
+            Some(ReadOutputs::Scales(values)) => {
+                Keyframes::Scale(keyframe_values(values.map(Vec3::from_array), cubic))
+            }
+            Some(ReadOutputs::MorphTargetWeights(_)) => {
+                eprintln!("Skipping morph target weights of {name}");
+                continue;
+            }
+            None => return Err(format!("a channel of {name} has no keyframes")),
+        };
+        let interpolation = match channel.sampler().interpolation() {
+            GltfInterpolation::Step => Interpolation::Step,
+            GltfInterpolation::Linear | GltfInterpolation::CubicSpline => Interpolation::Linear,
+        };
+        channels.push(
+            Channel::new(*joint, times, keyframes, interpolation)
+                .map_err(|err| format!("{name}: {err}"))?,
+        );
+    }
+    Ok(Clip::new(&name, channels))
+}
+
+/// The values of the keyframes. Cubic spline keyframes hold an in tangent, the value and an
+/// out tangent, of which only the value is kept.
+fn keyframe_values<T>(values: impl Iterator<Item = T>, cubic: bool) -> Vec<T> {
+    if cubic {
+        values.skip(1).step_by(3).collect()
+    } else {
+        values.collect()
+    }
+}
+
+/// The texture coordinate set the maps of `material` read. Vertices only carry one set, so
+/// maps reading different sets can't be drawn.
+fn tex_coord_set(material: &::gltf::Material) -> Result<u32, String> {
+    let base_color = material
+        .pbr_metallic_roughness()
+        .base_color_texture()
+        .map(|info| info.tex_coord());
+    let normal = material.normal_texture().map(|normal| normal.tex_coord());
+    match (base_color, normal) {
+        (Some(base_color), Some(normal)) if base_color != normal => Err(format!(
+            "material {} reads texture coordinates {base_color} and {normal}, only one set is \
+             supported",
+            material.name().unwrap_or_default()
+        )),
+        (base_color, normal) => Ok(base_color.or(normal).unwrap_or(0)),
+    }
+}
+
+/// Unweld indexed triangles, giving every corner the normal of its triangle.
+fn flat_normals(positions: &[Vec3], indices: &[u32]) -> (Vec<Vec3>, Vec<Vec3>) {
+    let mut flat_positions = Vec::with_capacity(indices.len());
+    let mut normals = Vec::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| positions[triangle[i] as usize]);
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        flat_positions.extend([a, b, c]);
+        normals.extend([normal; 3]);
+    }
+    (flat_positions, normals)
+}
+
+/// Apply `world` to the positions and normals of interleaved vertex data.
+fn transform_vertices(vertices: &[f32], world: Mat4) -> Vec<f32> {
+    let normal_matrix = Mat3::from_mat4(world).inverse().transpose();
+    vertices
+        .chunks_exact(GLTF_VERTEX_STRIDE as usize)
+        .flat_map(|vertex| {
+            let position = world.transform_point3(Vec3::from_slice(&vertex[0..3]));
+            let normal = (normal_matrix * Vec3::from_slice(&vertex[3..6])).normalize_or_zero();
+            position
+                .to_array()
+                .into_iter()
+                .chain(normal.to_array())
+                .chain([vertex[6], vertex[7]])
+        })
+        .collect()
+}
+
+/// Vertices with generated tangents. glTF normal maps point green up the image, which is
+/// towards -v since glTF texture coordinates start at the top, so the handedness flips.
+fn tangent_vertices(vertices: &[f32], indices: &[u32]) -> (Vec<f32>, Vec<u32>) {
+    let (mut vertices, indices) = tangents::with_tangents(vertices, indices);
+    for vertex in vertices.chunks_exact_mut(PRIMITIVE_VERTEX_STRIDE as usize) {
+        vertex[11] = -vertex[11];
+    }
+    (vertices, indices)
+}
+
+/// Vertices with the file's tangents moved along with the positions by `world`. Their bitangents
+/// already point up the image, like the normal maps' green, so unlike generated ones they keep
+/// their handedness, unless `world` mirrors them.
+fn with_file_tangents(vertices: &[f32], tangents: &[Vec4], world: Mat4) -> Vec<f32> {
+    let matrix = Mat3::from_mat4(world);
+    let mirror = matrix.determinant().signum();
+    vertices
+        .chunks_exact(GLTF_VERTEX_STRIDE as usize)
+        .zip(tangents)
+        .flat_map(|(vertex, tangent)| {
+            let direction = (matrix * tangent.truncate()).normalize_or_zero();
+            vertex
+                .iter()
+                .copied()
+                .chain(direction.extend(tangent.w * mirror).to_array())
+        })
+        .collect()
+}
+
+fn default_material() -> Material {
+    Material {
+        diffuse: Vec3::ONE,
+        specular: Vec3::splat(DIELECTRIC_SPECULAR),
+        diffuse_map: false,
+        specular_map: false,
+        ..Material::default()
+    }
+}
+
+fn convert_material(material: ::gltf::Material) -> GltfMaterial {
+    let pbr = material.pbr_metallic_roughness();
+    let base_color = Vec4::from_array(pbr.base_color_factor()).truncate();
+    let (specular, shininess) =
+        phong_from_metallic_roughness(base_color, pbr.metallic_factor(), pbr.roughness_factor());
+
+    GltfMaterial {
+        name: material.name().unwrap_or_default().to_string(),
+        material: Material {
+            // Metals have no diffuse reflection.
+            diffuse: base_color * (1.0 - pbr.metallic_factor()),
+            specular,
+            shininess,
+            diffuse_map: false,
+            specular_map: false,
+            ..Material::default()
+        },
+        base_color_texture: pbr.base_color_texture().map(|info| info.texture().index()),
+        normal_texture: material
+            .normal_texture()
+            .map(|normal| normal.texture().index()),
+    }
+}
+
+/// Approximate a metallic-roughness material with a specular color and Blinn-Phong exponent.
+/// The metallic-roughness texture has no counterpart and is ignored.
+fn phong_from_metallic_roughness(base_color: Vec3, metallic: f32, roughness: f32) -> (Vec3, f32) {
+    let specular = Vec3::splat(DIELECTRIC_SPECULAR).lerp(base_color, metallic);
+    // The exponent whose lobe matches a GGX lobe of the same roughness, alpha = roughness^2.
+    let alpha = (roughness * roughness).max(0.01);
+    let shininess = (2.0 / (alpha * alpha) - 2.0).clamp(1.0, 1024.0);
+    (specular, shininess)
+}
+
+fn convert_sampler(sampler: &::gltf::texture::Sampler) -> TextureSampler {
+    let wrap = |mode| match mode {
+        WrappingMode::ClampToEdge => gl::CLAMP_TO_EDGE,
+        WrappingMode::MirroredRepeat => gl::MIRRORED_REPEAT,
+        WrappingMode::Repeat => gl::REPEAT,
+    };
+    let mag_filter: GLenum = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => gl::NEAREST,
+        Some(MagFilter::Linear) | None => gl::LINEAR,
+    };
+    let min_filter = match sampler.min_filter() {
+        Some(MinFilter::Nearest) => gl::NEAREST,
+        Some(MinFilter::Linear) => gl::LINEAR,
+        Some(MinFilter::NearestMipmapNearest) => gl::NEAREST_MIPMAP_NEAREST,
+        Some(MinFilter::LinearMipmapNearest) => gl::LINEAR_MIPMAP_NEAREST,
+        Some(MinFilter::NearestMipmapLinear) => gl::NEAREST_MIPMAP_LINEAR,
+        Some(MinFilter::LinearMipmapLinear) | None => gl::LINEAR_MIPMAP_LINEAR,
+    };
+
+    TextureSampler {
+        wrap_s: wrap(sampler.wrap_s()),
+        wrap_t: wrap(sampler.wrap_t()),
+        mag_filter,
+        min_filter,
+    }
+}
+
+/// glTF texture coordinates start at the top left of the image, which is also where GL reads
+/// the first uploaded row from, so unlike other textures these aren't flipped.
+fn convert_image(data: ::gltf::image::Data) -> Result<DynamicImage, String> {
+    let (width, height) = (data.width, data.height);
+    let image = match data.format {
+        Format::R8 => {
+            ImageBuffer::from_raw(width, height, data.pixels).map(DynamicImage::ImageLuma8)
+        }
+        Format::R8G8 => {
+            ImageBuffer::from_raw(width, height, data.pixels).map(DynamicImage::ImageLumaA8)
+        }
+        Format::R8G8B8 => {
+            ImageBuffer::from_raw(width, height, data.pixels).map(DynamicImage::ImageRgb8)
+        }
+        Format::R8G8B8A8 => {
+            ImageBuffer::from_raw(width, height, data.pixels).map(DynamicImage::ImageRgba8)
+        }
+        format => return Err(format!("unsupported image format {format:?}")),
+    };
+    image.ok_or_else(|| "image data doesn't match its size".to_string())
+}
+
+/// Point light attributes with the crate's default ratios between the lighting terms. glTF
+/// intensities are physical units the lit shader has no use for, so only the color is kept.
+fn light_attributes(color: Vec3, range: Option<f32>) -> LightAttributes {
+    let defaults = LightAttributes::default();
+    let (linear, quadratic) = match range {
+        // Falls to about 1% of the light at `range`.
+        Some(range) if range > 0.0 => (4.5 / range, 75.0 / (range * range)),
+        _ => (defaults.linear, defaults.quadratic),
+    };
+
+    LightAttributes {
+        ambient: color * defaults.ambient,
+        diffuse: color * defaults.diffuse,
+        specular: color * defaults.specular,
+        linear,
+        quadratic,
+        ..defaults
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::IVec4;
+
+    use super::*;
+
+    /// A triangle without normals under a translated and rotated parent, plus a camera and a
+    /// light in child nodes. The buffer holds the three positions followed by u16 indices.
+    const SCENE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": { "KHR_lights_punctual": { "lights": [
+            { "type": "point", "color": [1.0, 0.5, 0.0], "range": 10.0 }
+        ] } },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "name": "root", "translation": [0, 0, -5], "children": [1, 2, 3] },
+            { "name": "triangle", "mesh": 0, "rotation": [0, 0.7071068, 0, 0.7071068] },
+            { "name": "camera", "camera": 0, "translation": [0, 1, 0] },
+            { "name": "light", "extensions": { "KHR_lights_punctual": { "light": 0 } } }
+        ],
+        "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.7853982, "znear": 0.5, "zfar": 50 } }],
+        "materials": [{ "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1], "metallicFactor": 0, "roughnessFactor": 1 } }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }] }],
+        "buffers": [{ "byteLength": 44, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA=" }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] },
+            { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+        ]
+    }"#;
+
+    /// A triangle with normals, tangents and two texture coordinate sets, the second of which
+    /// its normal map reads. The buffer holds each attribute in turn.
+    const TANGENT_SCENE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "images": [{ "uri": "normal.png" }],
+        "textures": [{ "source": 0 }],
+        "materials": [{ "normalTexture": { "index": 0, "texCoord": 1 } }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0, "NORMAL": 1, "TANGENT": 2, "TEXCOORD_0": 3, "TEXCOORD_1": 4 }, "material": 0 }] }],
+        "buffers": [{ "byteLength": 168, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/AAAAAAAAgD8AAAAAAACAvwAAAAAAAIA/AAAAAAAAgL8AAAAAAACAPwAAAAAAAIC/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/" }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 72, "byteLength": 48 },
+            { "buffer": 0, "byteOffset": 120, "byteLength": 24 },
+            { "buffer": 0, "byteOffset": 144, "byteLength": 24 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] },
+            { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" },
+            { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC4" },
+            { "bufferView": 3, "componentType": 5126, "count": 3, "type": "VEC2" },
+            { "bufferView": 4, "componentType": 5126, "count": 3, "type": "VEC2" }
+        ]
+    }"#;
+
+    /// A triangle skinned to a hip and a knee under a translated armature, listed knee first,
+    /// and an animation bending the hip a quarter turn around Z. The buffer holds the
+    /// positions, u8 joints, weights, inverse bind matrices, key times and rotations in turn.
+    const SKIN_SCENE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "name": "armature", "translation": [0, 0, -5], "children": [1, 3] },
+            { "name": "hip", "children": [2] },
+            { "name": "knee", "translation": [0, 1, 0] },
+            { "name": "leg", "mesh": 0, "skin": 0, "translation": [9, 9, 9] }
+        ],
+        "skins": [{ "joints": [2, 1], "inverseBindMatrices": 3 }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0, "JOINTS_0": 1, "WEIGHTS_0": 2 } }] }],
+        "animations": [{
+            "name": "bend",
+            "samplers": [{ "input": 4, "output": 5 }],
+            "channels": [{ "sampler": 0, "target": { "node": 1, "path": "rotation" } }]
+        }],
+        "buffers": [{ "byteLength": 264, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAKDAAACAPwAAAAAAAKDAAAAAAAAAAEAAAKDAAQAAAAEAAAAAAQAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAAA/AAAAPwAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAgL8AAKBAAACAPwAAgD8AAAAAAAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAAAAAACgQAAAgD8AAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAPQENT/0BDU/" }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 12 },
+            { "buffer": 0, "byteOffset": 48, "byteLength": 48 },
+            { "buffer": 0, "byteOffset": 96, "byteLength": 128 },
+            { "buffer": 0, "byteOffset": 224, "byteLength": 8 },
+            { "buffer": 0, "byteOffset": 232, "byteLength": 32 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, -5], "max": [1, 2, -5] },
+            { "bufferView": 1, "componentType": 5121, "count": 3, "type": "VEC4" },
+            { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC4" },
+            { "bufferView": 3, "componentType": 5126, "count": 2, "type": "MAT4" },
+            { "bufferView": 4, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0], "max": [1] },
+            { "bufferView": 5, "componentType": 5126, "count": 2, "type": "VEC4" }
+        ]
+    }"#;
+
+    /// Import `json` with its embedded buffers, standing in `images` for the ones it names.
+    fn load(json: &str, images: &[DynamicImage]) -> Result<GltfScene, String> {
+        let ::gltf::Gltf { document, blob } = ::gltf::Gltf::from_slice(json.as_bytes()).unwrap();
+        let buffers = ::gltf::import_buffers(&document, None, blob)
+            .unwrap()
+            .into_iter()
+            .map(|buffer| buffer.0)
+            .collect::<Vec<_>>();
+        GltfScene::from_document(&document, &buffers, images)
+    }
+
+    fn scene() -> GltfScene {
+        load(SCENE, &[]).unwrap()
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        let scene = scene();
+        assert_eq!(scene.roots, vec![0]);
+        assert_eq!(scene.nodes[0].children, vec![1, 2, 3]);
+
+        let matrices = scene.world_matrices();
+        let triangle = matrices[1].unwrap();
+        // A quarter turn around Y, then the parent's translation.
+        let corner = triangle.transform_point3(Vec3::X);
+        assert!(corner.abs_diff_eq(Vec3::new(0.0, 0.0, -6.0), 1e-5));
+
+        let lights = scene.light_attributes();
+        assert_eq!(lights.len(), 1);
+        assert_eq!(lights[0].position, Vec3::new(0.0, 0.0, -5.0));
+        assert_eq!(lights[0].diffuse, Vec3::new(0.5, 0.25, 0.0));
+    }
+
+    #[test]
+    fn test_primitive_gets_flat_normals() {
+        let scene = scene();
+        let primitive = &scene.meshes[0].primitives[0];
+
+        assert_eq!(primitive.indices, vec![0, 1, 2]);
+        assert_eq!(primitive.material, Some(0));
+        for vertex in primitive.vertices.chunks_exact(GLTF_VERTEX_STRIDE as usize) {
+            assert_eq!(&vertex[3..6], &[0.0, 0.0, 1.0]);
+        }
+
+        let world = scene.world_matrices()[1].unwrap();
+        let transformed = transform_vertices(&primitive.vertices, world);
+        assert!(Vec3::from_slice(&transformed[3..6]).abs_diff_eq(Vec3::X, 1e-5));
+    }
+
+    #[test]
+    fn test_file_tangents_and_tex_coord_set() {
+        let normal_map = [DynamicImage::new_rgb8(1, 1)];
+        let scene = load(TANGENT_SCENE, &normal_map).unwrap();
+        let primitive = &scene.meshes[0].primitives[0];
+        let tangents = primitive.tangents.as_ref().unwrap();
+        assert_eq!(tangents, &vec![Vec4::new(0.0, 1.0, 0.0, -1.0); 3]);
+        // The second vertex's coordinates of the second set.
+        assert_eq!(&primitive.vertices[14..16], &[1.0, 0.0]);
+
+        // Mirroring turns the bitangent around, and the handedness with it.
+        let mirror = Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0));
+        let vertices = with_file_tangents(&primitive.vertices, tangents, mirror);
+        assert_eq!(&vertices[8..12], &[0.0, 1.0, 0.0, 1.0]);
+
+        let two_sets = TANGENT_SCENE.replace(
+            r#""materials": [{ "#,
+            r#""materials": [{ "pbrMetallicRoughness": { "baseColorTexture": { "index": 0 } }, "#,
+        );
+        assert!(load(&two_sets, &normal_map)
+            .err()
+            .unwrap()
+            .contains("only one set is supported"));
+    }
+
+    #[test]
+    fn test_skin_and_animation() {
+        let scene = load(SKIN_SCENE, &[]).unwrap();
+        assert_eq!(scene.nodes[3].skin, Some(0));
+        let skin = &scene.skins[0];
+
+        // The armature moves the joints too, and parents come first.
+        let names = skin
+            .skeleton
+            .joints()
+            .iter()
+            .map(|joint| joint.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["armature", "hip", "knee"]);
+        assert_eq!(skin.joints, [2, 1]);
+        assert_eq!(skin.skeleton.joints()[2].parent, Some(1));
+        let rest_pose = skin.skeleton.rest_pose();
+        for joint in skin.joints.iter() {
+            let matrix = skin.skeleton.skinning_matrices(&rest_pose)[*joint];
+            assert!(matrix.abs_diff_eq(Mat4::IDENTITY, 1e-6));
+        }
+
+        let vertices = skin.vertices(&scene.meshes[0].primitives[0]).unwrap();
+        assert_eq!(vertices[1].joints.x, 1);
+        assert_eq!(vertices[2].joints, IVec4::new(2, 1, 0, 0));
+        assert_eq!(vertices[2].weights, Vec4::new(0.5, 0.5, 0.0, 0.0));
+
+        // Bending the hip swings the vertex following it around the armature's origin.
+        let clip = &skin.clips[0];
+        assert_eq!((clip.name.as_str(), clip.duration()), ("bend", 1.0));
+        let mut pose = rest_pose.clone();
+        clip.sample(1.0, &mut pose);
+        let matrices = skin.skeleton.skinning_matrices(&pose);
+        assert!(vertices[1]
+            .skinned_position(&matrices)
+            .abs_diff_eq(Vec3::new(0.0, 1.0, -5.0), 1e-5));
+
+        let missing_joint = SKIN_SCENE.replace(r#""joints": [2, 1]"#, r#""joints": [2]"#);
+        assert!(load(&missing_joint, &[])
+            .err()
+            .unwrap()
+            .contains("2 inverse bind matrices for 1 joints"));
+    }
+
+    #[test]
+    fn test_camera_and_material() {
+        let mut scene = scene();
+        assert_eq!(scene.cameras[0].near, 0.5);
+        assert!((scene.cameras[0].fov - 45.0).abs() < 1e-4);
+        // Wider than scroll zooming goes.
+        scene.cameras[0].fov = 100.0;
+        assert_eq!(scene.cameras()[0].fov(), 100.0);
+
+        let material = &scene.materials[0].material;
+        assert_eq!(material.diffuse, Vec3::X);
+        assert_eq!(material.specular, Vec3::splat(DIELECTRIC_SPECULAR));
+        assert_eq!(material.shininess, 1.0);
+    }
+
+    #[test]
+    fn test_phong_from_metallic_roughness() {
+        let (specular, shininess) = phong_from_metallic_roughness(Vec3::ONE, 1.0, 0.5);
+        assert_eq!(specular, Vec3::ONE);
+        assert_eq!(shininess, 30.0);
+
+        let (_, shininess) = phong_from_metallic_roughness(Vec3::ONE, 0.0, 0.0);
+        assert_eq!(shininess, 1024.0);
+    }
+}
@@ -0,0 +1,747 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use glam::{vec2, vec3, Vec2, Vec3};
+
+use crate::{
+    assets,
+    gl::{self, Gl},
+    mesh::{
+        layout::{StandardVertex, Vertex},
+        primitives::PrimitiveVertex,
+        tangents, VertexBuffer,
+    },
+    object::cube::{Cube, Material},
+    renderer::{
+        asset_cache::AssetCache,
+        texture::{height_map, load_image, normal_map_from_height, TextureManager, TextureSampler},
+    },
+    shader::{cache::ShaderCache, ShaderDefines},
+};
+
+/// Floats per vertex of the meshes built from a model: position, normal, texture coordinates.
+pub const OBJ_VERTEX_STRIDE: i32 = 8;
+
+/// Height difference in texels between white and black of a bump map with `-bm 1`.
+const BUMP_STRENGTH: f32 = 4.0;
+
+/// A parsed Wavefront OBJ file with the materials of its `mtllib`s.
+#[derive(Debug, Default)]
+pub struct ObjModel {
+    /// One mesh per group and material used in it.
+    pub meshes: Vec<ObjMesh>,
+    pub materials: HashMap<String, ObjMaterial>,
+}
+
+/// Indexed, triangulated geometry laid out like `OBJ_VERTEX_STRIDE` describes.
+#[derive(Debug, Default, PartialEq)]
+pub struct ObjMesh {
+    /// Name of the `g` or `o` group the faces belong to.
+    pub name: String,
+    /// Material selected with `usemtl`.
+    pub material: Option<String>,
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+/// A material of an MTL file. Map paths are relative to the asset root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMaterial {
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    pub diffuse_map: Option<PathBuf>,
+    pub specular_map: Option<PathBuf>,
+    /// A tangent space normal map, from `norm`.
+    pub normal_map: Option<PathBuf>,
+    /// A height map, from `bump` or `map_Bump`. Without a `norm` map the normals are derived
+    /// from it.
+    pub bump_map: Option<PathBuf>,
+    /// Scales the heights of the bump map, from its `-bm` option.
+    pub bump_multiplier: f32,
+}
+
+impl Default for ObjMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: Vec3::ONE,
+            specular: Vec3::ZERO,
+            shininess: 1.0,
+            diffuse_map: None,
+            specular_map: None,
+            normal_map: None,
+            bump_map: None,
+            bump_multiplier: 1.0,
+        }
+    }
+}
+
+impl ObjMaterial {
+    pub fn material(&self) -> Material {
+        Material {
+            diffuse: self.diffuse,
+            specular: self.specular,
+            // A zero exponent makes every angle full strength, which no MTL exporter means.
+            shininess: self.shininess.max(1.0),
+            diffuse_map: self.diffuse_map.is_some(),
+            specular_map: self.specular_map.is_some(),
+            normal_map: self.normal_map.is_some() || self.bump_map.is_some(),
+            ..Material::default()
+        }
+    }
+
+    /// Load the maps into the texture slots the lit object shader reads them from. Maps
+    /// shared between materials are only loaded once.
+    pub fn textures(&self, gl: &Gl, assets: &mut AssetCache) -> TextureManager {
+        let mut texture_manager = TextureManager::new();
+        let maps = [
+            ("material.diffuse", &self.diffuse_map, 0),
+            ("material.specular", &self.specular_map, 1),
+            ("material.normal", &self.normal_map, 2),
+        ];
+        for (name, path, unit) in maps {
+            if let Some(path) = path {
+                let texture = assets
+                    .texture(gl, &path.to_string_lossy(), &TextureSampler::default())
+                    .unwrap_or_else(|err| panic!("{err}"));
+                texture_manager.add_texture(name, texture, unit);
+            }
+        }
+        if let (None, Some(path)) = (&self.normal_map, &self.bump_map) {
+            let height = load_image(&path.to_string_lossy())
+                .map(|img| height_map(&img))
+                .unwrap_or_else(|err| panic!("{err}"));
+            let normal = normal_map_from_height(&height, BUMP_STRENGTH * self.bump_multiplier);
+            let sampler = TextureSampler {
+                mag_filter: gl::LINEAR,
+                min_filter: gl::LINEAR_MIPMAP_LINEAR,
+                ..TextureSampler::default()
+            };
+            let texture = assets.texture_from_image(gl, &normal, &sampler);
+            texture_manager.add_texture("material.normal", texture, 2);
+        }
+        texture_manager
+    }
+}
+
+impl ObjMesh {
+    pub fn vertex_buffer(&self, gl: &Gl) -> VertexBuffer {
+        VertexBuffer::with_indices(gl, &self.vertices, &self.indices, StandardVertex::layout())
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / OBJ_VERTEX_STRIDE as usize
+    }
+}
+
+impl ObjModel {
+    /// Load the model at `path` and every material library it references through the asset
+    /// resolver.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let source = assets::resolver().read_to_string(path)?;
+        parse_obj(&source, Path::new(path), |path| {
+            assets::resolver().read_to_string(path)
+        })
+    }
+
+    /// A lit object per mesh, all placed at `pos`. Meshes without a known material get the
+    /// default MTL material.
+    pub fn build_objects(
+        &self,
+        gl: &Gl,
+        pos: Vec3,
+        shader_cache: &mut ShaderCache,
+        scene_defines: &ShaderDefines,
+        assets: &mut AssetCache,
+    ) -> Vec<Cube> {
+        self.meshes
+            .iter()
+            .map(|mesh| {
+                let material = mesh
+                    .material
+                    .as_ref()
+                    .and_then(|name| self.materials.get(name))
+                    .cloned()
+                    .unwrap_or_default();
+                let vertex_buffer = if material.material().needs_tangents() {
+                    let (vertices, indices) =
+                        tangents::with_tangents(&mesh.vertices, &mesh.indices);
+                    assets.indexed_mesh(gl, &vertices, &indices, PrimitiveVertex::layout())
+                } else {
+                    assets.indexed_mesh(gl, &mesh.vertices, &mesh.indices, StandardVertex::layout())
+                };
+                Cube::with_geometry(
+                    gl,
+                    pos,
+                    vertex_buffer,
+                    material.material(),
+                    material.textures(gl, assets),
+                    shader_cache,
+                    scene_defines,
+                )
+            })
+            .collect()
+    }
+}
+
+/// One corner of a face, as 0 based indices into the model's attribute lists.
+#[derive(Clone, Copy, Debug)]
+struct Corner {
+    position: usize,
+    tex_coord: Option<usize>,
+    normal: Option<usize>,
+}
+
+struct Face {
+    corners: Vec<Corner>,
+    /// 0 when smoothing is off.
+    smoothing_group: u32,
+}
+
+/// Faces sharing a group and material, turned into one mesh.
+struct Segment {
+    name: String,
+    material: Option<String>,
+    faces: Vec<Face>,
+}
+
+/// Where a vertex's normal comes from. Part of the key vertices are deduplicated by.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum NormalSource {
+    File(usize),
+    /// Averaged over the faces of a smoothing group around the position.
+    Smooth(u32),
+    /// The normal of a single face.
+    Flat(usize),
+}
+
+/// Parse an OBJ file. `path` is used for error messages and to resolve `mtllib`s, which are
+/// loaded with `read`.
+pub fn parse_obj(
+    source: &str,
+    path: &Path,
+    mut read: impl FnMut(&Path) -> Result<String, String>,
+) -> Result<ObjModel, String> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut positions: Vec<Vec3> = vec![];
+    let mut tex_coords: Vec<Vec2> = vec![];
+    let mut normals: Vec<Vec3> = vec![];
+    let mut materials = HashMap::new();
+
+    let mut segments = vec![Segment {
+        name: String::new(),
+        material: None,
+        faces: vec![],
+    }];
+    let mut smoothing_group = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        let location = format!("{}:{}", path.display(), i + 1);
+        let line = line.split('#').next().unwrap().trim();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args = tokens.collect::<Vec<_>>();
+
+        match keyword {
+            "v" => positions.push(parse_vec3(&args, &location)?),
+            "vn" => normals.push(parse_vec3(&args, &location)?),
+            "vt" => {
+                let u = parse_float(args.first(), &location)?;
+                let v = match args.get(1) {
+                    Some(_) => parse_float(args.get(1), &location)?,
+                    None => 0.0,
+                };
+                tex_coords.push(vec2(u, v));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(format!("{location}: a face needs at least 3 vertices"));
+                }
+                let corners = args
+                    .iter()
+                    .map(|arg| {
+                        parse_corner(
+                            arg,
+                            [positions.len(), tex_coords.len(), normals.len()],
+                            &location,
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                segments.last_mut().unwrap().faces.push(Face {
+                    corners,
+                    smoothing_group,
+                });
+            }
+            "g" | "o" => {
+                let name = args.join(" ");
+                let material = segments.last().unwrap().material.clone();
+                start_segment(&mut segments, name, material);
+            }
+            "usemtl" => {
+                let material = args.join(" ");
+                if material.is_empty() {
+                    return Err(format!("{location}: usemtl needs a material name"));
+                }
+                let name = segments.last().unwrap().name.clone();
+                start_segment(&mut segments, name, Some(material));
+            }
+            "s" => {
+                smoothing_group = match args.first() {
+                    Some(&"off") => 0,
+                    Some(group) => group
+                        .parse()
+                        .map_err(|_| format!("{location}: invalid smoothing group {group}"))?,
+                    None => return Err(format!("{location}: s needs a group or off")),
+                };
+            }
+            "mtllib" => {
+                for library in args {
+                    let library_path = dir.join(library);
+                    let source = read(&library_path)
+                        .map_err(|err| format!("{location}: failed to read mtllib: {err}"))?;
+                    materials.extend(parse_mtl(&source, &library_path)?);
+                }
+            }
+            // Free-form geometry, lines and points aren't supported.
+            _ => {}
+        }
+    }
+
+    let smooth_normals = smooth_normals(&segments, &positions);
+    let meshes = segments
+        .into_iter()
+        .filter(|segment| !segment.faces.is_empty())
+        .map(|segment| build_mesh(segment, &positions, &tex_coords, &normals, &smooth_normals))
+        .collect();
+
+    Ok(ObjModel { meshes, materials })
+}
+
+/// Parse an MTL file. Map paths are resolved relative to the directory of `path`.
+pub fn parse_mtl(source: &str, path: &Path) -> Result<HashMap<String, ObjMaterial>, String> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, ObjMaterial)> = None;
+
+    for (i, line) in source.lines().enumerate() {
+        let location = format!("{}:{}", path.display(), i + 1);
+        let line = line.split('#').next().unwrap().trim();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args = tokens.collect::<Vec<_>>();
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(format!("{location}: newmtl needs a material name"));
+            }
+            materials.extend(current.take());
+            current = Some((args.join(" "), ObjMaterial::default()));
+            continue;
+        }
+
+        let Some((_, material)) = current.as_mut() else {
+            return Err(format!("{location}: {keyword} before the first newmtl"));
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_vec3(&args, &location)?,
+            "Ks" => material.specular = parse_vec3(&args, &location)?,
+            "Ns" => material.shininess = parse_float(args.first(), &location)?,
+            // Options like `-s 1 1 1` come before the file name.
+            "map_Kd" | "map_Ks" | "norm" | "map_Bump" | "bump" => {
+                let file = args
+                    .last()
+                    .ok_or_else(|| format!("{location}: {keyword} needs a file name"))?;
+                let map = Some(dir.join(file));
+                match keyword {
+                    "map_Kd" => material.diffuse_map = map,
+                    "map_Ks" => material.specular_map = map,
+                    "norm" => material.normal_map = map,
+                    _ => {
+                        material.bump_map = map;
+                        if let Some(i) = args.iter().position(|arg| *arg == "-bm") {
+                            material.bump_multiplier = parse_float(args.get(i + 1), &location)?;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    materials.extend(current);
+
+    Ok(materials)
+}
+
+fn start_segment(segments: &mut Vec<Segment>, name: String, material: Option<String>) {
+    let segment = Segment {
+        name,
+        material,
+        faces: vec![],
+    };
+    match segments.last_mut() {
+        // Nothing was added under the previous name or material yet, so just replace it.
+        Some(last) if last.faces.is_empty() => *last = segment,
+        _ => segments.push(segment),
+    }
+}
+
+fn parse_float(arg: Option<&&str>, location: &str) -> Result<f32, String> {
+    let arg = arg.ok_or_else(|| format!("{location}: missing number"))?;
+    arg.parse()
+        .map_err(|_| format!("{location}: invalid number {arg}"))
+}
+
+fn parse_vec3(args: &[&str], location: &str) -> Result<Vec3, String> {
+    Ok(vec3(
+        parse_float(args.first(), location)?,
+        parse_float(args.get(1), location)?,
+        parse_float(args.get(2), location)?,
+    ))
+}
+
+/// Parse `v`, `v/t`, `v//n` or `v/t/n`. `counts` are the number of positions, texture
+/// coordinates and normals defined so far, which negative indices count back from.
+fn parse_corner(arg: &str, counts: [usize; 3], location: &str) -> Result<Corner, String> {
+    let mut parts = arg.split('/');
+    let mut index = |kind: usize| -> Result<Option<usize>, String> {
+        let Some(part) = parts.next().filter(|part| !part.is_empty()) else {
+            return Ok(None);
+        };
+        let index: i64 = part
+            .parse()
+            .map_err(|_| format!("{location}: invalid index {part}"))?;
+        let count = counts[kind] as i64;
+        let resolved = if index < 0 { count + index } else { index - 1 };
+        if index == 0 || resolved < 0 || resolved >= count {
+            return Err(format!("{location}: index {index} out of range"));
+        }
+        Ok(Some(resolved as usize))
+    };
+
+    let position =
+        index(0)?.ok_or_else(|| format!("{location}: face vertex {arg} has no position"))?;
+    let tex_coord = index(1)?;
+    let normal = index(2)?;
+
+    Ok(Corner {
+        position,
+        tex_coord,
+        normal,
+    })
+}
+
+/// Normal of a polygon by Newell's method, which also works for slightly non planar and
+/// concave polygons. Its length is twice the polygon's area.
+fn polygon_normal(points: &[Vec3]) -> Vec3 {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .fold(Vec3::ZERO, |normal, (a, b)| {
+            normal
+                + vec3(
+                    (a.y - b.y) * (a.z + b.z),
+                    (a.z - b.z) * (a.x + b.x),
+                    (a.x - b.x) * (a.y + b.y),
+                )
+        })
+}
+
+/// Split a polygon into triangles by ear clipping, returning indices into `points`. Falls back
+/// to a fan for degenerate polygons.
+fn triangulate(points: &[Vec3]) -> Vec<[usize; 3]> {
+    if points.len() == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    // Project onto the plane the polygon mostly lies in.
+    let normal = polygon_normal(points);
+    let abs = normal.abs();
+    let axis = if abs.x >= abs.y && abs.x >= abs.z {
+        0
+    } else if abs.y >= abs.z {
+        1
+    } else {
+        2
+    };
+    let flip = normal[axis] < 0.0;
+    let projected = points
+        .iter()
+        .map(|point| {
+            let point = match axis {
+                0 => vec2(point.y, point.z),
+                1 => vec2(point.z, point.x),
+                _ => vec2(point.x, point.y),
+            };
+            if flip {
+                vec2(point.y, point.x)
+            } else {
+                point
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut remaining = (0..points.len()).collect::<Vec<_>>();
+    let mut triangles = vec![];
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let ear = (0..len).find(|&i| {
+            let [a, b, c] = [
+                remaining[(i + len - 1) % len],
+                remaining[i],
+                remaining[(i + 1) % len],
+            ];
+            let [pa, pb, pc] = [projected[a], projected[b], projected[c]];
+            // Convex corner, with no other vertex inside the triangle.
+            (pb - pa).perp_dot(pc - pb) > 0.0
+                && remaining
+                    .iter()
+                    .filter(|&&other| other != a && other != b && other != c)
+                    .all(|&other| !in_triangle(projected[other], pa, pb, pc))
+        });
+
+        let Some(i) = ear else {
+            // Degenerate or self intersecting, fan what is left.
+            triangles.extend((1..len - 1).map(|j| [remaining[0], remaining[j], remaining[j + 1]]));
+            return triangles;
+        };
+        triangles.push([
+            remaining[(i + len - 1) % len],
+            remaining[i],
+            remaining[(i + 1) % len],
+        ]);
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let ab = (b - a).perp_dot(p - a);
+    let bc = (c - b).perp_dot(p - b);
+    let ca = (a - c).perp_dot(p - c);
+    ab >= 0.0 && bc >= 0.0 && ca >= 0.0
+}
+
+/// Area weighted sum of the normals of every face around a position, per smoothing group.
+fn smooth_normals(segments: &[Segment], positions: &[Vec3]) -> HashMap<(usize, u32), Vec3> {
+    let mut sums = HashMap::new();
+    for face in segments.iter().flat_map(|segment| &segment.faces) {
+        if face.smoothing_group == 0 {
+            continue;
+        }
+        let normal = face_normal(face, positions);
+        for corner in face.corners.iter().filter(|corner| corner.normal.is_none()) {
+            *sums
+                .entry((corner.position, face.smoothing_group))
+                .or_insert(Vec3::ZERO) += normal;
+        }
+    }
+    sums
+}
+
+fn face_normal(face: &Face, positions: &[Vec3]) -> Vec3 {
+    let points = face
+        .corners
+        .iter()
+        .map(|corner| positions[corner.position])
+        .collect::<Vec<_>>();
+    polygon_normal(&points)
+}
+
+fn build_mesh(
+    segment: Segment,
+    positions: &[Vec3],
+    tex_coords: &[Vec2],
+    normals: &[Vec3],
+    smooth_normals: &HashMap<(usize, u32), Vec3>,
+) -> ObjMesh {
+    let mut mesh = ObjMesh {
+        name: segment.name,
+        material: segment.material,
+        vertices: vec![],
+        indices: vec![],
+    };
+    let mut vertex_indices: HashMap<(usize, Option<usize>, NormalSource), u32> = HashMap::new();
+
+    for (face_index, face) in segment.faces.iter().enumerate() {
+        let points = face
+            .corners
+            .iter()
+            .map(|corner| positions[corner.position])
+            .collect::<Vec<_>>();
+
+        for triangle in triangulate(&points) {
+            for corner in triangle.map(|i| face.corners[i]) {
+                let normal_source = match (corner.normal, face.smoothing_group) {
+                    (Some(normal), _) => NormalSource::File(normal),
+                    (None, 0) => NormalSource::Flat(face_index),
+                    (None, group) => NormalSource::Smooth(group),
+                };
+                let key = (corner.position, corner.tex_coord, normal_source);
+
+                let index = *vertex_indices.entry(key).or_insert_with(|| {
+                    let normal = match normal_source {
+                        NormalSource::File(normal) => normals[normal],
+                        NormalSource::Smooth(group) => smooth_normals[&(corner.position, group)],
+                        NormalSource::Flat(_) => polygon_normal(&points),
+                    }
+                    .normalize_or_zero();
+                    let tex_coord = corner.tex_coord.map_or(Vec2::ZERO, |i| tex_coords[i]);
+
+                    mesh.vertices.extend(positions[corner.position].to_array());
+                    mesh.vertices.extend(normal.to_array());
+                    mesh.vertices.extend(tex_coord.to_array());
+                    (mesh.vertices.len() / OBJ_VERTEX_STRIDE as usize - 1) as u32
+                });
+                mesh.indices.push(index);
+            }
+        }
+    }
+
+    mesh
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(source: &str) -> Result<ObjModel, String> {
+        parse_obj(source, Path::new("model.obj"), |path| {
+            Err(format!("{} not found", path.display()))
+        })
+    }
+
+    fn normal(mesh: &ObjMesh, vertex: usize) -> Vec3 {
+        let start = vertex * OBJ_VERTEX_STRIDE as usize + 3;
+        Vec3::from_slice(&mesh.vertices[start..start + 3])
+    }
+
+    #[test]
+    fn test_triangle_with_attributes() {
+        let model = parse(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\nf 1/1/1 2/2/1 3/3/1\n",
+        )
+        .unwrap();
+
+        assert_eq!(model.meshes.len(), 1);
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+        assert_eq!(
+            &mesh.vertices[8..16],
+            &[1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_quad_shares_vertices() {
+        let model = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf -4 -3 -2 -1\n").unwrap();
+
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.indices.len(), 6);
+        // No normals in the file, so a flat one is generated.
+        assert_eq!(normal(mesh, 0), Vec3::Z);
+    }
+
+    #[test]
+    fn test_concave_polygon() {
+        // An arrow head pointing up, concave at the second vertex.
+        let points = [
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 1.0, 0.0),
+            vec3(2.0, 0.0, 0.0),
+            vec3(1.0, 2.0, 0.0),
+        ];
+        let triangles = triangulate(&points);
+
+        assert_eq!(triangles.len(), 2);
+        // Fanning from the first vertex would create a triangle outside the polygon.
+        assert!(!triangles.contains(&[0, 1, 2]));
+        let area = triangles
+            .iter()
+            .map(|[a, b, c]| (points[*b] - points[*a]).cross(points[*c] - points[*a]).z / 2.0)
+            .sum::<f32>();
+        assert_eq!(area, 1.0);
+    }
+
+    #[test]
+    fn test_smoothing_groups() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\n";
+        let flat = parse(&format!("{source}s off\nf 1 2 3\nf 1 4 2\n")).unwrap();
+        let smooth = parse(&format!("{source}s 1\nf 1 2 3\nf 1 4 2\n")).unwrap();
+
+        // Flat faces need their own copy of the shared edge.
+        assert_eq!(flat.meshes[0].vertex_count(), 6);
+        assert_eq!(smooth.meshes[0].vertex_count(), 4);
+        assert_eq!(
+            normal(&smooth.meshes[0], 0),
+            vec3(0.0, 1.0, 1.0).normalize()
+        );
+    }
+
+    #[test]
+    fn test_groups_and_materials() {
+        let model = parse_obj(
+            "mtllib box.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\ng a\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 2 3\ng b\nf 1 2 3\n",
+            Path::new("models/box.obj"),
+            |path| {
+                assert_eq!(path, Path::new("models/box.mtl"));
+                Ok("newmtl red\nKd 1 0 0\nmap_Kd -s 1 1 1 red.png\nmap_Bump -bm 0.5 red_h.png\nnewmtl blue\nKd 0 0 1\nNs 64\nnorm blue_n.png\n".to_string())
+            },
+        )
+        .unwrap();
+
+        let meshes = model
+            .meshes
+            .iter()
+            .map(|mesh| (mesh.name.as_str(), mesh.material.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            meshes,
+            vec![("a", Some("red")), ("a", Some("blue")), ("b", Some("blue"))]
+        );
+
+        let red = &model.materials["red"];
+        assert_eq!(red.diffuse, Vec3::X);
+        assert_eq!(red.diffuse_map, Some(PathBuf::from("models/red.png")));
+        // Bump maps are heights, which the normals are derived from.
+        assert_eq!(red.normal_map, None);
+        assert_eq!(red.bump_map, Some(PathBuf::from("models/red_h.png")));
+        assert_eq!(red.bump_multiplier, 0.5);
+        assert!(red.material().normal_map);
+        assert!(red.material().needs_tangents());
+
+        let blue = &model.materials["blue"];
+        assert_eq!(blue.material().shininess, 64.0);
+        assert!(!blue.material().diffuse_map);
+        assert_eq!(blue.normal_map, Some(PathBuf::from("models/blue_n.png")));
+        assert_eq!(blue.bump_map, None);
+    }
+
+    #[test]
+    fn test_errors_have_line_numbers() {
+        assert_eq!(
+            parse("v 0 0 0\nv 1 0 0\nf 1 2 3\n").err().unwrap(),
+            "model.obj:3: index 3 out of range"
+        );
+        assert_eq!(
+            parse("v 0 0\n").err().unwrap(),
+            "model.obj:1: missing number"
+        );
+        assert_eq!(
+            parse("\nmtllib missing.mtl\n").err().unwrap(),
+            "model.obj:2: failed to read mtllib: missing.mtl not found"
+        );
+        assert_eq!(
+            parse_mtl("Kd 1 1 1\n", Path::new("a.mtl")).err().unwrap(),
+            "a.mtl:1: Kd before the first newmtl"
+        );
+    }
+}
@@ -1,88 +1,277 @@
 use std::rc::Rc;
 
-use glam::{Mat4, Vec3};
+use glam::Vec3;
 
 use crate::{
-    gl::{types::GLfloat, Gl},
-    mesh::{Mesh, VertexBuffer},
-    renderer::texture::TextureManager,
-    shader::{Shader, ShaderTrait},
+    gl::{self, Gl},
+    mesh::{layout::VertexLayout, Mesh},
+    renderer::{
+        asset_cache::{AssetCache, MeshAsset},
+        stats::FrameStats,
+        texture::{height_map, load_image, normal_map_from_height, TextureManager, TextureSampler},
+    },
+    scene::Transform,
+    shader::{cache::ShaderCache, uniform::uniform_struct, Shader, ShaderDefines, ShaderTrait},
 };
 
+pub const LIT_OBJECT_VERTEX_SHADER: &str = "src/shader/light_vert.glsl";
+pub const LIT_OBJECT_FRAGMENT_SHADER: &str = "src/shader/lit_object_frag.glsl";
+
 const SHININESS_DEFAULT: f32 = 32.0;
+const HEIGHT_SCALE_DEFAULT: f32 = 0.04;
+/// Height difference between the container's rim and wood, in texels.
+const CONTAINER_BUMP_STRENGTH: f32 = 8.0;
 
 pub struct Cube {
     mesh: Mesh,
     shader: Rc<Shader>,
+    texture_manager: TextureManager,
     material: Material,
 }
+#[derive(Clone, Debug, PartialEq)]
 pub struct Material {
+    /// Diffuse color, multiplied with the diffuse map if there is one.
+    pub diffuse: Vec3,
+    /// Specular color, multiplied with the specular map if there is one.
+    pub specular: Vec3,
     pub shininess: f32,
+    /// Sample the diffuse color from `material.diffuse`.
+    pub diffuse_map: bool,
+    /// Sample specular strength from `material.specular`.
+    pub specular_map: bool,
+    /// Perturb the normal with the tangent space normal map `material.normal`. Needs tangents
+    /// in the vertex layout.
+    pub normal_map: bool,
+    /// Shift texture coordinates by the parallax of `material.height`, whose white is the top
+    /// of the surface. Needs tangents in the vertex layout.
+    pub height_map: bool,
+    /// Depth of the height map in texture coordinates.
+    pub height_scale: f32,
+    /// Move vertices with the joints of a skeleton. Needs joints and weights in the vertex
+    /// layout, see `animation::skin`.
+    pub skinned: bool,
+}
+
+impl Material {
+    /// Whether the vertex layout needs an `aTangent` attribute for this material's maps.
+    pub fn needs_tangents(&self) -> bool {
+        self.normal_map || self.height_map
+    }
+
+    /// Defines selecting the lit object shader variant for this material.
+    pub fn defines(&self) -> ShaderDefines {
+        let mut defines = ShaderDefines::new();
+        if self.diffuse_map {
+            defines = defines.flag("HAS_DIFFUSE_MAP");
+        }
+        if self.specular_map {
+            defines = defines.flag("HAS_SPECULAR_MAP");
+        }
+        if self.needs_tangents() {
+            defines = defines.flag("HAS_TANGENTS");
+        }
+        if self.normal_map {
+            defines = defines.flag("HAS_NORMAL_MAP");
+        }
+        if self.skinned {
+            defines = defines.flag("SKINNED");
+        }
+        if self.height_map {
+            // Debug formatting keeps the decimal point GLSL needs for a float.
+            defines = defines
+                .flag("HAS_HEIGHT_MAP")
+                .with("HEIGHT_SCALE", format!("{:?}", self.height_scale));
+        }
+        defines
+    }
+}
+
+// The samplers are owned by the texture manager, and the map flags only select a variant.
+uniform_struct!(Material {
+    diffuse as "diffuseColor",
+    specular as "specularColor",
+    shininess,
+});
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            diffuse: Vec3::ONE,
+            specular: Vec3::ONE,
+            shininess: SHININESS_DEFAULT,
+            diffuse_map: true,
+            specular_map: true,
+            normal_map: false,
+            height_map: false,
+            height_scale: HEIGHT_SCALE_DEFAULT,
+            skinned: false,
+        }
+    }
+}
+
+/// The container maps, shared by every object using them. The normal and height maps are
+/// derived from the specular map, whose bright steel rim sits above the wood.
+pub fn container_textures(gl: &Gl, assets: &mut AssetCache) -> TextureManager {
+    let sampler = TextureSampler::default();
+    let mut texture_manager = TextureManager::new();
+    for (name, path, unit) in [
+        ("material.diffuse", "static/diffuse_container.png", 0),
+        ("material.specular", "static/specular_container.png", 1),
+    ] {
+        let texture = assets
+            .texture(gl, path, &sampler)
+            .unwrap_or_else(|err| panic!("\n{err}\n"));
+        texture_manager.add_texture(name, texture, unit);
+    }
+
+    let height = load_image("static/specular_container.png")
+        .map(|specular| height_map(&specular))
+        .unwrap_or_else(|err| panic!("\n{err}\n"));
+    let normal = normal_map_from_height(&height, CONTAINER_BUMP_STRENGTH);
+    let sampler = TextureSampler {
+        mag_filter: gl::LINEAR,
+        min_filter: gl::LINEAR_MIPMAP_LINEAR,
+        ..sampler
+    };
+    texture_manager.add_texture(
+        "material.normal",
+        assets.texture_from_image(gl, &normal, &sampler),
+        2,
+    );
+    texture_manager.add_texture(
+        "material.height",
+        assets.texture_from_image(gl, &height, &sampler),
+        3,
+    );
+    texture_manager
+}
+
+/// The material for `container_textures`, with the rim raised by the normal and height maps.
+/// The vertex layout needs tangents.
+pub fn container_material(parallax: bool) -> Material {
+    Material {
+        normal_map: true,
+        height_map: parallax,
+        ..Material::default()
+    }
 }
 
 impl Cube {
+    /// `scene_defines` are the renderer wide defines, e.g. the light count, that are combined
+    /// with the material's to pick the shader variant.
     pub fn new(
         gl: &Gl,
         pos: Vec3,
-        shader: Rc<Shader>,
+        shader_cache: &mut ShaderCache,
+        scene_defines: &ShaderDefines,
+        assets: &mut AssetCache,
         vertex_data: &[f32],
-        vertex_data_stride: i32,
+        vertex_layout: VertexLayout,
     ) -> Self {
-        let lit_object_vertex_buffer = VertexBuffer::new(gl, vertex_data, vertex_data_stride);
+        let vertex_buffer = assets.mesh(gl, vertex_data, vertex_layout);
+        let texture_manager = container_textures(gl, assets);
 
-        lit_object_vertex_buffer.set_float_attribute_position(gl, "aPos", shader.get_id(), 0, 3);
-        lit_object_vertex_buffer.set_float_attribute_position(gl, "aNormal", shader.get_id(), 3, 3);
-        lit_object_vertex_buffer.set_float_attribute_position(
+        Self::with_geometry(
             gl,
-            "aTexCoords",
-            shader.get_id(),
-            6,
-            2,
-        );
+            pos,
+            vertex_buffer,
+            Material::default(),
+            texture_manager,
+            shader_cache,
+            scene_defines,
+        )
+    }
 
-        let mut texture_manager = TextureManager::new();
-        texture_manager.create_texture(
-            gl,
-            "material.diffuse",
-            "static/diffuse_container.png",
-            &shader,
-            0,
-        );
-        texture_manager.create_texture(
+    /// A lit object drawing any geometry with the lit object shader. `vertex_buffer` needs
+    /// positions, normals and texture coordinates in its layout, plus tangents if
+    /// `material.needs_tangents()`, and `texture_manager` the maps `material` uses, named
+    /// `material.diffuse`, `material.specular`, `material.normal` and `material.height` on
+    /// units 0 to 3.
+    pub fn with_geometry(
+        gl: &Gl,
+        pos: Vec3,
+        vertex_buffer: impl Into<Rc<MeshAsset>>,
+        material: Material,
+        texture_manager: TextureManager,
+        shader_cache: &mut ShaderCache,
+        scene_defines: &ShaderDefines,
+    ) -> Self {
+        let shader = shader_cache.get(
             gl,
-            "material.specular",
-            "static/specular_container.png",
-            &shader,
-            1,
+            LIT_OBJECT_VERTEX_SHADER,
+            LIT_OBJECT_FRAGMENT_SHADER,
+            &material.defines().merge(scene_defines),
         );
-        texture_manager.bind_texture(gl, "material.diffuse", 0);
-        texture_manager.bind_texture(gl, "material.specular", 1);
+
+        let vertex_buffer = vertex_buffer.into();
+        vertex_buffer
+            .bind_attributes(gl, &shader)
+            .unwrap_or_else(|err| panic!("\n{err}\n"));
+
+        texture_manager.sync_samplers(gl, &shader);
         Self {
-            mesh: Mesh::new(pos, lit_object_vertex_buffer),
+            mesh: Mesh::new(pos, vertex_buffer),
             shader,
-            material: Material {
-                shininess: SHININESS_DEFAULT,
-            },
+            texture_manager,
+            material,
         }
     }
     pub fn adjust_blend(&mut self, blend: f32) {
         self.mesh.adjust_blend(blend)
     }
-    pub fn draw(&mut self, gl: &Gl, view_matrix: Mat4) {
+
+    /// Place the object, and stop it spinning around on its own.
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.mesh.set_transform(transform);
+        self.mesh.set_spinning(false);
+    }
+    pub fn draw(&mut self, gl: &Gl, stats: &mut FrameStats) {
         self.update_material_uniforms(gl);
-        self.mesh.draw(gl, view_matrix, &self.shader);
+        // Objects can have different maps, so they are bound per draw.
+        self.texture_manager.bind_textures(gl);
+        self.mesh.draw(gl, &self.shader, stats);
     }
-    pub fn adjust_zoom(&mut self, zoom: GLfloat) {
-        self.mesh.adjust_zoom(zoom);
+
+    /// Re-apply the uniforms that are only set once, e.g. after the shader was reloaded.
+    pub fn sync_state(&self, gl: &Gl) {
+        self.texture_manager.sync_samplers(gl, &self.shader);
+    }
+
+    /// Switch to the shader variant matching the current material and `scene_defines`.
+    pub fn request_shader(
+        &mut self,
+        gl: &Gl,
+        shader_cache: &mut ShaderCache,
+        scene_defines: &ShaderDefines,
+    ) {
+        let shader = shader_cache.get(
+            gl,
+            LIT_OBJECT_VERTEX_SHADER,
+            LIT_OBJECT_FRAGMENT_SHADER,
+            &self.material.defines().merge(scene_defines),
+        );
+        if !Rc::ptr_eq(&shader, &self.shader) {
+            self.shader = shader;
+            self.sync_state(gl);
+        }
     }
 
     fn update_material_uniforms(&self, gl: &Gl) {
         self.shader
-            .set_float(gl, "material.shininess", self.material.shininess)
+            .set_struct(gl, "material", &self.material)
             .unwrap();
     }
 
-    pub fn set_material(&mut self, material: Material) {
+    /// Changing the material can change the shader variant, so this also requests the
+    /// matching shader.
+    pub fn set_material(
+        &mut self,
+        gl: &Gl,
+        material: Material,
+        shader_cache: &mut ShaderCache,
+        scene_defines: &ShaderDefines,
+    ) {
         self.material = material;
+        self.request_shader(gl, shader_cache, scene_defines);
     }
 }
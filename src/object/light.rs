@@ -1,19 +1,24 @@
 use std::rc::Rc;
 
-use glam::{vec3, Mat4, Vec3};
+use glam::{vec3, Vec3};
 
 use crate::{
-    gl::{types::GLfloat, Gl},
-    mesh::{Mesh, VertexBuffer},
-    shader::{Shader, ShaderTrait},
+    gl::Gl,
+    mesh::{layout::VertexLayout, Mesh},
+    object::cube::LIT_OBJECT_VERTEX_SHADER,
+    renderer::{asset_cache::AssetCache, stats::FrameStats, uniform_buffer::Std140Writer},
+    shader::{cache::ShaderCache, uniform::uniform_struct, Shader, ShaderDefines},
 };
 
+pub const LIGHT_SOURCE_FRAGMENT_SHADER: &str = "src/shader/light_source_frag.glsl";
+
 const POSITION_DEFAULT: Vec3 = vec3(0.0, 2.0, 0.0);
 
 const AMBIENT_STRENGTH_DEFAULT: Vec3 = vec3(0.2, 0.2, 0.2);
 const DIFFUSE_STRENGTH_DEFAULT: Vec3 = vec3(0.5, 0.5, 0.5);
 const SPECULAR_STRENGTH_DEFAULT: Vec3 = vec3(1.0, 1.0, 1.0);
 
+#[derive(Clone, Debug)]
 pub struct LightAttributes {
     pub position: Vec3,
     // Strength of each type of lighting
@@ -29,9 +34,19 @@ pub struct LightAttributes {
 pub struct Light {
     mesh: Mesh,
     shader: Rc<Shader>,
-    lit_object_shader: Rc<Shader>,
     attrs: LightAttributes,
 }
+// For programs taking a single `Light` uniform instead of the `Lights` block.
+uniform_struct!(LightAttributes {
+    position,
+    ambient,
+    diffuse,
+    specular,
+    constant,
+    linear,
+    quadratic,
+});
+
 impl Default for LightAttributes {
     fn default() -> Self {
         Self {
@@ -47,25 +62,63 @@ impl Default for LightAttributes {
     }
 }
 
+impl LightAttributes {
+    /// Write these attributes as a `Light` struct of the `Lights` uniform block.
+    pub fn write_std140(&self, writer: &mut Std140Writer) {
+        writer.structure(|writer| {
+            writer
+                .vec3(self.position)
+                .vec3(self.ambient)
+                .vec3(self.diffuse)
+                .vec3(self.specular)
+                .float(self.constant)
+                .float(self.linear)
+                .float(self.quadratic);
+        });
+    }
+
+    /// Distance at which the attenuation drops to `cutoff`, e.g. 5/256 for where the light
+    /// stops visibly adding to 8 bit colors. Infinite for lights that never get that dim.
+    pub fn attenuation_radius(&self, cutoff: f32) -> f32 {
+        // Solve constant + linear * d + quadratic * d² = 1 / cutoff for d.
+        let c = self.constant - 1.0 / cutoff;
+        if c >= 0.0 {
+            0.0
+        } else if self.quadratic > 0.0 {
+            let discriminant = self.linear * self.linear - 4.0 * self.quadratic * c;
+            (-self.linear + discriminant.sqrt()) / (2.0 * self.quadratic)
+        } else if self.linear > 0.0 {
+            -c / self.linear
+        } else {
+            f32::INFINITY
+        }
+    }
+}
+
 impl Light {
-    /// Create a new light source. Leave attrs as None for default values.
+    /// Create a new light source. Leave attrs as None for default values. `scene_defines` are
+    /// the defines every lit object is built with, e.g. `MAX_LIGHTS`.
     pub fn new(
         gl: &Gl,
         attrs: Option<LightAttributes>,
-        lit_object_shader: Rc<Shader>,
+        shader_cache: &mut ShaderCache,
+        assets: &mut AssetCache,
         vertex_data: &[f32],
-        vertex_data_stride: i32,
+        vertex_layout: VertexLayout,
+        scene_defines: &ShaderDefines,
     ) -> Self {
         let attrs = attrs.unwrap_or_default();
-        let shader = Rc::new(Shader::new(
+        let shader = shader_cache.get(
             gl,
-            "src/shader/light_vert.glsl",
-            "src/shader/light_source_frag.glsl",
-        ));
-
-        let vertex_buffer = VertexBuffer::new(gl, vertex_data, vertex_data_stride);
+            LIT_OBJECT_VERTEX_SHADER,
+            LIGHT_SOURCE_FRAGMENT_SHADER,
+            scene_defines,
+        );
 
-        vertex_buffer.set_float_attribute_position(gl, "aPos", shader.get_id(), 0, 3);
+        let vertex_buffer = assets.mesh(gl, vertex_data, vertex_layout);
+        vertex_buffer
+            .bind_attributes(gl, &shader)
+            .unwrap_or_else(|err| panic!("\n{err}\n"));
 
         let mut mesh = Mesh::new(attrs.position, vertex_buffer);
 
@@ -74,55 +127,36 @@ impl Light {
         Self {
             mesh,
             shader,
-            lit_object_shader,
             attrs,
         }
     }
 
-    pub fn set_pos(&mut self, gl: &Gl, pos: Vec3) {
+    pub fn set_pos(&mut self, pos: Vec3) {
         self.attrs.position = pos;
-        self.sync_state(gl);
+        self.sync_state();
     }
 
-    pub fn set_attrs(&mut self, gl: &Gl, attrs: LightAttributes) {
+    pub fn set_attrs(&mut self, attrs: LightAttributes) {
         self.attrs = attrs;
-        self.sync_state(gl);
+        self.sync_state();
     }
 
-    fn sync_state(&mut self, gl: &Gl) {
+    /// The attributes are uploaded with the rest of the lights through the `Lights` uniform
+    /// block, only the mesh has to follow them.
+    fn sync_state(&mut self) {
         self.mesh.set_pos(self.attrs.position);
-        self.lit_object_shader
-            .set_vec3(gl, "light.position", self.attrs.position.into())
-            .unwrap();
-        self.lit_object_shader
-            .set_vec3(gl, "light.ambient", self.attrs.ambient.into())
-            .unwrap();
-        self.lit_object_shader
-            .set_vec3(gl, "light.diffuse", self.attrs.diffuse.into())
-            .unwrap();
-        self.lit_object_shader
-            .set_vec3(gl, "light.specular", self.attrs.specular.into())
-            .unwrap();
-        self.lit_object_shader
-            .set_float(gl, "light.constant", self.attrs.constant)
-            .unwrap();
-        self.lit_object_shader
-            .set_float(gl, "light.linear", self.attrs.linear)
-            .unwrap();
-        self.lit_object_shader
-            .set_float(gl, "light.quadratic", self.attrs.quadratic)
-            .unwrap();
+    }
+
+    pub fn attrs(&self) -> &LightAttributes {
+        &self.attrs
     }
 
     pub fn pos(&self) -> Vec3 {
         self.attrs.position
     }
 
-    pub fn draw(&mut self, gl: &Gl, view_matrix: Mat4) {
+    pub fn draw(&mut self, gl: &Gl, stats: &mut FrameStats) {
         // I should probably not have draw mutate.
-        self.mesh.draw(gl, view_matrix, &self.shader);
-    }
-    pub fn adjust_zoom(&mut self, degrees: GLfloat) {
-        self.mesh.adjust_zoom(degrees);
+        self.mesh.draw(gl, &self.shader, stats);
     }
 }
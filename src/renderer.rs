@@ -1,26 +1,118 @@
+pub mod asset_cache;
+pub mod batch;
+pub mod debug_draw;
+pub mod overlay;
+pub mod stats;
+pub mod storage_buffer;
+pub mod stream_buffer;
 pub mod texture;
+pub mod uniform_buffer;
 
-use std::{ffi::CString, rc::Rc};
+use std::ffi::CString;
 
-use glam::{vec3, Vec3};
+use asset_cache::AssetCache;
+use batch::{BatchRenderer, MaterialHandle, MeshHandle};
+use debug_draw::{DebugDraw, DebugRenderer, DEBUG_GRAY, DEBUG_GREEN, DEBUG_RED, DEBUG_YELLOW};
+use overlay::Overlay;
+use stats::FrameStats;
+use uniform_buffer::{
+    FrameUniforms, LightsUniforms, UniformBuffer, FRAME_BLOCK_BINDING, LIGHTS_BLOCK_BINDING,
+};
+
+use glam::{vec3, Mat4, Quat, Vec2, Vec3, Vec4};
 use glutin::prelude::GlDisplay;
 use winit::keyboard::KeyCode;
 
 use crate::{
+    animation::{
+        clip::{Channel, Clip, Interpolation, Keyframes},
+        controller::AnimationController,
+        skeleton::Skeleton,
+        skin::{JointBuffer, SkinnedVertex},
+    },
     camera::Camera,
     gl::{self, types::GLfloat, Gl},
     logging::setup_logging,
-    object::{cube::Cube, light::Light},
-    shader::Shader,
+    mesh::{
+        layout::{StandardVertex, Vertex},
+        lod::{LodGroup, LodLevel, LodState, LodThreshold, LodView},
+        primitives::{self, PrimitiveVertex, PRIMITIVE_VERTEX_STRIDE},
+        random_spin, simplify, tangents, VertexBuffer, SPIN_AXIS,
+    },
+    object::{
+        cube::{
+            container_material, container_textures, Cube, Material, LIT_OBJECT_FRAGMENT_SHADER,
+            LIT_OBJECT_VERTEX_SHADER,
+        },
+        light::Light,
+    },
+    scene::{NodeId, SceneGraph, Transform},
+    shader::{binary_cache::ProgramBinaryCache, cache::ShaderCache, ShaderDefines},
     timer::Timer,
 };
 
 type PositionDelta2D = (f64, f64);
 
+/// Seconds between checks of the shader sources for changes.
+const SHADER_RELOAD_INTERVAL: f32 = 0.5;
+
+const FOG_DENSITY: f32 = 0.08;
+
+/// Spheres in a row away from the camera, showing the levels of detail switch.
+const LOD_SPHERE_COUNT: usize = 6;
+const LOD_SPHERE_SPACING: f32 = 5.0;
+const LOD_FADE_SECONDS: f32 = 0.3;
+
+/// Attenuation the light's radius is drawn at, where it stops visibly lighting anything.
+const LIGHT_RADIUS_CUTOFF: f32 = 5.0 / 256.0;
+const GROUND_GRID_HEIGHT: f32 = -3.0;
+/// Colors of the LOD spheres' bounds by the level they use.
+const LOD_LEVEL_COLORS: [Vec4; 3] = [DEBUG_GREEN, DEBUG_YELLOW, DEBUG_RED];
+
+/// Where the skinned column stands, clear of the cubes and the spheres.
+const RIG_POSITION: Vec3 = vec3(5.0, -3.0, -4.0);
+const RIG_SEGMENTS: usize = 3;
+const RIG_SEGMENT_LENGTH: f32 = 1.0;
+const ANIMATION_FADE_SECONDS: f32 = 0.4;
+
+/// The skinned column and the clips moving it.
+struct Rig {
+    skeleton: Skeleton,
+    controller: AnimationController,
+    joints: JointBuffer,
+}
+
 pub struct Renderer {
+    shader_cache: ShaderCache,
+    assets: AssetCache,
+    /// Defines every lit program variant is compiled with.
+    scene_defines: ShaderDefines,
+    fog: bool,
+    /// Whether the containers use parallax mapping on top of their normal maps.
+    parallax: bool,
     light_source: Light,
+    /// Lit objects drawn one at a time, the skinned column among them.
     lit_objects: Vec<Cube>,
+    rig: Rig,
+    batches: BatchRenderer,
+    cube_mesh: MeshHandle,
+    cube_material: MaterialHandle,
+    scene: SceneGraph,
+    /// The cubes at `LIT_CUBE_POSITIONS`, drawn in a single instanced batch.
+    cubes: Vec<NodeId>,
+    sphere_lods: LodGroup<MeshHandle>,
+    spheres: Vec<(NodeId, LodState)>,
+    light_node: NodeId,
     camera: Camera,
+    overlay: Overlay,
+    debug_draw: DebugDraw,
+    debug_renderer: DebugRenderer,
+    /// Whether the light, bounds and world axes are drawn.
+    show_scene_debug: bool,
+    frame_uniforms: UniformBuffer,
+    lights_uniforms: UniformBuffer,
+    stats: FrameStats,
+    last_shader_reload_check: f32,
     gl: Gl,
 }
 
@@ -32,38 +124,143 @@ impl Renderer {
         });
 
         unsafe { gl.Enable(gl::DEPTH_TEST) };
-        setup_logging(&gl);
+        let gl_info = setup_logging(&gl);
 
-        let lit_object_program = Rc::new(Shader::new(
+        let mut shader_cache = ShaderCache::with_binary_cache(ProgramBinaryCache::new(
             &gl,
-            "src/shader/light_vert.glsl",
-            "src/shader/lit_object_frag.glsl",
+            ProgramBinaryCache::default_dir(),
+            &gl_info,
         ));
+        let scene_defines = scene_defines(false);
+
+        let lit_object_program = shader_cache.get(
+            &gl,
+            LIT_OBJECT_VERTEX_SHADER,
+            LIT_OBJECT_FRAGMENT_SHADER,
+            &Material::default().defines().merge(&scene_defines),
+        );
+
+        let frame_uniforms = UniformBuffer::new(&gl, FRAME_BLOCK_BINDING, FrameUniforms::SIZE);
+        let lights_uniforms = UniformBuffer::new(&gl, LIGHTS_BLOCK_BINDING, LightsUniforms::SIZE);
+        frame_uniforms
+            .check_block(&lit_object_program, "Frame")
+            .unwrap();
+        lights_uniforms
+            .check_block(&lit_object_program, "Lights")
+            .unwrap();
 
+        let mut assets = AssetCache::new();
+        // The cube isn't indexed, so every vertex is its own index.
+        let cube_vertex_count =
+            std::mem::size_of_val(&VERTEX_DATA) / StandardVertex::layout().stride(0) as usize;
+        let cube_indices = (0..cube_vertex_count as u32).collect::<Vec<_>>();
+        // Every corner already has a vertex of its own, so none are split and the order stays.
+        let (cube_vertices, _) = tangents::with_tangents(&VERTEX_DATA, &cube_indices);
         let light_source = Light::new(
             &gl,
             None,
-            Rc::clone(&lit_object_program),
-            &VERTEX_DATA,
-            VERTEX_DATA_STRIDE,
+            &mut shader_cache,
+            &mut assets,
+            &cube_vertices,
+            PrimitiveVertex::layout(),
+            &scene_defines,
         );
 
-        let lit_objects = Vec::from(LIT_CUBE_POSITIONS.map(|pos| {
-            Cube::new(
-                &gl,
-                pos,
-                Rc::clone(&lit_object_program),
-                &VERTEX_DATA,
-                VERTEX_DATA_STRIDE,
+        // The light and the cubes share the cube geometry.
+        let mut batches = BatchRenderer::new();
+        let cube_mesh =
+            batches.add_mesh(assets.mesh(&gl, &cube_vertices, PrimitiveVertex::layout()));
+        let cube_material = batches
+            .add_material(
+                container_material(false),
+                container_textures(&gl, &mut assets),
             )
-        }));
+            .unwrap_or_else(|err| panic!("\n{err}\n"));
+
+        let mut scene = SceneGraph::new();
+        let cubes_root = scene.add_node("cubes", Transform::IDENTITY);
+        let cubes = LIT_CUBE_POSITIONS
+            .iter()
+            .enumerate()
+            .map(|(i, pos)| {
+                let local = Transform {
+                    rotation: random_spin(),
+                    ..Transform::from_translation(*pos)
+                };
+                let cube = scene.add_child(cubes_root, &format!("cube {i}"), local);
+                scene.set_bounds(cube, batches.mesh_bounds(cube_mesh));
+                cube
+            })
+            .collect();
+        let sphere_lods = sphere_lods(&gl, &mut assets, &mut batches);
+        let spheres_root = scene.add_node("spheres", Transform::IDENTITY);
+        let spheres = (0..LOD_SPHERE_COUNT)
+            .map(|i| {
+                let pos = vec3(-6.0, 0.0, -2.0 - i as f32 * LOD_SPHERE_SPACING);
+                let sphere = scene.add_child(
+                    spheres_root,
+                    &format!("sphere {i}"),
+                    Transform::from_translation(pos),
+                );
+                scene.set_bounds(sphere, batches.mesh_bounds(*sphere_lods.mesh(0)));
+                (sphere, LodState::default())
+            })
+            .collect();
+        let light_node = scene.add_node("light", Transform::from_translation(light_source.pos()));
+
+        let (rig, rig_mesh) = rig(&gl);
+        let rig_material = Material {
+            skinned: true,
+            ..Material::default()
+        };
+        let skinned_program = shader_cache.get(
+            &gl,
+            LIT_OBJECT_VERTEX_SHADER,
+            LIT_OBJECT_FRAGMENT_SHADER,
+            &rig_material.defines().merge(&scene_defines),
+        );
+        rig.joints.check_block(&skinned_program).unwrap();
+        let mut rig_object = Cube::with_geometry(
+            &gl,
+            RIG_POSITION,
+            rig_mesh,
+            rig_material,
+            container_textures(&gl, &mut assets),
+            &mut shader_cache,
+            &scene_defines,
+        );
+        rig_object.set_transform(Transform::from_translation(RIG_POSITION));
 
         let camera = Camera::new();
+        let overlay = Overlay::new(&gl, &mut shader_cache);
+        let debug_renderer = DebugRenderer::new(&gl, &mut shader_cache);
         Self {
+            shader_cache,
+            assets,
+            scene_defines,
+            fog: false,
+            parallax: false,
             light_source,
-            lit_objects,
+            lit_objects: vec![rig_object],
+            rig,
+            batches,
+            cube_mesh,
+            cube_material,
+            scene,
+            cubes,
+            sphere_lods,
+            spheres,
+            light_node,
             gl,
             camera,
+            overlay,
+            debug_draw: DebugDraw::new(),
+            debug_renderer,
+            show_scene_debug: false,
+            frame_uniforms,
+            lights_uniforms,
+            stats: FrameStats::new(),
+            last_shader_reload_check: 0.0,
         }
     }
 
@@ -89,16 +286,81 @@ impl Renderer {
     }
 
     pub fn draw(&mut self, timer: &Timer) {
+        if timer.elapsed() - self.last_shader_reload_check >= SHADER_RELOAD_INTERVAL {
+            self.last_shader_reload_check = timer.elapsed();
+            self.reload_shaders();
+        }
         self.draw_with_clear_color(timer, 0.1, 0.1, 0.1, 0.9);
+        self.overlay.draw(
+            &self.gl,
+            &self.stats,
+            &self.assets.stats(),
+            timer,
+            &self.camera,
+        );
     }
 
-    pub fn adjust_zoom(&mut self, degrees: GLfloat) {
-        self.light_source.adjust_zoom(degrees);
-        for mesh in &mut self.lit_objects {
-            mesh.adjust_zoom(degrees);
+    /// Recompile any shader whose source changed on disk and restore the uniforms it lost.
+    fn reload_shaders(&mut self) {
+        // Uniform blocks keep their binding points across a reload, only the plain uniforms
+        // need restoring.
+        if self.shader_cache.reload_if_changed(&self.gl) {
+            for lit_object in &self.lit_objects {
+                lit_object.sync_state(&self.gl);
+            }
+            self.batches.sync_state(&self.gl);
+        }
+    }
+
+    /// The scene's transforms and bounds, as of the last frame.
+    pub fn scene(&self) -> &SceneGraph {
+        &self.scene
+    }
+
+    pub fn toggle_overlay(&mut self) {
+        self.overlay.toggle();
+    }
+
+    /// Lines and points to draw over this frame, e.g. picked rays.
+    pub fn debug_draw(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
+    }
+
+    /// Cross-fade the skinned column to its next clip.
+    pub fn next_animation(&mut self) {
+        let controller = &mut self.rig.controller;
+        let next = controller
+            .current()
+            .map_or(0, |playback| (playback.clip + 1) % controller.clips().len());
+        controller.play(next, ANIMATION_FADE_SECONDS);
+    }
+
+    /// Show or hide the light's reach, the bounds of the objects and the world axes.
+    pub fn toggle_debug_draw(&mut self) {
+        self.show_scene_debug = !self.show_scene_debug;
+    }
+
+    /// Switch every lit object to the variant of its shader with or without fog.
+    pub fn toggle_fog(&mut self) {
+        self.fog = !self.fog;
+        self.scene_defines = scene_defines(self.fog);
+        for lit_object in &mut self.lit_objects {
+            lit_object.request_shader(&self.gl, &mut self.shader_cache, &self.scene_defines);
         }
     }
 
+    /// Switch the containers between plain normal mapping and parallax mapping.
+    pub fn toggle_parallax(&mut self) {
+        self.parallax = !self.parallax;
+        self.batches
+            .set_material(self.cube_material, container_material(self.parallax))
+            .unwrap_or_else(|err| panic!("\n{err}\n"));
+    }
+
+    pub fn adjust_zoom(&mut self, degrees: GLfloat) {
+        self.camera.adjust_zoom(degrees);
+    }
+
     pub fn resize(&self, width: i32, height: i32) {
         unsafe { self.gl.Viewport(0, 0, width, height) }
     }
@@ -111,25 +373,257 @@ impl Renderer {
         blue: GLfloat,
         alpha: GLfloat,
     ) {
+        self.stats.reset();
         unsafe {
             self.gl.ClearColor(red, green, blue, alpha);
             self.gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
 
             let time_elapsed = timer.elapsed();
-            self.light_source.set_pos(
+            self.scene.update_local(self.light_node, |local| {
+                local.translation.x = time_elapsed.sin();
+            });
+            for cube in &self.cubes {
+                self.scene.update_local(*cube, |local| {
+                    local.rotate_local(SPIN_AXIS.normalize(), 1.0);
+                });
+            }
+            self.scene.update();
+
+            self.light_source
+                .set_pos(self.scene.world_position(self.light_node));
+            self.update_uniform_buffers(timer);
+
+            self.light_source.draw(&self.gl, &mut self.stats);
+
+            let rig = &mut self.rig;
+            rig.controller.advance(timer.delta_time());
+            let pose = rig.controller.pose(&rig.skeleton);
+            rig.joints
+                .update(&self.gl, &rig.skeleton.skinning_matrices(&pose));
+
+            for lit_object in &mut self.lit_objects {
+                lit_object.draw(&self.gl, &mut self.stats)
+            }
+
+            for cube in &self.cubes {
+                self.batches.submit(
+                    self.cube_mesh,
+                    self.cube_material,
+                    self.scene.world_matrix(*cube),
+                );
+            }
+            for (sphere, lod_state) in &mut self.spheres {
+                let Some(bounds) = self.scene.world_bounds(*sphere) else {
+                    continue;
+                };
+                let view = LodView::new(&bounds.sphere, self.camera.pos(), self.camera.fov());
+                let selection = self
+                    .sphere_lods
+                    .select(lod_state, &view, timer.delta_time());
+                self.batches.submit_lod(
+                    &self.sphere_lods,
+                    &selection,
+                    self.cube_material,
+                    self.scene.world_matrix(*sphere),
+                );
+            }
+            self.batches.flush(
                 &self.gl,
-                vec3(
-                    time_elapsed.sin(),
-                    self.light_source.pos().y,
-                    self.light_source.pos().z,
-                ),
+                &mut self.shader_cache,
+                &self.scene_defines,
+                &mut self.stats,
             );
-            self.light_source.draw(&self.gl, self.camera.view_matrix());
+        }
 
-            for lit_object in &mut self.lit_objects {
-                lit_object.draw(&self.gl, self.camera.view_matrix())
+        if self.show_scene_debug {
+            self.draw_scene_debug();
+        }
+        self.debug_renderer
+            .draw(&self.gl, &mut self.debug_draw, &mut self.stats);
+        self.assets.collect_garbage(&self.gl);
+    }
+
+    fn draw_scene_debug(&mut self) {
+        let debug_draw = &mut self.debug_draw;
+        debug_draw.grid(Vec3::Y * GROUND_GRID_HEIGHT, 40.0, 40, DEBUG_GRAY);
+
+        let light = self.light_source.attrs();
+        debug_draw.sphere(
+            light.position,
+            light.attenuation_radius(LIGHT_RADIUS_CUTOFF),
+            DEBUG_YELLOW,
+        );
+        debug_draw.on_top().point(light.position, DEBUG_YELLOW);
+
+        for cube in &self.cubes {
+            if let Some(bounds) = self.scene.world_bounds(*cube) {
+                debug_draw.aabb(&bounds.aabb, DEBUG_GREEN);
+            }
+        }
+        for (sphere, lod_state) in &self.spheres {
+            if let Some(bounds) = self.scene.world_bounds(*sphere) {
+                let color = LOD_LEVEL_COLORS[lod_state.level().min(LOD_LEVEL_COLORS.len() - 1)];
+                debug_draw.sphere(bounds.sphere.center, bounds.sphere.radius, color);
             }
         }
+        debug_draw.on_top().axes(&Mat4::IDENTITY, 1.0);
+
+        let rig = &self.rig;
+        let joints = rig
+            .skeleton
+            .world_matrices(&rig.controller.pose(&rig.skeleton))
+            .iter()
+            .map(|world| RIG_POSITION + world.transform_point3(Vec3::ZERO))
+            .collect::<Vec<_>>();
+        for (joint, position) in rig.skeleton.joints().iter().zip(&joints) {
+            if let Some(parent) = joint.parent {
+                debug_draw
+                    .on_top()
+                    .line(joints[parent], *position, DEBUG_YELLOW);
+            }
+            debug_draw.on_top().point(*position, DEBUG_YELLOW);
+        }
+    }
+
+    /// Upload the state shared by every program, once per frame.
+    fn update_uniform_buffers(&self, timer: &Timer) {
+        let frame = FrameUniforms {
+            view: self.camera.view_matrix(),
+            projection: self.camera.projection_matrix(self.gl.get_aspect_ratio()),
+            camera_position: self.camera.pos(),
+            time: timer.elapsed(),
+        };
+        self.frame_uniforms.update(&self.gl, &frame.std140());
+
+        let lights = LightsUniforms::std140(&[self.light_source.attrs()]);
+        self.lights_uniforms.update(&self.gl, &lights);
+    }
+}
+
+/// A sphere at full detail and simplified to a quarter and a sixteenth of its triangles.
+fn sphere_lods(
+    gl: &Gl,
+    assets: &mut AssetCache,
+    batches: &mut BatchRenderer,
+) -> LodGroup<MeshHandle> {
+    let sphere = primitives::sphere(0.5, 48, 24);
+    let positions = sphere
+        .vertices
+        .chunks_exact(PRIMITIVE_VERTEX_STRIDE as usize)
+        .map(|vertex| Vec3::from_slice(&vertex[..3]))
+        .collect::<Vec<_>>();
+    let full = assets.indexed_mesh(
+        gl,
+        &sphere.vertices,
+        &sphere.indices,
+        PrimitiveVertex::layout(),
+    );
+
+    // Simplifying only drops triangles, so the coarser levels draw the full level's vertices
+    // through element buffers of their own. The batches keep the full level alive for them.
+    let mut levels = vec![LodLevel {
+        mesh: batches.add_mesh(full.clone()),
+        threshold: LodThreshold::ScreenSize(0.15),
+    }];
+    for (divisor, threshold) in [
+        (4, LodThreshold::ScreenSize(0.05)),
+        (16, LodThreshold::ScreenSize(0.0)),
+    ] {
+        let simplified =
+            simplify::simplify(&positions, &sphere.indices, sphere.indices.len() / divisor);
+        levels.push(LodLevel {
+            mesh: batches.add_mesh(full.with_shared_vertices(gl, &simplified.indices)),
+            threshold,
+        });
+    }
+    LodGroup::new(levels).with_fade(LOD_FADE_SECONDS)
+}
+
+/// A column of `RIG_SEGMENTS` joints stacked on each other, the mesh skinned to them and two
+/// clips: a smooth sway and a nod stepping between poses.
+fn rig(gl: &Gl) -> (Rig, VertexBuffer) {
+    let mut skeleton = Skeleton::new();
+    let mut parent = None;
+    for i in 0..RIG_SEGMENTS {
+        let offset = if i == 0 { 0.0 } else { RIG_SEGMENT_LENGTH };
+        parent = Some(skeleton.add_joint(
+            &format!("segment {i}"),
+            parent,
+            Transform::from_translation(Vec3::Y * offset),
+        ));
+    }
+
+    // Each vertex follows the segments it is closest to, so the column bends smoothly.
+    let height = RIG_SEGMENTS as f32 * RIG_SEGMENT_LENGTH;
+    let geometry = primitives::cylinder(0.25, height, 16, 4 * RIG_SEGMENTS as u32);
+    let vertices = geometry
+        .vertices
+        .chunks_exact(PRIMITIVE_VERTEX_STRIDE as usize)
+        .map(|vertex| {
+            let position = Vec3::from_slice(&vertex[..3]) + Vec3::Y * height * 0.5;
+            let influences = (0..RIG_SEGMENTS)
+                .map(|joint| {
+                    let center = (joint as f32 + 0.5) * RIG_SEGMENT_LENGTH;
+                    let distance = (position.y - center).abs() / RIG_SEGMENT_LENGTH;
+                    (joint, (1.0 - distance).max(0.0))
+                })
+                .collect::<Vec<_>>();
+            SkinnedVertex::new(
+                position,
+                Vec3::from_slice(&vertex[3..6]),
+                Vec2::from_slice(&vertex[6..8]),
+                &influences,
+            )
+        })
+        .collect::<Vec<_>>();
+    let mesh =
+        VertexBuffer::with_indices(gl, &vertices, &geometry.indices, SkinnedVertex::layout());
+
+    let sway_angles = [0.0, 25.0, 0.0, -25.0, 0.0];
+    let sway = (1..RIG_SEGMENTS)
+        .map(|joint| {
+            let rotations = sway_angles
+                .iter()
+                .map(|degrees: &f32| Quat::from_rotation_z(degrees.to_radians()))
+                .collect();
+            Channel::new(
+                joint,
+                vec![0.0, 1.0, 2.0, 3.0, 4.0],
+                Keyframes::Rotation(rotations),
+                Interpolation::Linear,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let nod = Channel::new(
+        RIG_SEGMENTS - 1,
+        vec![0.0, 0.5, 1.0, 1.5, 2.0],
+        Keyframes::Rotation(
+            [0.0, 40.0, 0.0, -40.0, 0.0]
+                .map(|degrees: f32| Quat::from_rotation_x(degrees.to_radians()))
+                .to_vec(),
+        ),
+        Interpolation::Step,
+    )
+    .unwrap();
+    let mut controller =
+        AnimationController::new(vec![Clip::new("sway", sway), Clip::new("nod", vec![nod])]);
+    controller.play(0, 0.0);
+
+    let rig = Rig {
+        skeleton,
+        controller,
+        joints: JointBuffer::new(gl),
+    };
+    (rig, mesh)
+}
+
+fn scene_defines(fog: bool) -> ShaderDefines {
+    let defines = LightsUniforms::defines();
+    if fog {
+        defines.with("FOG_DENSITY", FOG_DENSITY)
+    } else {
+        defines
     }
 }
 
@@ -147,8 +641,6 @@ static LIT_CUBE_POSITIONS: [Vec3; 10] = [
     vec3(-1.3,  1.0, -1.5)
 ];
 
-const VERTEX_DATA_STRIDE: i32 = 8;
-
 #[rustfmt::skip]
 static VERTEX_DATA: [f32; 288] = [
      // positions      // normals        // texture coords
@@ -0,0 +1,429 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    rc::{Rc, Weak},
+};
+
+use image::{ColorType, DynamicImage};
+use xxhash_rust::xxh3::{xxh3_128, Xxh3};
+
+use crate::{
+    gl::{
+        self,
+        types::{GLintptr, GLsizei, GLsizeiptr, GLuint},
+        Gl,
+    },
+    mesh::{layout::VertexLayout, VertexBuffer},
+};
+
+use super::texture::{load_image, upload_texture, TextureSampler};
+
+/// A GL object whose last handle was dropped, waiting for `AssetCache::collect_garbage`.
+enum ReleasedObject {
+    Texture(GLuint),
+    Buffer(GLuint),
+    VertexArray(GLuint),
+}
+
+/// Drop can't reach the GL context, so dropped assets queue their objects here instead.
+type ReleaseQueue = Rc<RefCell<Vec<ReleasedObject>>>;
+
+/// A texture object. Cached ones are released after their last handle drops.
+pub struct TextureAsset {
+    id: GLuint,
+    release: Option<ReleaseQueue>,
+}
+
+impl TextureAsset {
+    /// A texture the cache doesn't track, which is never released.
+    pub fn unmanaged(id: GLuint) -> Self {
+        Self { id, release: None }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+}
+
+impl Drop for TextureAsset {
+    fn drop(&mut self) {
+        if let Some(release) = &self.release {
+            release.borrow_mut().push(ReleasedObject::Texture(self.id));
+        }
+    }
+}
+
+/// Geometry in a vertex buffer. Cached ones are released after their last handle drops.
+pub struct MeshAsset {
+    vertex_buffer: VertexBuffer,
+    release: Option<ReleaseQueue>,
+}
+
+impl MeshAsset {
+    /// A vertex array drawing this mesh instanced, see `VertexBuffer::instanced`. Its own
+    /// objects are released like the mesh's once it drops.
+    pub fn instanced(&self, gl: &Gl, instance_layout: VertexLayout) -> MeshAsset {
+        MeshAsset {
+            vertex_buffer: self.vertex_buffer.instanced(gl, instance_layout),
+            release: self.release.clone(),
+        }
+    }
+
+    /// A mesh drawing these vertices through `indices`, see
+    /// `VertexBuffer::with_shared_vertices`. Like `instanced` ones, it needs this mesh alive.
+    pub fn with_shared_vertices(&self, gl: &Gl, indices: &[u32]) -> MeshAsset {
+        MeshAsset {
+            vertex_buffer: self.vertex_buffer.with_shared_vertices(gl, indices),
+            release: self.release.clone(),
+        }
+    }
+}
+
+impl Deref for MeshAsset {
+    type Target = VertexBuffer;
+
+    fn deref(&self) -> &VertexBuffer {
+        &self.vertex_buffer
+    }
+}
+
+impl DerefMut for MeshAsset {
+    fn deref_mut(&mut self) -> &mut VertexBuffer {
+        &mut self.vertex_buffer
+    }
+}
+
+/// Geometry the cache doesn't track, which is never released.
+impl From<VertexBuffer> for Rc<MeshAsset> {
+    fn from(vertex_buffer: VertexBuffer) -> Self {
+        Rc::new(MeshAsset {
+            vertex_buffer,
+            release: None,
+        })
+    }
+}
+
+impl Drop for MeshAsset {
+    fn drop(&mut self) {
+        if let Some(release) = &self.release {
+            let (vao, buffers) = self.vertex_buffer.gl_objects();
+            let mut release = release.borrow_mut();
+            release.push(ReleasedObject::VertexArray(vao));
+            release.extend(buffers.into_iter().map(ReleasedObject::Buffer));
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AssetCacheStats {
+    pub hits: u32,
+    pub misses: u32,
+    /// GL objects deleted after their asset's last handle dropped.
+    pub released: u32,
+}
+
+/// A decoded image, by a hash of its pixels. On a match the uploaded texture is read back and
+/// compared, so images whose hashes collide never share a texture.
+#[derive(PartialEq, Eq, Hash)]
+struct ImageKey {
+    width: u32,
+    height: u32,
+    color: ColorType,
+    hash: u128,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum TextureKey {
+    Path(String, TextureSampler),
+    Content(ImageKey, TextureSampler),
+}
+
+/// Geometry, by its sizes and a hash of its bytes. Floats are hashed by their bits, so e.g.
+/// `-0.0` and `0.0` are different geometry. On a match the uploaded buffers are read back and
+/// compared, like for images.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct MeshKey {
+    vertices: usize,
+    indices: Option<usize>,
+    hash: u128,
+    layout: VertexLayout,
+}
+
+/// Hands out shared handles to meshes and textures, so identical assets are only uploaded
+/// once. Textures of files are keyed by path, meshes and decoded images by their content.
+///
+/// The cache only holds weak references. Once the last handle of an asset drops, its GL
+/// objects are deleted by the next `collect_garbage`.
+#[derive(Default)]
+pub struct AssetCache {
+    textures: HashMap<TextureKey, Weak<TextureAsset>>,
+    meshes: HashMap<MeshKey, Weak<MeshAsset>>,
+    release: ReleaseQueue,
+    stats: AssetCacheStats,
+}
+
+impl AssetCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The texture at `path`, flipped like `TextureManager::create_texture`.
+    pub fn texture(
+        &mut self,
+        gl: &Gl,
+        path: &str,
+        sampler: &TextureSampler,
+    ) -> Result<Rc<TextureAsset>, String> {
+        let key = TextureKey::Path(path.to_string(), *sampler);
+        if let Some(texture) = self.cached_texture(&key) {
+            return Ok(texture);
+        }
+        let img = load_image(path)?;
+        Ok(self.insert_texture(key, upload_texture(gl, &img, sampler)))
+    }
+
+    /// A texture holding `img`, e.g. one embedded in a model file.
+    pub fn texture_from_image(
+        &mut self,
+        gl: &Gl,
+        img: &DynamicImage,
+        sampler: &TextureSampler,
+    ) -> Rc<TextureAsset> {
+        let image = ImageKey {
+            width: img.width(),
+            height: img.height(),
+            color: img.color(),
+            hash: xxh3_128(img.as_bytes()),
+        };
+        let key = TextureKey::Content(image, *sampler);
+        let texture = self
+            .textures
+            .get(&key)
+            .and_then(Weak::upgrade)
+            .filter(|texture| texture_matches(gl, texture.id(), img));
+        self.record_lookup(texture.is_some());
+        match texture {
+            Some(texture) => texture,
+            None => self.insert_texture(key, upload_texture(gl, img, sampler)),
+        }
+    }
+
+    fn cached_texture(&mut self, key: &TextureKey) -> Option<Rc<TextureAsset>> {
+        let texture = self.textures.get(key).and_then(Weak::upgrade);
+        self.record_lookup(texture.is_some());
+        texture
+    }
+
+    fn insert_texture(&mut self, key: TextureKey, id: GLuint) -> Rc<TextureAsset> {
+        let texture = Rc::new(TextureAsset {
+            id,
+            release: Some(self.release.clone()),
+        });
+        self.textures.insert(key, Rc::downgrade(&texture));
+        texture
+    }
+
+    /// Non indexed geometry.
+    pub fn mesh(&mut self, gl: &Gl, vertices: &[f32], layout: VertexLayout) -> Rc<MeshAsset> {
+        self.get_mesh(gl, vertices, None, layout, |layout| {
+            VertexBuffer::new(gl, vertices, layout)
+        })
+    }
+
+    pub fn indexed_mesh(
+        &mut self,
+        gl: &Gl,
+        vertices: &[f32],
+        indices: &[u32],
+        layout: VertexLayout,
+    ) -> Rc<MeshAsset> {
+        self.get_mesh(gl, vertices, Some(indices), layout, |layout| {
+            VertexBuffer::with_indices(gl, vertices, indices, layout)
+        })
+    }
+
+    fn get_mesh(
+        &mut self,
+        gl: &Gl,
+        vertices: &[f32],
+        indices: Option<&[u32]>,
+        layout: VertexLayout,
+        create: impl FnOnce(VertexLayout) -> VertexBuffer,
+    ) -> Rc<MeshAsset> {
+        let key = mesh_key(vertices, indices, layout.clone());
+        let mesh = self
+            .meshes
+            .get(&key)
+            .and_then(Weak::upgrade)
+            .filter(|mesh| {
+                buffer_matches(gl, mesh.vbo(), as_bytes(vertices))
+                    && match (mesh.ebo(), indices) {
+                        (Some(ebo), Some(indices)) => buffer_matches(gl, ebo, as_bytes(indices)),
+                        (ebo, indices) => ebo.is_none() && indices.is_none(),
+                    }
+            });
+        self.record_lookup(mesh.is_some());
+        if let Some(mesh) = mesh {
+            return mesh;
+        }
+
+        let mesh = Rc::new(MeshAsset {
+            vertex_buffer: create(layout),
+            release: Some(self.release.clone()),
+        });
+        self.meshes.insert(key, Rc::downgrade(&mesh));
+        mesh
+    }
+
+    fn record_lookup(&mut self, hit: bool) {
+        if hit {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+    }
+
+    /// Delete the GL objects of every asset whose last handle dropped, and forget the dead
+    /// cache entries. Returns the number of deleted objects.
+    pub fn collect_garbage(&mut self, gl: &Gl) -> usize {
+        let released = std::mem::take(&mut *self.release.borrow_mut());
+        for object in &released {
+            unsafe {
+                match object {
+                    ReleasedObject::Texture(id) => gl.DeleteTextures(1, id),
+                    ReleasedObject::Buffer(id) => gl.DeleteBuffers(1, id),
+                    ReleasedObject::VertexArray(id) => gl.DeleteVertexArrays(1, id),
+                }
+            }
+        }
+        if !released.is_empty() {
+            self.textures
+                .retain(|_, texture| texture.strong_count() > 0);
+            self.meshes.retain(|_, mesh| mesh.strong_count() > 0);
+        }
+
+        self.stats.released += released.len() as u32;
+        released.len()
+    }
+
+    pub fn stats(&self) -> AssetCacheStats {
+        self.stats
+    }
+
+    /// Textures with at least one handle left.
+    pub fn live_textures(&self) -> usize {
+        self.textures
+            .values()
+            .filter(|texture| texture.strong_count() > 0)
+            .count()
+    }
+
+    /// Meshes with at least one handle left.
+    pub fn live_meshes(&self) -> usize {
+        self.meshes
+            .values()
+            .filter(|mesh| mesh.strong_count() > 0)
+            .count()
+    }
+}
+
+fn mesh_key(vertices: &[f32], indices: Option<&[u32]>, layout: VertexLayout) -> MeshKey {
+    let mut hasher = Xxh3::new();
+    hasher.update(as_bytes(vertices));
+    if let Some(indices) = indices {
+        hasher.update(as_bytes(indices));
+    }
+    MeshKey {
+        vertices: vertices.len(),
+        indices: indices.map(<[u32]>::len),
+        hash: hasher.digest128(),
+        layout,
+    }
+}
+
+fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    // Plain numbers without padding, so every byte is initialized.
+    unsafe { std::slice::from_raw_parts(data.as_ptr().cast(), std::mem::size_of_val(data)) }
+}
+
+/// Whether the start of buffer `id` holds `bytes`, to tell a hash collision from a match.
+fn buffer_matches(gl: &Gl, id: GLuint, bytes: &[u8]) -> bool {
+    let mut contents = vec![0u8; bytes.len()];
+    unsafe {
+        gl.GetNamedBufferSubData(
+            id,
+            0 as GLintptr,
+            bytes.len() as GLsizeiptr,
+            contents.as_mut_ptr().cast(),
+        );
+    }
+    contents == bytes
+}
+
+/// Whether `texture` holds `img` as `upload_texture` stores it, without alpha.
+fn texture_matches(gl: &Gl, texture: GLuint, img: &DynamicImage) -> bool {
+    let expected = img.to_rgb8();
+    let mut contents = vec![0u8; expected.len()];
+    unsafe {
+        gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl.GetTextureImage(
+            texture,
+            0,
+            gl::RGB,
+            gl::UNSIGNED_BYTE,
+            contents.len() as GLsizei,
+            contents.as_mut_ptr().cast(),
+        );
+        gl.PixelStorei(gl::PACK_ALIGNMENT, 4);
+    }
+    contents == expected.as_raw().as_slice()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mesh::layout::{StandardVertex, Vertex};
+
+    #[test]
+    fn test_mesh_key() {
+        let layout = StandardVertex::layout();
+        let vertices = [0.0; 8];
+        let key = mesh_key(&vertices, None, layout.clone());
+
+        assert_eq!(key, mesh_key(&[0.0; 8], None, layout.clone()));
+        assert_ne!(key, mesh_key(&vertices, Some(&[0]), layout.clone()));
+        assert_ne!(key, mesh_key(&[-0.0; 8], None, layout.clone()));
+        assert_ne!(key, mesh_key(&[0.0; 16], None, layout.clone()));
+        // The lengths split data the hash alone would run together.
+        assert_ne!(
+            mesh_key(&[1.0, 2.0], Some(&[]), layout.clone()),
+            mesh_key(&[1.0], Some(&[2.0f32.to_bits()]), layout.clone())
+        );
+        assert_ne!(key, mesh_key(&vertices, None, VertexLayout::new()));
+    }
+
+    #[test]
+    fn test_dropped_handles_are_queued() {
+        let release = ReleaseQueue::default();
+        let texture = Rc::new(TextureAsset {
+            id: 7,
+            release: Some(release.clone()),
+        });
+        let weak = Rc::downgrade(&texture);
+        let shared = texture.clone();
+
+        drop(texture);
+        assert!(release.borrow().is_empty());
+        drop(shared);
+        assert!(weak.upgrade().is_none());
+        assert!(matches!(
+            release.borrow().as_slice(),
+            [ReleasedObject::Texture(7)]
+        ));
+
+        // Textures nobody tracks are never released.
+        drop(TextureAsset::unmanaged(8));
+        assert_eq!(release.borrow().len(), 1);
+    }
+}
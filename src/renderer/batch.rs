@@ -0,0 +1,332 @@
+use std::{collections::HashMap, rc::Rc};
+
+use glam::{Mat3, Mat4, Vec3};
+
+use crate::{
+    gl::Gl,
+    mesh::{
+        bounds::Bounds,
+        layout::{vertex_struct, Semantic, Vertex},
+        lod::{LodGroup, LodSelection},
+    },
+    object::cube::{Material, LIT_OBJECT_FRAGMENT_SHADER, LIT_OBJECT_VERTEX_SHADER},
+    renderer::{asset_cache::MeshAsset, stats::FrameStats, texture::TextureManager},
+    shader::{cache::ShaderCache, Shader, ShaderDefines, ShaderTrait},
+};
+
+/// Selects the lit object shader variant reading transforms and material parameters from
+/// per-instance attributes.
+pub const INSTANCED_DEFINE: &str = "INSTANCED";
+
+/// Selects the variant dithering instances whose `fade` isn't 0.
+pub const LOD_FADE_DEFINE: &str = "LOD_FADE";
+
+/// Per-instance attributes of the instanced lit object shader.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InstanceData {
+    pub model: Mat4,
+    /// Inverse transpose of the model matrix, for world space normals.
+    pub normal_matrix: Mat3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    /// Share of the pixels drawn during a level of detail cross-fade, see `with_fade`.
+    pub fade: f32,
+}
+
+vertex_struct!(InstanceData {
+    model: Semantic::Custom("aModel"),
+    normal_matrix: Semantic::Custom("aNormalMatrix"),
+    diffuse: Semantic::Custom("aDiffuseColor"),
+    specular: Semantic::Custom("aSpecularColor"),
+    shininess: Semantic::Custom("aShininess"),
+    fade: Semantic::Custom("aFade"),
+});
+
+impl InstanceData {
+    /// An instance at `model` using the parameters of `material`.
+    pub fn new(model: Mat4, material: &Material) -> Self {
+        Self {
+            model,
+            normal_matrix: Mat3::from_mat4(model).inverse().transpose(),
+            diffuse: material.diffuse,
+            specular: material.specular,
+            shininess: material.shininess,
+            fade: 0.0,
+        }
+    }
+
+    /// Draw only part of the pixels, in a dither pattern. Positive coverage is fading in and
+    /// negative fading out, where fading in by `p` draws the pixels fading out by `-p` leaves
+    /// out, so the two levels of a cross-fade fill the screen together. 0 draws every pixel.
+    pub fn with_fade(self, fade: f32) -> Self {
+        Self { fade, ..self }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MeshHandle(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialHandle(usize);
+
+struct BatchMaterial {
+    material: Material,
+    texture_manager: TextureManager,
+}
+
+/// The vertex array and shader drawing one mesh and material pair.
+struct Batch {
+    /// Released with the meshes once the renderer drops.
+    vertex_buffer: MeshAsset,
+    instance_binding: u32,
+    shader: Option<Rc<Shader>>,
+}
+
+/// Draws lit objects instanced. Instances are queued with `submit` and every mesh and material
+/// pair is drawn with a single draw call by `flush`.
+///
+/// The maps of a material are shared by its instances, but the colors and shininess are
+/// per-instance attributes, so instances only differing in those still share a draw call.
+#[derive(Default)]
+pub struct BatchRenderer {
+    meshes: Vec<Rc<MeshAsset>>,
+    materials: Vec<BatchMaterial>,
+    batches: HashMap<(MeshHandle, MaterialHandle), Batch>,
+    queued: HashMap<(MeshHandle, MaterialHandle), Vec<InstanceData>>,
+}
+
+impl BatchRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register geometry with positions, normals and texture coordinates in its layout.
+    pub fn add_mesh(&mut self, vertex_buffer: impl Into<Rc<MeshAsset>>) -> MeshHandle {
+        self.meshes.push(vertex_buffer.into());
+        MeshHandle(self.meshes.len() - 1)
+    }
+
+    /// Register a material with the maps it uses, named like `Cube::with_geometry` expects.
+    /// Skinned materials are drawn one at a time by `Cube`, see `check_material`.
+    pub fn add_material(
+        &mut self,
+        material: Material,
+        texture_manager: TextureManager,
+    ) -> Result<MaterialHandle, String> {
+        check_material(&material)?;
+        self.materials.push(BatchMaterial {
+            material,
+            texture_manager,
+        });
+        Ok(MaterialHandle(self.materials.len() - 1))
+    }
+
+    pub fn mesh_bounds(&self, mesh: MeshHandle) -> Option<Bounds> {
+        self.meshes[mesh.0].bounds()
+    }
+
+    pub fn material(&self, material: MaterialHandle) -> &Material {
+        &self.materials[material.0].material
+    }
+
+    /// Changing the material can change the shader variant, which `flush` picks up.
+    pub fn set_material(
+        &mut self,
+        handle: MaterialHandle,
+        material: Material,
+    ) -> Result<(), String> {
+        check_material(&material)?;
+        self.materials[handle.0].material = material;
+        Ok(())
+    }
+
+    /// Queue an instance of `mesh` at `model` with the parameters of `material`.
+    pub fn submit(&mut self, mesh: MeshHandle, material: MaterialHandle, model: Mat4) {
+        let instance = InstanceData::new(model, self.material(material));
+        self.submit_instance(mesh, material, instance);
+    }
+
+    /// Queue the levels `selection` picked from `lod`, cross-fading if it is fading.
+    pub fn submit_lod(
+        &mut self,
+        lod: &LodGroup<MeshHandle>,
+        selection: &LodSelection,
+        material: MaterialHandle,
+        model: Mat4,
+    ) {
+        let instance = InstanceData::new(model, self.material(material));
+        match selection.fading_from {
+            Some((from, progress)) => {
+                // A fade just started would draw nothing, and 0 would draw everything.
+                let fade_in = instance.with_fade(progress.max(f32::MIN_POSITIVE));
+                self.submit_instance(*lod.mesh(selection.level), material, fade_in);
+                self.submit_instance(*lod.mesh(from), material, instance.with_fade(-progress));
+            }
+            None => self.submit_instance(*lod.mesh(selection.level), material, instance),
+        }
+    }
+
+    /// Queue an instance with its own material parameters.
+    pub fn submit_instance(
+        &mut self,
+        mesh: MeshHandle,
+        material: MaterialHandle,
+        instance: InstanceData,
+    ) {
+        self.queued
+            .entry((mesh, material))
+            .or_default()
+            .push(instance);
+    }
+
+    /// Number of instances queued since the last flush.
+    pub fn queued_instances(&self) -> usize {
+        self.queued.values().map(Vec::len).sum()
+    }
+
+    /// Draw every queued instance and clear the queue. `scene_defines` are combined with each
+    /// material's defines to pick the shader variant, like for `Cube`.
+    pub fn flush(
+        &mut self,
+        gl: &Gl,
+        shader_cache: &mut ShaderCache,
+        scene_defines: &ShaderDefines,
+        stats: &mut FrameStats,
+    ) {
+        let Self {
+            meshes,
+            materials,
+            batches,
+            queued,
+        } = self;
+
+        for (key, instances) in queued.iter_mut() {
+            if instances.is_empty() {
+                continue;
+            }
+            let (mesh, material) = key;
+            let batch_material = &materials[material.0];
+
+            let mut defines = batch_material
+                .material
+                .defines()
+                .merge(scene_defines)
+                .flag(INSTANCED_DEFINE);
+            if instances.iter().any(|instance| instance.fade != 0.0) {
+                defines = defines.flag(LOD_FADE_DEFINE);
+            }
+            let shader = shader_cache.get(
+                gl,
+                LIT_OBJECT_VERTEX_SHADER,
+                LIT_OBJECT_FRAGMENT_SHADER,
+                &defines,
+            );
+            let batch = batches.entry(*key).or_insert_with(|| {
+                let mesh = &meshes[mesh.0];
+                Batch {
+                    vertex_buffer: mesh.instanced(gl, InstanceData::layout()),
+                    instance_binding: mesh.layout().buffer_count() as u32,
+                    shader: None,
+                }
+            });
+            if !batch
+                .shader
+                .as_ref()
+                .is_some_and(|current| Rc::ptr_eq(current, &shader))
+            {
+                batch
+                    .vertex_buffer
+                    .bind_attributes(gl, &shader)
+                    .unwrap_or_else(|err| panic!("\n{err}\n"));
+                batch_material.texture_manager.sync_samplers(gl, &shader);
+                batch.shader = Some(shader.clone());
+            }
+
+            batch
+                .vertex_buffer
+                .set_buffer_data(gl, batch.instance_binding, instances);
+            batch_material.texture_manager.bind_textures(gl);
+            shader.enable(gl);
+            stats.record_state_change();
+            unsafe { gl.BindVertexArray(batch.vertex_buffer.vao()) };
+            stats.record_state_change();
+            batch
+                .vertex_buffer
+                .draw_instanced(gl, instances.len() as u32, stats);
+
+            instances.clear();
+        }
+    }
+
+    /// Re-apply the sampler units, e.g. after the shaders were reloaded.
+    pub fn sync_state(&self, gl: &Gl) {
+        for ((_, material), batch) in &self.batches {
+            if let Some(shader) = &batch.shader {
+                self.materials[material.0]
+                    .texture_manager
+                    .sync_samplers(gl, shader);
+            }
+        }
+    }
+}
+
+/// The instanced variant takes the locations skinning reads joints and weights from for the
+/// model matrix, and every instance would need a skeleton of its own.
+fn check_material(material: &Material) -> Result<(), String> {
+    if material.skinned {
+        return Err("Skinned materials can't be drawn instanced, draw them with `Cube`".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_instance_layout() {
+        let layout = InstanceData::layout();
+        assert_eq!(
+            layout.stride(0) as usize,
+            std::mem::size_of::<InstanceData>()
+        );
+        // Six inputs taking 4 + 3 + 1 + 1 + 1 + 1 locations.
+        let locations = layout
+            .attributes()
+            .iter()
+            .map(|attribute| attribute.columns)
+            .sum::<u32>();
+        assert_eq!(locations, 11);
+        assert_eq!(layout.attributes()[1].offset, 64);
+    }
+
+    #[test]
+    fn test_normal_matrix() {
+        let model = Mat4::from_scale(Vec3::new(2.0, 1.0, 1.0));
+        let instance = InstanceData::new(model, &Material::default());
+        // Non uniform scales squash normals the other way.
+        assert_eq!(instance.normal_matrix * Vec3::X, Vec3::new(0.5, 0.0, 0.0));
+        assert_eq!(instance.shininess, Material::default().shininess);
+    }
+
+    #[test]
+    fn test_rejects_skinned_materials() {
+        let skinned = Material {
+            skinned: true,
+            ..Material::default()
+        };
+        let mut batches = BatchRenderer::new();
+        let err = batches
+            .add_material(skinned.clone(), TextureManager::new())
+            .unwrap_err();
+        assert!(err.contains("instanced"), "{err}");
+
+        let handle = batches
+            .add_material(Material::default(), TextureManager::new())
+            .unwrap();
+        assert!(batches.set_material(handle, skinned).is_err());
+        assert!(!batches.material(handle).skinned);
+    }
+}
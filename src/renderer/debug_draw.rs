@@ -0,0 +1,379 @@
+use std::{
+    f32::consts::TAU,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
+
+use glam::{vec4, Mat4, Vec3, Vec4};
+
+use crate::{
+    gl::{self, Gl},
+    mesh::{
+        bounds::Aabb,
+        layout::{vertex_struct, Semantic, Vertex},
+        Topology,
+    },
+    renderer::{
+        overlay::OVERLAY_FRAGMENT_SHADER,
+        stats::FrameStats,
+        stream_buffer::{StreamRange, StreamVertexBuffer},
+    },
+    shader::{cache::ShaderCache, Shader, ShaderDefines, ShaderTrait},
+};
+
+pub const DEBUG_VERTEX_SHADER: &str = "src/shader/debug_vert.glsl";
+/// Only passes the vertex colors through, like the overlay's.
+pub const DEBUG_FRAGMENT_SHADER: &str = OVERLAY_FRAGMENT_SHADER;
+
+/// Vertices the debug primitives of one frame can take, lines and points together.
+pub const MAX_DEBUG_VERTICES: usize = 1 << 16;
+
+/// Line segments making up a circle.
+const CIRCLE_SEGMENTS: usize = 32;
+/// Length of an arrow's head, as a fraction of the arrow.
+const ARROW_HEAD_LENGTH: f32 = 0.2;
+const POINT_SIZE: f32 = 6.0;
+
+pub const DEBUG_RED: Vec4 = vec4(1.0, 0.2, 0.2, 1.0);
+pub const DEBUG_GREEN: Vec4 = vec4(0.2, 1.0, 0.2, 1.0);
+pub const DEBUG_BLUE: Vec4 = vec4(0.3, 0.4, 1.0, 1.0);
+pub const DEBUG_YELLOW: Vec4 = vec4(1.0, 0.9, 0.2, 1.0);
+pub const DEBUG_GRAY: Vec4 = vec4(0.5, 0.5, 0.5, 0.6);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DebugVertex {
+    pub position: Vec3,
+    pub color: Vec4,
+}
+
+vertex_struct!(DebugVertex {
+    position: Semantic::Position,
+    color: Semantic::Color,
+});
+
+/// Lines and points in world space, collected over a frame.
+#[derive(Clone, Debug, Default)]
+pub struct DebugPrimitives {
+    /// Two vertices per line.
+    lines: Vec<DebugVertex>,
+    points: Vec<DebugVertex>,
+}
+
+impl DebugPrimitives {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line(&mut self, a: Vec3, b: Vec3, color: Vec4) -> &mut Self {
+        self.lines.extend([
+            DebugVertex { position: a, color },
+            DebugVertex { position: b, color },
+        ]);
+        self
+    }
+
+    pub fn point(&mut self, position: Vec3, color: Vec4) -> &mut Self {
+        self.points.push(DebugVertex { position, color });
+        self
+    }
+
+    /// `length` along `direction` from `origin`, e.g. a picking ray.
+    pub fn ray(&mut self, origin: Vec3, direction: Vec3, length: f32, color: Vec4) -> &mut Self {
+        self.point(origin, color).line(
+            origin,
+            origin + direction.normalize_or_zero() * length,
+            color,
+        )
+    }
+
+    /// A line from `from` to `to` with a head at `to`.
+    pub fn arrow(&mut self, from: Vec3, to: Vec3, color: Vec4) -> &mut Self {
+        self.line(from, to, color);
+        let shaft = to - from;
+        let Some(direction) = shaft.try_normalize() else {
+            return self;
+        };
+        let head_length = shaft.length() * ARROW_HEAD_LENGTH;
+        let (side, up) = direction.any_orthonormal_pair();
+        let base = to - direction * head_length;
+        for offset in [side, -side, up, -up] {
+            self.line(to, base + offset * head_length * 0.5, color);
+        }
+        self
+    }
+
+    pub fn aabb(&mut self, aabb: &Aabb, color: Vec4) -> &mut Self {
+        self.box_edges(&aabb.corners(), color)
+    }
+
+    /// A circle around `center` facing `normal`.
+    pub fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Vec4) -> &mut Self {
+        let (u, v) = normal.normalize_or(Vec3::Y).any_orthonormal_pair();
+        let at = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(at(i), at(i + 1), color);
+        }
+        self
+    }
+
+    /// A sphere as its three circles around the axes.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Vec4) -> &mut Self {
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.circle(center, axis, radius, color);
+        }
+        self
+    }
+
+    /// A grid on the XZ plane around `center`, `size` wide with `cells` cells a side.
+    pub fn grid(&mut self, center: Vec3, size: f32, cells: u32, color: Vec4) -> &mut Self {
+        let cells = cells.max(1);
+        let half = size * 0.5;
+        for i in 0..=cells {
+            let offset = i as f32 / cells as f32 * size - half;
+            self.line(
+                center + Vec3::new(offset, 0.0, -half),
+                center + Vec3::new(offset, 0.0, half),
+                color,
+            );
+            self.line(
+                center + Vec3::new(-half, 0.0, offset),
+                center + Vec3::new(half, 0.0, offset),
+                color,
+            );
+        }
+        self
+    }
+
+    /// The x, y and z axes of `transform` in red, green and blue, `length` long before the
+    /// transform's scale.
+    pub fn axes(&mut self, transform: &Mat4, length: f32) -> &mut Self {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        for (axis, color) in [
+            (Vec3::X, DEBUG_RED),
+            (Vec3::Y, DEBUG_GREEN),
+            (Vec3::Z, DEBUG_BLUE),
+        ] {
+            self.arrow(origin, transform.transform_point3(axis * length), color);
+        }
+        self
+    }
+
+    /// The volume a camera with `projection * view` equal to `view_projection` sees.
+    pub fn frustum(&mut self, view_projection: &Mat4, color: Vec4) -> &mut Self {
+        let inverse = view_projection.inverse();
+        let ndc = Aabb::new(Vec3::splat(-1.0), Vec3::ONE);
+        let corners = ndc.corners().map(|corner| inverse.project_point3(corner));
+        self.box_edges(&corners, color)
+    }
+
+    /// The 12 edges between box corners ordered like `Aabb::corners`, where corners whose
+    /// index differs in a single bit share an edge.
+    fn box_edges(&mut self, corners: &[Vec3; 8], color: Vec4) -> &mut Self {
+        for (i, corner) in corners.iter().enumerate() {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(*corner, corners[i | bit], color);
+                }
+            }
+        }
+        self
+    }
+
+    pub fn lines(&self) -> &[DebugVertex] {
+        &self.lines
+    }
+
+    pub fn points(&self) -> &[DebugVertex] {
+        &self.points
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.points.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.points.clear();
+    }
+}
+
+/// Immediate mode debug drawing. Primitives drawn during a frame are rendered by a
+/// `DebugRenderer` at its end and then forgotten.
+///
+/// Drawing through the `DebugPrimitives` methods directly is depth tested, so scene geometry
+/// hides what is behind it. Primitives drawn through `on_top` are always visible.
+#[derive(Clone, Debug, Default)]
+pub struct DebugDraw {
+    depth_tested: DebugPrimitives,
+    on_top: DebugPrimitives,
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_top(&mut self) -> &mut DebugPrimitives {
+        &mut self.on_top
+    }
+
+    pub fn clear(&mut self) {
+        self.depth_tested.clear();
+        self.on_top.clear();
+    }
+}
+
+impl Deref for DebugDraw {
+    type Target = DebugPrimitives;
+
+    fn deref(&self) -> &Self::Target {
+        &self.depth_tested
+    }
+}
+
+impl DerefMut for DebugDraw {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.depth_tested
+    }
+}
+
+/// Renders the primitives of a `DebugDraw` in a single stream of lines and points.
+pub struct DebugRenderer {
+    shader: Rc<Shader>,
+    vertex_buffer: StreamVertexBuffer,
+}
+
+impl DebugRenderer {
+    pub fn new(gl: &Gl, shader_cache: &mut ShaderCache) -> Self {
+        let shader = shader_cache.get(
+            gl,
+            DEBUG_VERTEX_SHADER,
+            DEBUG_FRAGMENT_SHADER,
+            &ShaderDefines::new(),
+        );
+        let vertex_buffer = StreamVertexBuffer::new(gl, DebugVertex::layout(), MAX_DEBUG_VERTICES);
+        vertex_buffer
+            .bind_attributes(gl, &shader)
+            .unwrap_or_else(|err| panic!("\n{err}\n"));
+
+        Self {
+            shader,
+            vertex_buffer,
+        }
+    }
+
+    /// Draw and clear everything `debug_draw` collected. Needs the `Frame` uniform block
+    /// updated for this frame. Primitives past `MAX_DEBUG_VERTICES` are dropped.
+    pub fn draw(&mut self, gl: &Gl, debug_draw: &mut DebugDraw, stats: &mut FrameStats) {
+        let passes = [&debug_draw.depth_tested, &debug_draw.on_top].map(|primitives| {
+            (
+                self.push(gl, primitives.lines(), 2),
+                self.push(gl, primitives.points(), 1),
+            )
+        });
+
+        unsafe {
+            self.shader.enable(gl);
+            gl.BindVertexArray(self.vertex_buffer.vao());
+            gl.Enable(gl::BLEND);
+            gl.BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            // Debug geometry shouldn't hide anything drawn after it.
+            gl.DepthMask(gl::FALSE);
+            gl.PointSize(POINT_SIZE);
+        }
+        stats.record_state_change();
+        for (i, (lines, points)) in passes.into_iter().enumerate() {
+            if i == 1 {
+                unsafe { gl.Disable(gl::DEPTH_TEST) };
+            }
+            self.vertex_buffer.set_topology(Topology::Lines);
+            self.vertex_buffer.draw(gl, lines, stats);
+            self.vertex_buffer.set_topology(Topology::Points);
+            self.vertex_buffer.draw(gl, points, stats);
+        }
+        unsafe {
+            gl.Enable(gl::DEPTH_TEST);
+            gl.DepthMask(gl::TRUE);
+            gl.Disable(gl::BLEND);
+        }
+
+        self.vertex_buffer.end_frame(gl);
+        debug_draw.clear();
+    }
+
+    /// Push as many whole primitives of `group` vertices as still fit this frame.
+    fn push(&mut self, gl: &Gl, vertices: &[DebugVertex], group: usize) -> StreamRange {
+        let stream = self.vertex_buffer.stream();
+        let room = (stream.frame_size() - stream.used()) / std::mem::size_of::<DebugVertex>();
+        let count = vertices.len().min(room) / group * group;
+        self.vertex_buffer
+            .push(gl, &vertices[..count])
+            .unwrap_or_else(|err| panic!("\n{err}\n"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn positions(vertices: &[DebugVertex]) -> Vec<Vec3> {
+        vertices.iter().map(|vertex| vertex.position).collect()
+    }
+
+    #[test]
+    fn test_aabb_edges() {
+        let mut primitives = DebugPrimitives::new();
+        primitives.aabb(&Aabb::new(Vec3::ZERO, Vec3::ONE), DEBUG_RED);
+
+        let lines = positions(primitives.lines());
+        assert_eq!(lines.len(), 24);
+        // Every edge is a unit step along a single axis.
+        for line in lines.chunks_exact(2) {
+            let step = (line[1] - line[0]).abs();
+            assert_eq!(step.element_sum(), 1.0);
+            assert_eq!(step.max_element(), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_frustum() {
+        let mut primitives = DebugPrimitives::new();
+        let projection = Mat4::perspective_rh_gl(90f32.to_radians(), 1.0, 1.0, 10.0);
+        primitives.frustum(&projection, DEBUG_GRAY);
+
+        let lines = positions(primitives.lines());
+        assert_eq!(lines.len(), 24);
+        for corner in lines {
+            // On the near or the far plane, as wide as it is far away at 90 degrees.
+            let distance = -corner.z;
+            assert!((distance - 1.0).abs() < 1e-4 || (distance - 10.0).abs() < 1e-3);
+            assert!((corner.x.abs() - distance).abs() < 1e-3);
+            assert!((corner.y.abs() - distance).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut debug_draw = DebugDraw::new();
+        debug_draw.sphere(Vec3::ZERO, 1.0, DEBUG_GREEN);
+        debug_draw
+            .on_top()
+            .arrow(Vec3::ZERO, Vec3::X, DEBUG_YELLOW)
+            .point(Vec3::X, DEBUG_YELLOW);
+
+        assert_eq!(debug_draw.lines().len(), 3 * CIRCLE_SEGMENTS * 2);
+        assert!(debug_draw
+            .lines()
+            .iter()
+            .all(|vertex| (vertex.position.length() - 1.0).abs() < 1e-5));
+        assert_eq!(debug_draw.on_top().lines().len(), 5 * 2);
+        assert_eq!(debug_draw.on_top().points().len(), 1);
+
+        debug_draw.clear();
+        assert!(debug_draw.is_empty() && debug_draw.on_top().is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+use std::rc::Rc;
+
+use glam::{vec2, vec4, Vec2, Vec4};
+
+use crate::{
+    camera::Camera,
+    gl::{self, Gl},
+    mesh::{
+        layout::{vertex_struct, Semantic},
+        VertexBuffer,
+    },
+    renderer::{asset_cache::AssetCacheStats, stats::FrameStats},
+    shader::{cache::ShaderCache, Shader, ShaderDefines, ShaderTrait},
+    timer::{Timer, FRAME_HISTORY_LEN},
+};
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct OverlayVertex {
+    /// Pixels from the top left corner.
+    position: Vec2,
+    color: Vec4,
+}
+
+vertex_struct!(OverlayVertex {
+    position: Semantic::Position,
+    color: Semantic::Color,
+});
+
+pub const OVERLAY_VERTEX_SHADER: &str = "src/shader/overlay_vert.glsl";
+pub const OVERLAY_FRAGMENT_SHADER: &str = "src/shader/overlay_frag.glsl";
+
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+// Size in pixels of one font cell.
+const GLYPH_SCALE: f32 = 2.0;
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+const GLYPH_ADVANCE: f32 = (GLYPH_WIDTH as f32 + 1.0) * GLYPH_SCALE;
+const LINE_HEIGHT: f32 = (GLYPH_HEIGHT as f32 + 2.0) * GLYPH_SCALE;
+
+const GRAPH_BAR_WIDTH: f32 = 2.0;
+const GRAPH_HEIGHT: f32 = 60.0;
+// Frame time in seconds that fills the whole graph height.
+const GRAPH_MAX_FRAME_TIME: f32 = 1.0 / 20.0;
+const TARGET_FRAME_TIME: f32 = 1.0 / 60.0;
+
+const BACKGROUND_COLOR: Vec4 = vec4(0.0, 0.0, 0.0, 0.6);
+const TEXT_COLOR: Vec4 = vec4(1.0, 1.0, 1.0, 1.0);
+const TARGET_LINE_COLOR: Vec4 = vec4(1.0, 1.0, 1.0, 0.4);
+const GOOD_FRAME_COLOR: Vec4 = vec4(0.2, 0.9, 0.2, 0.9);
+const SLOW_FRAME_COLOR: Vec4 = vec4(0.9, 0.8, 0.2, 0.9);
+const BAD_FRAME_COLOR: Vec4 = vec4(0.9, 0.2, 0.2, 0.9);
+
+/// Frame statistics drawn on top of the scene.
+pub struct Overlay {
+    shader: Rc<Shader>,
+    vertex_buffer: VertexBuffer,
+    vertices: Vec<OverlayVertex>,
+    visible: bool,
+}
+
+impl Overlay {
+    pub fn new(gl: &Gl, shader_cache: &mut ShaderCache) -> Self {
+        let shader = shader_cache.get(
+            gl,
+            OVERLAY_VERTEX_SHADER,
+            OVERLAY_FRAGMENT_SHADER,
+            &ShaderDefines::new(),
+        );
+
+        let vertex_buffer = VertexBuffer::from_vertices::<OverlayVertex>(gl, &[]);
+        vertex_buffer
+            .bind_attributes(gl, &shader)
+            .unwrap_or_else(|err| panic!("\n{err}\n"));
+
+        Self {
+            shader,
+            vertex_buffer,
+            vertices: vec![],
+            visible: false,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn draw(
+        &mut self,
+        gl: &Gl,
+        stats: &FrameStats,
+        assets: &AssetCacheStats,
+        timer: &Timer,
+        camera: &Camera,
+    ) {
+        if !self.visible {
+            return;
+        }
+
+        self.build(stats, assets, timer, camera);
+        self.vertex_buffer.set_data(gl, &self.vertices);
+
+        let (width, height) = gl.get_viewport_size();
+        self.shader
+            .set_uniform(gl, "screenSize", &vec2(width, height))
+            .unwrap();
+
+        unsafe {
+            gl.Disable(gl::DEPTH_TEST);
+            gl.Enable(gl::BLEND);
+            gl.BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+
+            self.shader.enable(gl);
+            gl.BindVertexArray(self.vertex_buffer.vao());
+            gl.DrawArrays(gl::TRIANGLES, 0, self.vertices.len() as i32);
+
+            gl.Disable(gl::BLEND);
+            gl.Enable(gl::DEPTH_TEST);
+        }
+    }
+
+    fn build(
+        &mut self,
+        stats: &FrameStats,
+        assets: &AssetCacheStats,
+        timer: &Timer,
+        camera: &Camera,
+    ) {
+        self.vertices.clear();
+
+        let frame_time_ms = timer.frame_times().back().copied().unwrap_or(0.0) * 1000.0;
+        let pos = camera.pos();
+        let lines = [
+            format!("FPS {:.1}  {:.2} MS", timer.fps(), frame_time_ms),
+            format!(
+                "DRAWS {}  TRIS {}  STATE {}",
+                stats.draw_calls, stats.triangles, stats.state_changes
+            ),
+            format!(
+                "ASSET HITS {}  MISSES {}  RELEASED {}",
+                assets.hits, assets.misses, assets.released
+            ),
+            format!("POS {:.2} {:.2} {:.2}", pos.x, pos.y, pos.z),
+            format!("YAW {:.1}  PITCH {:.1}", camera.yaw(), camera.pitch()),
+        ];
+
+        let graph_width = FRAME_HISTORY_LEN as f32 * GRAPH_BAR_WIDTH;
+        let text_width = lines
+            .iter()
+            .map(|line| line.len() as f32 * GLYPH_ADVANCE)
+            .fold(graph_width, f32::max);
+        let text_height = lines.len() as f32 * LINE_HEIGHT;
+
+        self.push_rect(
+            MARGIN,
+            MARGIN,
+            text_width + PADDING * 2.0,
+            text_height + GRAPH_HEIGHT + PADDING * 3.0,
+            BACKGROUND_COLOR,
+        );
+
+        let x = MARGIN + PADDING;
+        let mut y = MARGIN + PADDING;
+        for line in &lines {
+            self.push_text(x, y, line, TEXT_COLOR);
+            y += LINE_HEIGHT;
+        }
+
+        let graph_bottom = y + PADDING + GRAPH_HEIGHT;
+        for (i, frame_time) in timer.frame_times().iter().enumerate() {
+            let bar_height = (frame_time / GRAPH_MAX_FRAME_TIME).min(1.0) * GRAPH_HEIGHT;
+            let color = if *frame_time <= TARGET_FRAME_TIME * 1.05 {
+                GOOD_FRAME_COLOR
+            } else if *frame_time <= TARGET_FRAME_TIME * 2.0 {
+                SLOW_FRAME_COLOR
+            } else {
+                BAD_FRAME_COLOR
+            };
+            self.push_rect(
+                x + i as f32 * GRAPH_BAR_WIDTH,
+                graph_bottom - bar_height,
+                GRAPH_BAR_WIDTH,
+                bar_height,
+                color,
+            );
+        }
+
+        let target_y = graph_bottom - TARGET_FRAME_TIME / GRAPH_MAX_FRAME_TIME * GRAPH_HEIGHT;
+        self.push_rect(x, target_y, graph_width, 1.0, TARGET_LINE_COLOR);
+    }
+
+    fn push_text(&mut self, x: f32, y: f32, text: &str, color: Vec4) {
+        for (i, c) in text.chars().enumerate() {
+            let glyph_x = x + i as f32 * GLYPH_ADVANCE;
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                        self.push_rect(
+                            glyph_x + col as f32 * GLYPH_SCALE,
+                            y + row as f32 * GLYPH_SCALE,
+                            GLYPH_SCALE,
+                            GLYPH_SCALE,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn push_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Vec4) {
+        let corners = [
+            (x, y),
+            (x + width, y),
+            (x + width, y + height),
+            (x + width, y + height),
+            (x, y + height),
+            (x, y),
+        ];
+        for (x, y) in corners {
+            self.vertices.push(OverlayVertex {
+                position: vec2(x, y),
+                color,
+            });
+        }
+    }
+}
+
+/// 3x5 bitmap for `c`, one row per entry with the leftmost pixel in the highest bit.
+/// Unknown characters render as blank.
+#[rustfmt::skip]
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
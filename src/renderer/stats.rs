@@ -0,0 +1,65 @@
+use crate::gl::{self, types::GLenum};
+
+/// Counters collected while drawing a single frame.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct FrameStats {
+    pub draw_calls: u32,
+    pub triangles: u32,
+    pub state_changes: u32,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Record a draw call of `vertex_count` vertices with the given primitive mode.
+    pub fn record_draw(&mut self, mode: GLenum, vertex_count: u32) {
+        self.record_instanced_draw(mode, vertex_count, 1);
+    }
+
+    /// Record a single draw call of `instance_count` instances of `vertex_count` vertices.
+    pub fn record_instanced_draw(&mut self, mode: GLenum, vertex_count: u32, instance_count: u32) {
+        self.draw_calls += 1;
+        let triangles = match mode {
+            gl::TRIANGLES => vertex_count / 3,
+            gl::TRIANGLE_STRIP | gl::TRIANGLE_FAN => vertex_count.saturating_sub(2),
+            _ => 0,
+        };
+        self.triangles = self
+            .triangles
+            .saturating_add(triangles.saturating_mul(instance_count));
+    }
+
+    /// Record a pipeline state change such as a program or vertex array bind.
+    pub fn record_state_change(&mut self) {
+        self.state_changes += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record_draw() {
+        let mut stats = FrameStats::new();
+        stats.record_draw(gl::TRIANGLES, 36);
+        stats.record_draw(gl::TRIANGLE_STRIP, 4);
+        stats.record_draw(gl::LINES, 2);
+
+        assert_eq!(stats.draw_calls, 3);
+        assert_eq!(stats.triangles, 14);
+
+        stats.record_instanced_draw(gl::TRIANGLES, 36, 100_000);
+        assert_eq!(stats.draw_calls, 4);
+        assert_eq!(stats.triangles, 1_200_014);
+
+        stats.reset();
+        assert_eq!(stats, FrameStats::default());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    gl::{
+        self,
+        types::{GLintptr, GLsizeiptr, GLuint},
+        Gl,
+    },
+    shader::Shader,
+};
+
+/// A shader storage buffer, readable and writable from any stage including compute.
+pub struct StorageBuffer {
+    id: GLuint,
+    binding: GLuint,
+    size: usize,
+}
+
+impl StorageBuffer {
+    /// A zero filled buffer of `size` bytes bound to `binding`.
+    pub fn new(gl: &Gl, binding: GLuint, size: usize) -> Self {
+        Self::with_data(gl, binding, &vec![0; size])
+    }
+
+    pub fn with_data(gl: &Gl, binding: GLuint, data: &[u8]) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl.CreateBuffers(1, &mut id);
+            gl.NamedBufferData(
+                id,
+                data.len() as GLsizeiptr,
+                data.as_ptr().cast(),
+                gl::DYNAMIC_COPY,
+            );
+            gl.BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, id);
+        }
+
+        Self {
+            id,
+            binding,
+            size: data.len(),
+        }
+    }
+
+    /// Overwrite part of the buffer starting at byte `offset`.
+    pub fn update(&self, gl: &Gl, offset: usize, data: &[u8]) {
+        assert!(
+            offset + data.len() <= self.size,
+            "Storage buffer update out of bounds"
+        );
+        unsafe {
+            gl.NamedBufferSubData(
+                self.id,
+                offset as GLintptr,
+                data.len() as GLsizeiptr,
+                data.as_ptr().cast(),
+            );
+        }
+    }
+
+    /// Read the whole buffer back. Stalls until every command writing to it has finished, and
+    /// needs a `gl::BUFFER_UPDATE_BARRIER_BIT` barrier after shader writes.
+    pub fn read(&self, gl: &Gl) -> Vec<u8> {
+        let mut data = vec![0; self.size];
+        unsafe {
+            gl.GetNamedBufferSubData(
+                self.id,
+                0,
+                self.size as GLsizeiptr,
+                data.as_mut_ptr().cast(),
+            );
+        }
+        data
+    }
+
+    /// Move the buffer to another binding point, e.g. to swap the inputs and outputs of a
+    /// simulation step.
+    pub fn bind(&mut self, gl: &Gl, binding: GLuint) {
+        unsafe { gl.BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, self.id) };
+        self.binding = binding;
+    }
+
+    /// Check that `shader` declares the storage block `name` at this buffer's binding point
+    /// and that the buffer is large enough for it. A trailing unsized array counts as one
+    /// element.
+    pub fn check_block(&self, shader: &Shader, name: &str) -> Result<(), String> {
+        let reflection = shader.reflection();
+        let Some(block) = reflection.storage_block(name) else {
+            return Ok(());
+        };
+
+        if block.binding as GLuint != self.binding {
+            return Err(format!(
+                "Storage block {name} is bound to {}, expected {}",
+                block.binding, self.binding
+            ));
+        }
+        if block.data_size as usize > self.size {
+            return Err(format!(
+                "Storage block {name} needs at least {} bytes, the buffer has {}",
+                block.data_size, self.size
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn binding(&self) -> GLuint {
+        self.binding
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
@@ -0,0 +1,688 @@
+use crate::{
+    gl::{
+        self,
+        types::{GLbitfield, GLint, GLintptr, GLsizei, GLsizeiptr, GLsync, GLuint},
+        Gl,
+    },
+    mesh::{attribute_formats, layout::VertexLayout, Topology},
+    renderer::stats::FrameStats,
+    shader::{reflection::Reflection, Shader},
+};
+
+/// Frames the GPU may lag behind before writes wait for it.
+pub const STREAM_FRAMES_IN_FLIGHT: usize = 3;
+
+/// Nanoseconds a single wait for the GPU to release a region blocks before trying again.
+const FENCE_WAIT_TIMEOUT: u64 = 1_000_000;
+
+const PERSISTENT_MAP_FLAGS: GLbitfield =
+    gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+
+/// How a `StreamBuffer` gets data to the GPU without waiting for draws still reading it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamStrategy {
+    /// One buffer mapped for its whole life, split into a region per frame in flight. A fence
+    /// after each frame tells when its region can be written again.
+    PersistentMapping,
+    /// The buffer is reallocated every frame, so the driver hands out fresh memory while the
+    /// old one is still drawn from. Needs neither `glBufferStorage` nor fences, and goes through
+    /// the `GL_ARRAY_BUFFER` binding rather than the 4.5 named buffer calls.
+    Orphaning,
+}
+
+impl StreamStrategy {
+    /// Persistent mapping where the context has `glBufferStorage` (4.4), orphaning otherwise.
+    pub fn best(gl: &Gl) -> Self {
+        let (mut major, mut minor) = (0, 0);
+        unsafe {
+            gl.GetIntegerv(gl::MAJOR_VERSION, &mut major);
+            gl.GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        }
+        if (major, minor) >= (4, 4)
+            && gl.NamedBufferStorage.is_loaded()
+            && gl.MapNamedBufferRange.is_loaded()
+            && gl.FenceSync.is_loaded()
+        {
+            StreamStrategy::PersistentMapping
+        } else {
+            StreamStrategy::Orphaning
+        }
+    }
+
+    fn region_count(self) -> usize {
+        match self {
+            StreamStrategy::PersistentMapping => STREAM_FRAMES_IN_FLIGHT,
+            StreamStrategy::Orphaning => 1,
+        }
+    }
+}
+
+/// Hands out byte ranges of a buffer split into equally sized regions, one per frame.
+#[derive(Clone, Debug, PartialEq)]
+struct RingAllocator {
+    region_size: usize,
+    region_count: usize,
+    region: usize,
+    /// Next free byte, from the start of the buffer.
+    head: usize,
+}
+
+impl RingAllocator {
+    fn new(region_size: usize, region_count: usize) -> Self {
+        Self {
+            region_size,
+            region_count,
+            region: 0,
+            head: 0,
+        }
+    }
+
+    /// Offset of `size` free bytes aligned to `alignment` in the current region, `None` if they
+    /// don't fit into what is left of it.
+    fn allocate(&mut self, size: usize, alignment: usize) -> Option<usize> {
+        let offset = self.head.next_multiple_of(alignment.max(1));
+        let region_end = (self.region + 1) * self.region_size;
+        if offset + size > region_end {
+            return None;
+        }
+        self.head = offset + size;
+        Some(offset)
+    }
+
+    /// Move on to the next region, wrapping around, and return its index.
+    fn next_region(&mut self) -> usize {
+        self.region = (self.region + 1) % self.region_count;
+        self.head = self.region * self.region_size;
+        self.region
+    }
+
+    fn used(&self) -> usize {
+        self.head - self.region * self.region_size
+    }
+
+    fn total_size(&self) -> usize {
+        self.region_size * self.region_count
+    }
+}
+
+/// A buffer for data written anew every frame, such as debug lines, particles or UI.
+///
+/// Every frame `push` data into it, draw from the returned offsets and call `end_frame`.
+/// Writes never touch memory a draw of the last `STREAM_FRAMES_IN_FLIGHT - 1` frames may still
+/// read, so they don't stall the GPU, and only wait for it when it lags further behind.
+pub struct StreamBuffer {
+    id: GLuint,
+    strategy: StreamStrategy,
+    ring: RingAllocator,
+    /// The persistent mapping of the whole buffer.
+    mapping: Option<*mut u8>,
+    /// Signaled once the GPU is done with the draws of each region's last frame.
+    fences: Vec<Option<GLsync>>,
+    /// Times `end_frame` had to wait for the GPU.
+    stalls: u32,
+}
+
+impl StreamBuffer {
+    /// A buffer taking up to `frame_size` bytes each frame, with the best strategy the context
+    /// supports.
+    pub fn new(gl: &Gl, frame_size: usize) -> Self {
+        Self::with_strategy(gl, frame_size, StreamStrategy::best(gl))
+    }
+
+    pub fn with_strategy(gl: &Gl, frame_size: usize, strategy: StreamStrategy) -> Self {
+        let ring = RingAllocator::new(frame_size, strategy.region_count());
+        let size = ring.total_size() as GLsizeiptr;
+        let mut id = 0;
+        let mut mapping = None;
+        unsafe {
+            match strategy {
+                StreamStrategy::PersistentMapping => {
+                    gl.CreateBuffers(1, &mut id);
+                    gl.NamedBufferStorage(id, size, std::ptr::null(), PERSISTENT_MAP_FLAGS);
+                    let pointer = gl.MapNamedBufferRange(id, 0, size, PERSISTENT_MAP_FLAGS);
+                    assert!(!pointer.is_null(), "Failed to map the stream buffer");
+                    mapping = Some(pointer.cast());
+                }
+                StreamStrategy::Orphaning => {
+                    gl.GenBuffers(1, &mut id);
+                    orphan(gl, id, size);
+                }
+            }
+        }
+
+        Self {
+            id,
+            strategy,
+            fences: vec![None; ring.region_count],
+            ring,
+            mapping,
+            stalls: 0,
+        }
+    }
+
+    /// Copy `data` into this frame's part of the buffer at an offset that is a multiple of
+    /// `alignment`, and return that offset. Fails once the frame's `frame_size` bytes run out.
+    pub fn push<T: Copy>(
+        &mut self,
+        gl: &Gl,
+        data: &[T],
+        alignment: usize,
+    ) -> Result<usize, String> {
+        let size = std::mem::size_of_val(data);
+        let offset = self.ring.allocate(size, alignment).ok_or_else(|| {
+            format!(
+                "Stream buffer out of space: {size} more bytes after {} of {} this frame",
+                self.ring.used(),
+                self.ring.region_size
+            )
+        })?;
+
+        unsafe {
+            match self.mapping {
+                // In bounds as the allocation is inside the mapped range, and coherent so the
+                // GPU sees the write without flushing.
+                Some(mapping) => std::ptr::copy_nonoverlapping(
+                    data.as_ptr().cast::<u8>(),
+                    mapping.add(offset),
+                    size,
+                ),
+                None => {
+                    gl.BindBuffer(gl::ARRAY_BUFFER, self.id);
+                    gl.BufferSubData(
+                        gl::ARRAY_BUFFER,
+                        offset as GLintptr,
+                        size as GLsizeiptr,
+                        data.as_ptr().cast(),
+                    );
+                    gl.BindBuffer(gl::ARRAY_BUFFER, 0);
+                }
+            }
+        }
+        Ok(offset)
+    }
+
+    /// Close the frame after its last draw reading the buffer was issued, and get the next
+    /// frame's region ready for writing.
+    pub fn end_frame(&mut self, gl: &Gl) {
+        match self.strategy {
+            StreamStrategy::PersistentMapping => unsafe {
+                let done = self.ring.region;
+                if let Some(fence) = self.fences[done].take() {
+                    gl.DeleteSync(fence);
+                }
+                self.fences[done] = Some(gl.FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0));
+
+                let next = self.ring.next_region();
+                if let Some(fence) = self.fences[next].take() {
+                    self.wait(gl, fence);
+                    gl.DeleteSync(fence);
+                }
+            },
+            StreamStrategy::Orphaning => unsafe {
+                self.ring.next_region();
+                orphan(gl, self.id, self.ring.total_size() as GLsizeiptr);
+            },
+        }
+    }
+
+    unsafe fn wait(&mut self, gl: &Gl, fence: GLsync) {
+        // A failed wait won't succeed on retrying either, so it ends the wait too.
+        let done = |result| result != gl::TIMEOUT_EXPIRED;
+        if done(gl.ClientWaitSync(fence, 0, 0)) {
+            return;
+        }
+        self.stalls += 1;
+        // Flushing makes sure the fence reaches the GPU, or the wait may never end.
+        while !done(gl.ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, FENCE_WAIT_TIMEOUT)) {}
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn strategy(&self) -> StreamStrategy {
+        self.strategy
+    }
+
+    /// Bytes each frame can push.
+    pub fn frame_size(&self) -> usize {
+        self.ring.region_size
+    }
+
+    /// Bytes pushed this frame so far.
+    pub fn used(&self) -> usize {
+        self.ring.used()
+    }
+
+    /// Times the GPU was so far behind that a frame had to wait for it.
+    pub fn stalls(&self) -> u32 {
+        self.stalls
+    }
+
+    /// Unmap and delete the buffer and its fences.
+    pub fn delete(self, gl: &Gl) {
+        unsafe {
+            for fence in self.fences.into_iter().flatten() {
+                gl.DeleteSync(fence);
+            }
+            if self.mapping.is_some() {
+                gl.UnmapNamedBuffer(self.id);
+            }
+            gl.DeleteBuffers(1, &self.id);
+        }
+    }
+}
+
+/// Give `id` fresh storage of `size` bytes, leaving the old storage to the draws still reading
+/// it.
+unsafe fn orphan(gl: &Gl, id: GLuint, size: GLsizeiptr) {
+    gl.BindBuffer(gl::ARRAY_BUFFER, id);
+    gl.BufferData(gl::ARRAY_BUFFER, size, std::ptr::null(), gl::STREAM_DRAW);
+    gl.BindBuffer(gl::ARRAY_BUFFER, 0);
+}
+
+/// Vertices pushed into a `StreamVertexBuffer` this frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamRange {
+    pub first: u32,
+    pub count: u32,
+}
+
+/// A vertex array reading from a `StreamBuffer`, for geometry that changes every frame. With
+/// `StreamStrategy::Orphaning` the vertex array is set up through the bound vertex array rather
+/// than the 4.5 named calls, like the buffer.
+pub struct StreamVertexBuffer {
+    stream: StreamBuffer,
+    vao: GLuint,
+    layout: VertexLayout,
+    topology: Topology,
+}
+
+impl StreamVertexBuffer {
+    /// Room for `max_vertices` vertices of `layout` each frame. The layout has a single buffer.
+    pub fn new(gl: &Gl, layout: VertexLayout, max_vertices: usize) -> Self {
+        assert_eq!(
+            layout.buffer_count(),
+            1,
+            "Stream vertex buffers read a single buffer"
+        );
+        let stride = layout.stride(0);
+        let stream = StreamBuffer::new(gl, max_vertices * stride as usize);
+
+        let mut vao = 0;
+        unsafe {
+            match stream.strategy() {
+                StreamStrategy::PersistentMapping => {
+                    gl.CreateVertexArrays(1, &mut vao);
+                    gl.VertexArrayVertexBuffer(vao, 0, stream.id(), 0, stride as GLsizei);
+                }
+                StreamStrategy::Orphaning => {
+                    gl.GenVertexArrays(1, &mut vao);
+                    gl.BindVertexArray(vao);
+                    gl.BindVertexBuffer(0, stream.id(), 0, stride as GLsizei);
+                    gl.BindVertexArray(0);
+                }
+            }
+        }
+        Self {
+            stream,
+            vao,
+            layout,
+            topology: Topology::default(),
+        }
+    }
+
+    /// Copy `vertices` into the buffer for drawing this frame. `T` is laid out like the layout
+    /// says, e.g. a `Vertex` struct or its floats.
+    pub fn push<T: Copy>(&mut self, gl: &Gl, vertices: &[T]) -> Result<StreamRange, String> {
+        let stride = self.layout.stride(0).max(1) as usize;
+        // Aligned to whole vertices, so the offset is a vertex index into the buffer.
+        let offset = self.stream.push(gl, vertices, stride)?;
+        Ok(StreamRange {
+            first: (offset / stride) as u32,
+            count: (std::mem::size_of_val(vertices) / stride) as u32,
+        })
+    }
+
+    pub fn bind_attributes(&self, gl: &Gl, shader: &Shader) -> Result<(), String> {
+        self.bind_reflected(gl, &shader.reflection())
+    }
+
+    fn bind_reflected(&self, gl: &Gl, reflection: &Reflection) -> Result<(), String> {
+        let formats = attribute_formats(&self.layout, reflection)?;
+        match self.stream.strategy() {
+            StreamStrategy::PersistentMapping => {
+                for format in formats {
+                    format.apply_named(gl, self.vao);
+                }
+            }
+            StreamStrategy::Orphaning => unsafe {
+                gl.BindVertexArray(self.vao);
+                for format in formats {
+                    format.apply_bound(gl);
+                }
+                gl.BindVertexArray(0);
+            },
+        }
+        Ok(())
+    }
+
+    /// Draw vertices pushed this frame. The vertex array has to be bound.
+    pub fn draw(&self, gl: &Gl, range: StreamRange, stats: &mut FrameStats) {
+        if range.count == 0 {
+            return;
+        }
+        let mode = self.topology.gl_enum();
+        unsafe { gl.DrawArrays(mode, range.first as GLint, range.count as GLsizei) };
+        stats.record_draw(mode, range.count);
+    }
+
+    /// See `StreamBuffer::end_frame`.
+    pub fn end_frame(&mut self, gl: &Gl) {
+        self.stream.end_frame(gl);
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    pub fn stream(&self) -> &StreamBuffer {
+        &self.stream
+    }
+
+    pub fn vao(&self) -> GLuint {
+        self.vao
+    }
+
+    pub fn delete(self, gl: &Gl) {
+        unsafe { gl.DeleteVertexArrays(1, &self.vao) };
+        self.stream.delete(gl);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, ffi::c_void};
+
+    use glam::{Vec3, Vec4};
+
+    use super::*;
+    use crate::{
+        gl::types::{GLboolean, GLchar, GLenum},
+        mesh::layout::Vertex,
+        renderer::debug_draw::DebugVertex,
+    };
+
+    thread_local! {
+        static CALLS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn record(call: String) {
+        CALLS.with(|calls| calls.borrow_mut().push(call));
+    }
+
+    extern "system" fn gen_buffers(n: GLsizei, buffers: *mut GLuint) {
+        record(format!("GenBuffers {n}"));
+        unsafe { *buffers = 7 };
+    }
+
+    extern "system" fn bind_buffer(_target: GLenum, buffer: GLuint) {
+        record(format!("BindBuffer {buffer}"));
+    }
+
+    extern "system" fn buffer_data(
+        _target: GLenum,
+        size: GLsizeiptr,
+        _data: *const c_void,
+        _usage: GLenum,
+    ) {
+        record(format!("BufferData {size}"));
+    }
+
+    extern "system" fn buffer_sub_data(
+        _target: GLenum,
+        offset: GLintptr,
+        size: GLsizeiptr,
+        _data: *const c_void,
+    ) {
+        record(format!("BufferSubData {offset} {size}"));
+    }
+
+    extern "system" fn delete_buffers(n: GLsizei, _buffers: *const GLuint) {
+        record(format!("DeleteBuffers {n}"));
+    }
+
+    extern "system" fn get_integerv(pname: GLenum, data: *mut GLint) {
+        let value = if pname == gl::MAJOR_VERSION { 4 } else { 6 };
+        unsafe { *data = value };
+    }
+
+    extern "system" fn gen_vertex_arrays(n: GLsizei, arrays: *mut GLuint) {
+        record(format!("GenVertexArrays {n}"));
+        unsafe { *arrays = 3 };
+    }
+
+    extern "system" fn bind_vertex_array(array: GLuint) {
+        record(format!("BindVertexArray {array}"));
+    }
+
+    extern "system" fn bind_vertex_buffer(
+        binding: GLuint,
+        buffer: GLuint,
+        offset: GLintptr,
+        stride: GLsizei,
+    ) {
+        record(format!(
+            "BindVertexBuffer {binding} {buffer} {offset} {stride}"
+        ));
+    }
+
+    extern "system" fn enable_vertex_attrib_array(location: GLuint) {
+        record(format!("EnableVertexAttribArray {location}"));
+    }
+
+    extern "system" fn vertex_attrib_format(
+        location: GLuint,
+        size: GLint,
+        _component_type: GLenum,
+        _normalized: GLboolean,
+        offset: GLuint,
+    ) {
+        record(format!("VertexAttribFormat {location} {size} {offset}"));
+    }
+
+    extern "system" fn vertex_attrib_binding(location: GLuint, binding: GLuint) {
+        record(format!("VertexAttribBinding {location} {binding}"));
+    }
+
+    extern "system" fn delete_vertex_arrays(n: GLsizei, _arrays: *const GLuint) {
+        record(format!("DeleteVertexArrays {n}"));
+    }
+
+    /// The program has a single input, `vec3 aPos` at location 2.
+    extern "system" fn get_program_interfaceiv(
+        _program: GLuint,
+        interface: GLenum,
+        _pname: GLenum,
+        count: *mut GLint,
+    ) {
+        unsafe { *count = (interface == gl::PROGRAM_INPUT) as GLint };
+    }
+
+    extern "system" fn get_program_resourceiv(
+        _program: GLuint,
+        _interface: GLenum,
+        _index: GLuint,
+        prop_count: GLsizei,
+        props: *const GLenum,
+        _count: GLsizei,
+        _length: *mut GLsizei,
+        values: *mut GLint,
+    ) {
+        for i in 0..prop_count as usize {
+            let value = match unsafe { *props.add(i) } {
+                gl::TYPE => gl::FLOAT_VEC3 as GLint,
+                gl::ARRAY_SIZE => 1,
+                gl::LOCATION => 2,
+                gl::NAME_LENGTH => 5,
+                _ => 0,
+            };
+            unsafe { *values.add(i) = value };
+        }
+    }
+
+    extern "system" fn get_program_resource_name(
+        _program: GLuint,
+        _interface: GLenum,
+        _index: GLuint,
+        _size: GLsizei,
+        length: *mut GLsizei,
+        name: *mut GLchar,
+    ) {
+        unsafe {
+            std::ptr::copy_nonoverlapping(c"aPos".as_ptr(), name, 5);
+            *length = 4;
+        }
+    }
+
+    /// A 4.6 context without any named buffer or vertex array functions. Calling a function it
+    /// lacks panics.
+    fn pre_dsa_gl() -> Gl {
+        Gl::load_with(|name| match name {
+            "glGenBuffers" => gen_buffers as *const c_void,
+            "glBindBuffer" => bind_buffer as *const c_void,
+            "glBufferData" => buffer_data as *const c_void,
+            "glBufferSubData" => buffer_sub_data as *const c_void,
+            "glDeleteBuffers" => delete_buffers as *const c_void,
+            "glGetIntegerv" => get_integerv as *const c_void,
+            "glGenVertexArrays" => gen_vertex_arrays as *const c_void,
+            "glBindVertexArray" => bind_vertex_array as *const c_void,
+            "glBindVertexBuffer" => bind_vertex_buffer as *const c_void,
+            "glEnableVertexAttribArray" => enable_vertex_attrib_array as *const c_void,
+            "glVertexAttribFormat" => vertex_attrib_format as *const c_void,
+            "glVertexAttribBinding" => vertex_attrib_binding as *const c_void,
+            "glDeleteVertexArrays" => delete_vertex_arrays as *const c_void,
+            "glGetProgramInterfaceiv" => get_program_interfaceiv as *const c_void,
+            "glGetProgramResourceiv" => get_program_resourceiv as *const c_void,
+            "glGetProgramResourceName" => get_program_resource_name as *const c_void,
+            _ => std::ptr::null(),
+        })
+    }
+
+    #[test]
+    fn test_ring_allocation() {
+        let mut ring = RingAllocator::new(64, 3);
+        assert_eq!(ring.allocate(10, 4), Some(0));
+        assert_eq!(ring.allocate(8, 12), Some(12));
+        assert_eq!(ring.used(), 20);
+        // Doesn't spill into the next frame's region.
+        assert_eq!(ring.allocate(48, 1), None);
+        assert_eq!(ring.allocate(44, 1), Some(20));
+
+        assert_eq!(ring.next_region(), 1);
+        assert_eq!(ring.used(), 0);
+        assert_eq!(ring.allocate(12, 12), Some(72));
+        assert_eq!(ring.next_region(), 2);
+        assert_eq!(ring.next_region(), 0);
+        assert_eq!(ring.allocate(1, 1), Some(0));
+    }
+
+    #[test]
+    fn test_single_region() {
+        let mut ring = RingAllocator::new(16, StreamStrategy::Orphaning.region_count());
+        assert_eq!(ring.allocate(16, 4), Some(0));
+        assert_eq!(ring.next_region(), 0);
+        assert_eq!(ring.allocate(16, 4), Some(0));
+        assert_eq!(ring.total_size(), 16);
+    }
+
+    #[test]
+    fn test_orphaning_without_named_buffers() {
+        let gl = pre_dsa_gl();
+        assert_eq!(StreamStrategy::best(&gl), StreamStrategy::Orphaning);
+
+        let mut stream = StreamBuffer::new(&gl, 16);
+        assert_eq!(stream.push(&gl, &[1u32, 2], 4), Ok(0));
+        assert_eq!(stream.push(&gl, &[3u8], 4), Ok(8));
+        assert!(stream.push(&gl, &[0u32; 2], 4).is_err());
+        stream.end_frame(&gl);
+        assert_eq!(stream.used(), 0);
+        assert_eq!(stream.push(&gl, &[0u32; 4], 4), Ok(0));
+        stream.delete(&gl);
+
+        let calls = CALLS.with(|calls| calls.take());
+        assert_eq!(
+            calls,
+            [
+                "GenBuffers 1",
+                "BindBuffer 7",
+                "BufferData 16",
+                "BindBuffer 0",
+                "BindBuffer 7",
+                "BufferSubData 0 8",
+                "BindBuffer 0",
+                "BindBuffer 7",
+                "BufferSubData 8 1",
+                "BindBuffer 0",
+                "BindBuffer 7",
+                "BufferData 16",
+                "BindBuffer 0",
+                "BindBuffer 7",
+                "BufferSubData 0 16",
+                "BindBuffer 0",
+                "DeleteBuffers 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stream_vertices_without_named_vertex_arrays() {
+        let gl = pre_dsa_gl();
+        let mut vertices = StreamVertexBuffer::new(&gl, DebugVertex::layout(), 4);
+        vertices
+            .bind_reflected(&gl, &Reflection::new(&gl, 1))
+            .unwrap();
+        let vertex = DebugVertex {
+            position: Vec3::ONE,
+            color: Vec4::ONE,
+        };
+        assert_eq!(
+            vertices.push(&gl, &[vertex; 2]),
+            Ok(StreamRange { first: 0, count: 2 })
+        );
+        vertices.delete(&gl);
+
+        let calls = CALLS.with(|calls| calls.take());
+        assert_eq!(
+            calls,
+            [
+                "GenBuffers 1",
+                "BindBuffer 7",
+                "BufferData 128",
+                "BindBuffer 0",
+                "GenVertexArrays 1",
+                "BindVertexArray 3",
+                "BindVertexBuffer 0 7 0 32",
+                "BindVertexArray 0",
+                "BindVertexArray 3",
+                "EnableVertexAttribArray 2",
+                "VertexAttribFormat 2 3 0",
+                "VertexAttribBinding 2 0",
+                "BindVertexArray 0",
+                "BindBuffer 7",
+                "BufferSubData 0 64",
+                "BindBuffer 0",
+                "DeleteVertexArrays 1",
+                "DeleteBuffers 1",
+            ]
+        );
+    }
+}
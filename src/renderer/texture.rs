@@ -1,15 +1,50 @@
-use std::{collections::HashMap, os::raw::c_void};
+use std::{collections::HashMap, io::Cursor, os::raw::c_void, rc::Rc};
 
-use image::ImageReader;
+use glam::Vec3;
+use image::{DynamicImage, ImageReader, Rgb, RgbImage};
 
 use crate::{
-    gl::{self, types::GLuint, Gl},
+    assets,
+    gl::{
+        self,
+        types::{GLenum, GLuint},
+        Gl,
+    },
     shader::{Shader, ShaderTrait},
 };
 
+use super::asset_cache::TextureAsset;
+
+struct Texture {
+    texture: Rc<TextureAsset>,
+    /// Texture unit the sampler uniform of the same name reads from.
+    unit: i32,
+}
+
+/// Wrapping and filtering of a texture, as `gl::REPEAT`, `gl::LINEAR` etc.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureSampler {
+    pub wrap_s: GLenum,
+    pub wrap_t: GLenum,
+    pub mag_filter: GLenum,
+    pub min_filter: GLenum,
+}
+
+impl Default for TextureSampler {
+    fn default() -> Self {
+        Self {
+            wrap_s: gl::REPEAT,
+            wrap_t: gl::REPEAT,
+            mag_filter: gl::NEAREST,
+            min_filter: gl::NEAREST,
+        }
+    }
+}
+
+/// The samplers of one object, each reading a texture from its own unit.
 #[derive(Default)]
 pub struct TextureManager {
-    texture_name_map: HashMap<String, GLuint>,
+    texture_name_map: HashMap<String, Texture>,
 }
 
 impl TextureManager {
@@ -17,54 +52,166 @@ impl TextureManager {
         Self::default()
     }
 
-    pub fn create_texture(
+    /// Load a texture that isn't shared with other objects. Prefer `AssetCache::texture`
+    /// and `add_texture` for textures used more than once.
+    pub fn create_texture(&mut self, gl: &Gl, name: &str, path: &str, index: i32) {
+        let img = match load_image(path) {
+            Ok(img) => img,
+            Err(err) => panic!("{err}"),
+        };
+        self.create_texture_from_image(gl, name, &img, index, &TextureSampler::default());
+    }
+
+    /// Upload an already decoded image. Its first row ends up at texture coordinate t = 0.
+    pub fn create_texture_from_image(
         &mut self,
         gl: &Gl,
         name: &str,
-        path: &str,
-        program: &Shader,
+        img: &DynamicImage,
         index: i32,
+        sampler: &TextureSampler,
     ) {
-        let img = ImageReader::open(path).unwrap().decode().unwrap().flipv();
-
-        let img_height = img.height();
-        let img_width = img.width();
-        let data = img.to_rgba8();
-
-        let mut texture: GLuint = 0;
-        unsafe {
-            gl.GenTextures(1, &mut texture);
-            gl.BindTexture(gl::TEXTURE_2D, texture);
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-            gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-            gl.TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGB as i32,
-                img_width as i32,
-                img_height as i32,
-                0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                data.as_ptr() as *const c_void,
-            );
-            gl.GenerateMipmap(gl::TEXTURE_2D);
-        };
-        program.set_int(gl, name, index).unwrap();
+        let texture = TextureAsset::unmanaged(upload_texture(gl, img, sampler));
+        self.add_texture(name, Rc::new(texture), index);
+    }
 
-        self.texture_name_map.insert(name.to_string(), texture);
+    /// Read the sampler uniform `name` from `texture` bound to unit `index`.
+    pub fn add_texture(&mut self, name: &str, texture: Rc<TextureAsset>, index: i32) {
+        self.texture_name_map.insert(
+            name.to_string(),
+            Texture {
+                texture,
+                unit: index,
+            },
+        );
     }
 
     pub fn bind_texture(&self, gl: &Gl, name: &str, unit: GLuint) {
         let texture = self.get_texture(name).unwrap();
         unsafe {
-            gl.BindTextureUnit(unit, *texture);
+            gl.BindTextureUnit(unit, texture.texture.id());
+        }
+    }
+
+    /// Bind every texture to the unit its sampler reads from.
+    pub fn bind_textures(&self, gl: &Gl) {
+        for texture in self.texture_name_map.values() {
+            unsafe {
+                gl.BindTextureUnit(texture.unit as GLuint, texture.texture.id());
+            }
         }
     }
 
-    fn get_texture(&self, name: &str) -> Option<&GLuint> {
+    /// Point every sampler uniform in `program` at its texture unit. Has to be called again
+    /// whenever the program changes, e.g. after a reload. Samplers the program doesn't use,
+    /// like the specular map in a variant without one, are skipped.
+    pub fn sync_samplers(&self, gl: &Gl, program: &Shader) {
+        for (name, texture) in &self.texture_name_map {
+            if program.reflection().uniform(name).is_some() {
+                program.set_uniform(gl, name, &texture.unit).unwrap();
+            }
+        }
+    }
+
+    fn get_texture(&self, name: &str) -> Option<&Texture> {
         self.texture_name_map.get(name)
     }
 }
+
+/// Decode an image asset, flipped so its bottom row is at texture coordinate t = 0.
+pub fn load_image(path: &str) -> Result<DynamicImage, String> {
+    let data = assets::resolver().read(path)?;
+    let img = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|err| format!("Failed to read {path}: {err}"))?
+        .decode()
+        .map_err(|err| format!("Failed to decode {path}: {err}"))?;
+    Ok(img.flipv())
+}
+
+/// The luminance of `img` as a height map, white being the top.
+pub fn height_map(img: &DynamicImage) -> DynamicImage {
+    DynamicImage::ImageLuma8(img.to_luma8())
+}
+
+/// A tangent space normal map of `height`, which wraps around at the edges. `strength` is the
+/// height difference between white and black, in texels.
+pub fn normal_map_from_height(height: &DynamicImage, strength: f32) -> DynamicImage {
+    let height = height.to_luma32f();
+    let (width, rows) = height.dimensions();
+    let sample = |x: i64, y: i64| {
+        height.get_pixel(
+            x.rem_euclid(width as i64) as u32,
+            y.rem_euclid(rows as i64) as u32,
+        )[0]
+    };
+
+    let normals = RgbImage::from_fn(width, rows, |x, y| {
+        let (x, y) = (x as i64, y as i64);
+        // Rows go up +v, as the image is already flipped for GL.
+        let du = (sample(x + 1, y) - sample(x - 1, y)) * 0.5;
+        let dv = (sample(x, y + 1) - sample(x, y - 1)) * 0.5;
+        let normal = Vec3::new(-du * strength, -dv * strength, 1.0).normalize();
+        let encoded = (normal * 0.5 + Vec3::splat(0.5)) * 255.0;
+        Rgb(encoded.round().to_array().map(|c| c as u8))
+    });
+    DynamicImage::ImageRgb8(normals)
+}
+
+/// Create a texture object holding `img` and its mipmaps.
+pub fn upload_texture(gl: &Gl, img: &DynamicImage, sampler: &TextureSampler) -> GLuint {
+    let img_height = img.height();
+    let img_width = img.width();
+    let data = img.to_rgba8();
+
+    let mut texture: GLuint = 0;
+    unsafe {
+        gl.GenTextures(1, &mut texture);
+        gl.BindTexture(gl::TEXTURE_2D, texture);
+        gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, sampler.wrap_s as i32);
+        gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, sampler.wrap_t as i32);
+        gl.TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MAG_FILTER,
+            sampler.mag_filter as i32,
+        );
+        gl.TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MIN_FILTER,
+            sampler.min_filter as i32,
+        );
+        gl.TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGB as i32,
+            img_width as i32,
+            img_height as i32,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            data.as_ptr() as *const c_void,
+        );
+        gl.GenerateMipmap(gl::TEXTURE_2D);
+    };
+    texture
+}
+
+#[cfg(test)]
+mod test {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    #[test]
+    fn test_normal_map_from_height() {
+        let flat = DynamicImage::ImageLuma8(GrayImage::from_pixel(4, 4, Luma([100])));
+        let normals = normal_map_from_height(&flat, 4.0).to_rgb8();
+        assert!(normals.pixels().all(|pixel| pixel.0 == [128, 128, 255]));
+
+        // Rising along +u, so the normal leans towards -u.
+        let ramp = DynamicImage::ImageLuma8(GrayImage::from_fn(4, 4, |x, _| Luma([x as u8 * 50])));
+        let normal = normal_map_from_height(&ramp, 4.0).to_rgb8()[(1, 1)];
+        assert!(normal[0] < 128);
+        assert_eq!(normal[1], 128);
+    }
+}
@@ -0,0 +1,324 @@
+use glam::{Mat3, Mat4, Vec2, Vec3, Vec4};
+
+use crate::{
+    gl::{self, types::GLuint, Gl},
+    object::light::LightAttributes,
+    shader::{Shader, ShaderDefines},
+};
+
+/// Binding point of the `Frame` block declared in `common/frame.glsl`.
+pub const FRAME_BLOCK_BINDING: GLuint = 0;
+/// Binding point of the `Lights` block declared in `common/lighting.glsl`.
+pub const LIGHTS_BLOCK_BINDING: GLuint = 1;
+/// Size of the `lights` array in the `Lights` block.
+pub const MAX_LIGHTS: usize = 8;
+
+/// Serializes values into a byte buffer following the std140 layout rules.
+#[derive(Default)]
+pub struct Std140Writer {
+    data: Vec<u8>,
+}
+
+impl Std140Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current write offset in bytes.
+    pub fn offset(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Pad with zeros up to the next multiple of `alignment`.
+    pub fn align_to(&mut self, alignment: usize) -> &mut Self {
+        let padding = self.data.len().next_multiple_of(alignment) - self.data.len();
+        self.data.extend(std::iter::repeat_n(0, padding));
+        self
+    }
+
+    /// Pad with zeros up to `offset`, e.g. for unused trailing array elements.
+    pub fn pad_to(&mut self, offset: usize) -> &mut Self {
+        if offset > self.data.len() {
+            self.data.resize(offset, 0);
+        }
+        self
+    }
+
+    pub fn float(&mut self, val: f32) -> &mut Self {
+        self.write(4, &[val.to_ne_bytes()])
+    }
+
+    pub fn int(&mut self, val: i32) -> &mut Self {
+        self.write(4, &[val.to_ne_bytes()])
+    }
+
+    pub fn uint(&mut self, val: u32) -> &mut Self {
+        self.write(4, &[val.to_ne_bytes()])
+    }
+
+    pub fn bool(&mut self, val: bool) -> &mut Self {
+        self.uint(val.into())
+    }
+
+    pub fn vec2(&mut self, val: Vec2) -> &mut Self {
+        self.write(8, &val.to_array().map(f32::to_ne_bytes))
+    }
+
+    /// A vec3 is aligned like a vec4 but only takes 12 bytes, so a following scalar packs into
+    /// its last component.
+    pub fn vec3(&mut self, val: Vec3) -> &mut Self {
+        self.write(16, &val.to_array().map(f32::to_ne_bytes))
+    }
+
+    pub fn vec4(&mut self, val: Vec4) -> &mut Self {
+        self.write(16, &val.to_array().map(f32::to_ne_bytes))
+    }
+
+    /// Matrices are stored as arrays of column vectors, each padded to a vec4.
+    pub fn mat3(&mut self, val: Mat3) -> &mut Self {
+        for column in val.to_cols_array_2d() {
+            self.vec3(Vec3::from_array(column)).align_to(16);
+        }
+        self
+    }
+
+    pub fn mat4(&mut self, val: Mat4) -> &mut Self {
+        for column in val.to_cols_array_2d() {
+            self.vec4(Vec4::from_array(column));
+        }
+        self
+    }
+
+    /// Array elements are aligned and strided to a multiple of a vec4.
+    pub fn float_array(&mut self, vals: &[f32]) -> &mut Self {
+        for val in vals {
+            self.align_to(16).float(*val);
+        }
+        self.align_to(16)
+    }
+
+    /// Write a struct member. Structs start and end on a vec4 boundary.
+    pub fn structure(&mut self, write_fields: impl FnOnce(&mut Self)) -> &mut Self {
+        self.align_to(16);
+        write_fields(self);
+        self.align_to(16)
+    }
+
+    /// Finish the block, padding it to a whole number of vec4s.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.align_to(16);
+        self.data
+    }
+
+    fn write<const N: usize>(&mut self, alignment: usize, components: &[[u8; 4]; N]) -> &mut Self {
+        self.align_to(alignment);
+        for component in components {
+            self.data.extend_from_slice(component);
+        }
+        self
+    }
+}
+
+/// A buffer backing a uniform block, bound to a fixed binding point shared by every program.
+pub struct UniformBuffer {
+    id: GLuint,
+    binding: GLuint,
+    size: usize,
+}
+
+impl UniformBuffer {
+    pub fn new(gl: &Gl, binding: GLuint, size: usize) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl.CreateBuffers(1, &mut id);
+            gl.NamedBufferData(
+                id,
+                size as gl::types::GLsizeiptr,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl.BindBufferBase(gl::UNIFORM_BUFFER, binding, id);
+        }
+
+        Self { id, binding, size }
+    }
+
+    /// Replace the contents of the buffer. `data` has to be exactly the size of the block.
+    pub fn update(&self, gl: &Gl, data: &[u8]) {
+        assert_eq!(
+            data.len(),
+            self.size,
+            "Uniform buffer data doesn't match the block size"
+        );
+        unsafe {
+            gl.NamedBufferSubData(
+                self.id,
+                0,
+                data.len() as gl::types::GLsizeiptr,
+                data.as_ptr().cast(),
+            );
+        }
+    }
+
+    /// Check that `shader` declares the block `name` at this buffer's binding point with the
+    /// same size, catching GLSL and Rust declarations that drifted apart.
+    pub fn check_block(&self, shader: &Shader, name: &str) -> Result<(), String> {
+        let reflection = shader.reflection();
+        let Some(block) = reflection.uniform_block(name) else {
+            // Blocks the program doesn't use are optimized away.
+            return Ok(());
+        };
+
+        if block.binding as GLuint != self.binding {
+            return Err(format!(
+                "Uniform block {name} is bound to {}, expected {}",
+                block.binding, self.binding
+            ));
+        }
+        if block.data_size as usize != self.size {
+            return Err(format!(
+                "Uniform block {name} is {} bytes, expected {}",
+                block.data_size, self.size
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn binding(&self) -> GLuint {
+        self.binding
+    }
+}
+
+/// Contents of the `Frame` block, written once per frame.
+pub struct FrameUniforms {
+    pub view: Mat4,
+    pub projection: Mat4,
+    pub camera_position: Vec3,
+    pub time: f32,
+}
+
+impl FrameUniforms {
+    pub const SIZE: usize = 144;
+
+    pub fn std140(&self) -> Vec<u8> {
+        let mut writer = Std140Writer::new();
+        writer
+            .mat4(self.view)
+            .mat4(self.projection)
+            .vec3(self.camera_position)
+            .float(self.time);
+        writer.finish()
+    }
+}
+
+/// Contents of the `Lights` block.
+pub struct LightsUniforms;
+
+impl LightsUniforms {
+    /// Size of one `Light` struct in the `lights` array.
+    const LIGHT_SIZE: usize = 80;
+    pub const SIZE: usize = 16 + Self::LIGHT_SIZE * MAX_LIGHTS;
+
+    /// Sizes the `lights` array of the block in the shaders to match `SIZE`.
+    pub fn defines() -> ShaderDefines {
+        ShaderDefines::new().with("MAX_LIGHTS", MAX_LIGHTS)
+    }
+
+    /// Serialize up to `MAX_LIGHTS` lights, any further lights are dropped.
+    pub fn std140(lights: &[&LightAttributes]) -> Vec<u8> {
+        let lights = &lights[..lights.len().min(MAX_LIGHTS)];
+
+        let mut writer = Std140Writer::new();
+        writer.int(lights.len() as i32).align_to(16);
+        for light in lights {
+            light.write_std140(&mut writer);
+        }
+        writer.pad_to(Self::SIZE);
+        writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::{vec2, vec3, Mat4};
+
+    use super::*;
+
+    #[test]
+    fn test_std140_alignment() {
+        let mut writer = Std140Writer::new();
+        writer.float(1.0);
+        writer.vec3(vec3(1.0, 2.0, 3.0));
+        assert_eq!(writer.offset(), 28);
+        writer.float(4.0);
+        assert_eq!(writer.offset(), 32);
+        writer.vec2(vec2(1.0, 2.0));
+        writer.float(1.0);
+        writer.mat4(Mat4::IDENTITY);
+        assert_eq!(writer.offset(), 48 + 64);
+        assert_eq!(writer.finish().len(), 112);
+    }
+
+    #[test]
+    fn test_std140_arrays_and_structs() {
+        let mut writer = Std140Writer::new();
+        writer.float_array(&[1.0, 2.0, 3.0]);
+        assert_eq!(writer.offset(), 48);
+
+        writer.float(0.0);
+        writer.structure(|writer| {
+            writer.vec3(Vec3::ONE).float(1.0);
+        });
+        assert_eq!(writer.offset(), 80);
+
+        let mut writer = Std140Writer::new();
+        writer.mat3(Mat3::IDENTITY);
+        assert_eq!(writer.offset(), 48);
+    }
+
+    #[test]
+    fn test_frame_uniforms_size() {
+        let frame = FrameUniforms {
+            view: Mat4::IDENTITY,
+            projection: Mat4::IDENTITY,
+            camera_position: Vec3::ZERO,
+            time: 0.0,
+        };
+        assert_eq!(frame.std140().len(), FrameUniforms::SIZE);
+    }
+
+    #[test]
+    fn test_lights_uniforms_layout() {
+        let light = LightAttributes::default();
+
+        let mut writer = Std140Writer::new();
+        light.write_std140(&mut writer);
+        assert_eq!(writer.offset(), LightsUniforms::LIGHT_SIZE);
+
+        let data = LightsUniforms::std140(&[&light, &light]);
+        assert_eq!(data.len(), LightsUniforms::SIZE);
+        assert_eq!(i32::from_ne_bytes(data[0..4].try_into().unwrap()), 2);
+        // `quadratic` of the second light.
+        let quadratic = 16 + LightsUniforms::LIGHT_SIZE + 68;
+        assert_eq!(
+            f32::from_ne_bytes(data[quadratic..quadratic + 4].try_into().unwrap()),
+            light.quadratic
+        );
+
+        let defines = LightsUniforms::defines();
+        let (_, max_lights) = defines
+            .iter()
+            .find(|(name, _)| *name == "MAX_LIGHTS")
+            .unwrap();
+        let max_lights = max_lights.parse::<usize>().unwrap();
+        assert_eq!(
+            16 + LightsUniforms::LIGHT_SIZE * max_lights,
+            LightsUniforms::SIZE
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::mesh::bounds::Bounds;
+
+/// Translation, rotation and scale, applied in reverse order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Decompose an affine matrix without shear.
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /// Rotate around `axis` in the transform's own space.
+    pub fn rotate_local(&mut self, axis: Vec3, degrees: f32) {
+        self.rotation =
+            (self.rotation * Quat::from_axis_angle(axis, degrees.to_radians())).normalize();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+struct Node {
+    name: String,
+    local: Transform,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    /// Valid unless the node or one of its ancestors is dirty.
+    world: Mat4,
+    dirty: bool,
+    /// Of the node's own geometry, in its local space.
+    bounds: Option<Bounds>,
+    /// World space bounds of the node and its descendants, as of the last `update`.
+    world_bounds: Option<Bounds>,
+}
+
+/// Transforms linked into a hierarchy. Each node's world matrix is its parent's world matrix
+/// times its local transform.
+///
+/// Changing a node only marks it dirty. `update` then recomputes the world matrices of the
+/// dirty nodes and their descendants, and leaves every other cached matrix alone.
+///
+/// Nodes can also carry the bounds of their geometry, which `update` merges up the hierarchy
+/// so every node knows the world space extent of its subtree.
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+    /// Whether bounds or the hierarchy changed since the world bounds were merged.
+    bounds_dirty: bool,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a node without a parent.
+    pub fn add_node(&mut self, name: &str, local: Transform) -> NodeId {
+        self.nodes.push(Node {
+            name: name.to_string(),
+            local,
+            parent: None,
+            children: vec![],
+            world: Mat4::IDENTITY,
+            dirty: true,
+            bounds: None,
+            world_bounds: None,
+        });
+        NodeId(self.nodes.len() - 1)
+    }
+
+    pub fn add_child(&mut self, parent: NodeId, name: &str, local: Transform) -> NodeId {
+        let node = self.add_node(name, local);
+        self.link(node, parent);
+        node
+    }
+
+    /// Move `node` under `parent`, or make it a root with `None`. Its local transform is kept,
+    /// so it moves with its new parent.
+    pub fn set_parent(&mut self, node: NodeId, parent: Option<NodeId>) -> Result<(), String> {
+        if let Some(parent) = parent {
+            if self.is_ancestor(node, parent) {
+                return Err(format!(
+                    "Can't attach {} to its descendant {}",
+                    self.name(node),
+                    self.name(parent)
+                ));
+            }
+        }
+
+        if let Some(old_parent) = self.nodes[node.0].parent.take() {
+            self.nodes[old_parent.0]
+                .children
+                .retain(|child| *child != node);
+        }
+        if let Some(parent) = parent {
+            self.link(node, parent);
+        }
+        self.nodes[node.0].dirty = true;
+        self.bounds_dirty = true;
+        Ok(())
+    }
+
+    fn link(&mut self, node: NodeId, parent: NodeId) {
+        self.nodes[node.0].parent = Some(parent);
+        self.nodes[parent.0].children.push(node);
+    }
+
+    /// Whether `ancestor` is `node` or one of the nodes above it.
+    fn is_ancestor(&self, ancestor: NodeId, node: NodeId) -> bool {
+        let mut current = Some(node);
+        while let Some(id) = current {
+            if id == ancestor {
+                return true;
+            }
+            current = self.nodes[id.0].parent;
+        }
+        false
+    }
+
+    pub fn name(&self, node: NodeId) -> &str {
+        &self.nodes[node.0].name
+    }
+
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.nodes[node.0].parent
+    }
+
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        &self.nodes[node.0].children
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes
+            .iter()
+            .position(|node| node.name == name)
+            .map(NodeId)
+    }
+
+    pub fn local(&self, node: NodeId) -> &Transform {
+        &self.nodes[node.0].local
+    }
+
+    pub fn set_local(&mut self, node: NodeId, local: Transform) {
+        let node = &mut self.nodes[node.0];
+        node.local = local;
+        node.dirty = true;
+    }
+
+    /// Change the local transform in place.
+    pub fn update_local(&mut self, node: NodeId, update: impl FnOnce(&mut Transform)) {
+        let node = &mut self.nodes[node.0];
+        update(&mut node.local);
+        node.dirty = true;
+    }
+
+    /// Set the bounds of the node's own geometry, in its local space.
+    pub fn set_bounds(&mut self, node: NodeId, bounds: Option<Bounds>) {
+        self.nodes[node.0].bounds = bounds;
+        self.bounds_dirty = true;
+    }
+
+    pub fn bounds(&self, node: NodeId) -> Option<Bounds> {
+        self.nodes[node.0].bounds
+    }
+
+    /// World space bounds around the node and all its descendants, as of the last `update`.
+    /// `None` if none of them has bounds.
+    pub fn world_bounds(&self, node: NodeId) -> Option<Bounds> {
+        self.nodes[node.0].world_bounds
+    }
+
+    /// The cached world matrix, as of the last `update`.
+    pub fn world_matrix(&self, node: NodeId) -> Mat4 {
+        self.nodes[node.0].world
+    }
+
+    pub fn world_position(&self, node: NodeId) -> Vec3 {
+        self.world_matrix(node).w_axis.truncate()
+    }
+
+    /// Recompute the world matrices of dirty nodes and their descendants, and the world bounds
+    /// if anything moved. Returns how many matrices were recomputed.
+    pub fn update(&mut self) -> usize {
+        let mut updated = 0;
+        // Parents come before their children, so walking it backwards visits children first.
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(index, _)| (NodeId(index), false))
+            .collect::<Vec<_>>();
+
+        while let Some((id, parent_changed)) = stack.pop() {
+            order.push(id);
+            let changed = parent_changed || self.nodes[id.0].dirty;
+            if changed {
+                let parent_world = self.nodes[id.0]
+                    .parent
+                    .map_or(Mat4::IDENTITY, |parent| self.nodes[parent.0].world);
+                let node = &mut self.nodes[id.0];
+                node.world = parent_world * node.local.matrix();
+                node.dirty = false;
+                updated += 1;
+            }
+            stack.extend(
+                self.nodes[id.0]
+                    .children
+                    .iter()
+                    .map(|child| (*child, changed)),
+            );
+        }
+
+        if updated > 0 || self.bounds_dirty {
+            self.merge_bounds(&order);
+            self.bounds_dirty = false;
+        }
+        updated
+    }
+
+    fn merge_bounds(&mut self, order: &[NodeId]) {
+        for id in order.iter().rev() {
+            let node = &self.nodes[id.0];
+            let own = node.bounds.map(|bounds| bounds.transformed(&node.world));
+            let merged = node
+                .children
+                .iter()
+                .filter_map(|child| self.nodes[child.0].world_bounds)
+                .chain(own)
+                .reduce(|a, b| a.union(&b));
+            self.nodes[id.0].world_bounds = merged;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{a} != {b}");
+    }
+
+    #[test]
+    fn test_world_matrices() {
+        let mut scene = SceneGraph::new();
+        let root = scene.add_node("root", Transform::from_translation(Vec3::X));
+        let arm = scene.add_child(
+            root,
+            "arm",
+            Transform {
+                translation: Vec3::Y,
+                rotation: Quat::from_rotation_z(90f32.to_radians()),
+                scale: Vec3::splat(2.0),
+            },
+        );
+        let hand = scene.add_child(arm, "hand", Transform::from_translation(Vec3::X));
+        assert_eq!(scene.update(), 3);
+
+        // One unit along the rotated, scaled arm.
+        assert_near(scene.world_position(hand), Vec3::new(1.0, 3.0, 0.0));
+        assert_eq!(scene.find("arm"), Some(arm));
+    }
+
+    #[test]
+    fn test_only_dirty_subtrees_update() {
+        let mut scene = SceneGraph::new();
+        let a = scene.add_node("a", Transform::IDENTITY);
+        let b = scene.add_child(a, "b", Transform::IDENTITY);
+        let c = scene.add_child(b, "c", Transform::IDENTITY);
+        let other = scene.add_node("other", Transform::IDENTITY);
+        scene.update();
+        assert_eq!(scene.update(), 0);
+
+        scene.update_local(b, |local| local.translation = Vec3::Z);
+        assert_eq!(scene.update(), 2);
+        assert_near(scene.world_position(c), Vec3::Z);
+        assert_near(scene.world_position(other), Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_set_parent() {
+        let mut scene = SceneGraph::new();
+        let a = scene.add_node("a", Transform::from_translation(Vec3::X));
+        let b = scene.add_child(a, "b", Transform::IDENTITY);
+        let light = scene.add_node("light", Transform::from_translation(Vec3::Y));
+
+        assert!(scene.set_parent(a, Some(b)).is_err());
+        scene.set_parent(light, Some(b)).unwrap();
+        scene.update();
+        assert_near(scene.world_position(light), Vec3::new(1.0, 1.0, 0.0));
+
+        scene.set_parent(light, None).unwrap();
+        scene.update();
+        assert!(scene.children(b).is_empty());
+        assert_near(scene.world_position(light), Vec3::Y);
+    }
+
+    #[test]
+    fn test_world_bounds() {
+        let unit = Bounds::from_points([Vec3::splat(-1.0), Vec3::ONE]).unwrap();
+        let mut scene = SceneGraph::new();
+        let root = scene.add_node("root", Transform::from_translation(Vec3::X));
+        let a = scene.add_child(root, "a", Transform::from_translation(Vec3::Y * 2.0));
+        let b = scene.add_child(root, "b", Transform::from_translation(Vec3::Y * -2.0));
+        scene.set_bounds(a, Some(unit));
+        scene.set_bounds(b, Some(unit));
+        scene.update();
+
+        let bounds = scene.world_bounds(root).unwrap();
+        assert_near(bounds.aabb.min, Vec3::new(0.0, -3.0, -1.0));
+        assert_near(bounds.aabb.max, Vec3::new(2.0, 3.0, 1.0));
+        assert_near(bounds.sphere.center, Vec3::X);
+        assert!((bounds.sphere.radius - (2.0 + 3f32.sqrt())).abs() < 1e-5);
+
+        // Moving a child, or taking its bounds away, shrinks the root's.
+        scene.set_bounds(b, None);
+        scene.update();
+        assert_near(
+            scene.world_bounds(root).unwrap().aabb.min,
+            Vec3::new(0.0, 1.0, -1.0),
+        );
+        scene.update_local(a, |local| local.scale = Vec3::splat(0.5));
+        scene.update();
+        assert_near(
+            scene.world_bounds(root).unwrap().aabb.max,
+            Vec3::new(1.5, 2.5, 0.5),
+        );
+        assert_eq!(scene.world_bounds(b), None);
+    }
+
+    #[test]
+    fn test_transform_round_trip() {
+        let transform = Transform {
+            translation: Vec3::new(1.0, 2.0, 3.0),
+            rotation: Quat::from_rotation_y(0.5),
+            scale: Vec3::new(1.0, 2.0, 3.0),
+        };
+        let decomposed = Transform::from_matrix(transform.matrix());
+        assert_near(decomposed.translation, transform.translation);
+        assert_near(decomposed.scale, transform.scale);
+        assert!(decomposed.rotation.abs_diff_eq(transform.rotation, 1e-5));
+    }
+}
@@ -1,9 +1,33 @@
-use std::fs;
+pub mod binary_cache;
+pub mod builder;
+pub mod cache;
+pub mod compute;
+pub(crate) mod preprocess;
+pub mod reflection;
+pub mod uniform;
 
-use glam::Mat4;
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+use binary_cache::ProgramBinaryCache;
+use builder::{ShaderBuilder, ShaderStage};
+use preprocess::{preprocess, PreprocessedSource, SHADER_ROOT};
+use reflection::{glsl_type_name, is_sampler, AttributeInfo, Reflection};
+use uniform::{Uniform, UniformStruct, UniformValue};
 
 use crate::{
-    gl::{self, create_shader, types::GLuint, Gl},
+    assets,
+    gl::{
+        self, create_shader, get_program_info_log, get_shader_info_log,
+        types::{GLenum, GLuint},
+        Gl,
+    },
     helper::add_null_term,
 };
 
@@ -12,147 +36,386 @@ pub trait ShaderTrait {
 
     fn enable(&self, gl: &Gl);
 
-    fn set_bool(&self, gl: &Gl, name: &str, val: bool) -> Result<(), String>;
-    fn set_int(&self, gl: &Gl, name: &str, val: i32) -> Result<(), String>;
-    fn set_float(&self, gl: &Gl, name: &str, val: f32) -> Result<(), String>;
-    fn set_vec2(&self, gl: &Gl, name: &str, val: (f32, f32)) -> Result<(), String>;
-    fn set_vec3(&self, gl: &Gl, name: &str, val: (f32, f32, f32)) -> Result<(), String>;
-    fn set_mat4(&self, gl: &Gl, name: &str, val: Mat4) -> Result<(), String>;
+    /// Set the default block uniform `name`. Arrays and slices fill consecutive elements of a
+    /// GLSL array, starting at `name`, which can also be an element like `offsets[2]`.
+    fn set_uniform<T: Uniform + ?Sized>(&self, gl: &Gl, name: &str, val: &T) -> Result<(), String>;
+
+    /// Set every field of the struct uniform `name`.
+    fn set_struct<T: UniformStruct>(&self, gl: &Gl, name: &str, val: &T) -> Result<(), String>
+    where
+        Self: Sized,
+    {
+        val.set_uniforms(gl, self, name)
+    }
+
+    /// Set the elements of an array of struct uniforms.
+    fn set_struct_array<T: UniformStruct>(
+        &self,
+        gl: &Gl,
+        name: &str,
+        vals: &[T],
+    ) -> Result<(), String>
+    where
+        Self: Sized,
+    {
+        vals.iter()
+            .enumerate()
+            .try_for_each(|(i, val)| val.set_uniforms(gl, self, &format!("{name}[{i}]")))
+    }
+}
+
+/// Preprocessor definitions a program variant is compiled with. They are injected right after
+/// the `#version` line of every stage.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderDefines {
+    defines: BTreeMap<String, String>,
+}
+
+impl ShaderDefines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `#define name value`
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.defines.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// `#define name`, for use with `#ifdef`.
+    pub fn flag(self, name: &str) -> Self {
+        self.with(name, "")
+    }
+
+    /// Combine with `other`, whose values win on conflicts.
+    pub fn merge(mut self, other: &ShaderDefines) -> Self {
+        self.defines.extend(other.defines.clone());
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.defines
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+/// One stage of a program and the file it is compiled from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct StageSource {
+    stage: ShaderStage,
+    path: String,
 }
 
-#[derive(Clone)]
+/// Not `Clone`: a reload deletes the old program, which would dangle in any copy.
 pub struct Shader {
-    program_id: GLuint,
+    program_id: Cell<GLuint>,
+    reflection: RefCell<Reflection>,
+    stages: Vec<StageSource>,
+    defines: ShaderDefines,
+    binary_cache: Option<Rc<ProgramBinaryCache>>,
+    /// Every file the current program was built from, including expanded `#include`s.
+    dependencies: RefCell<Vec<PathBuf>>,
+    /// Newest modification time of the sources the current program was built from.
+    last_modified: Cell<Option<SystemTime>>,
 }
 
 impl ShaderTrait for Shader {
     fn get_id(&self) -> GLuint {
-        self.program_id
+        self.program_id.get()
     }
 
     fn enable(&self, gl: &Gl) {
         unsafe {
-            gl.UseProgram(self.program_id);
+            gl.UseProgram(self.get_id());
         }
     }
 
-    fn set_bool(&self, gl: &Gl, name: &str, val: bool) -> Result<(), String> {
-        match self.get_uniform_id(gl, name) {
-            Ok(id) => {
-                self.enable(gl);
-                unsafe {
-                    gl.Uniform1i(id, val.into());
-                }
-                Ok(())
-            }
-            Err(err) => Err(err),
+    fn set_uniform<T: Uniform + ?Sized>(&self, gl: &Gl, name: &str, val: &T) -> Result<(), String> {
+        let values = val.elements();
+        let id = self.get_uniform_id(name, T::Element::GL_TYPES, values.len())?;
+        self.enable(gl);
+        unsafe {
+            T::Element::upload(gl, id, values);
         }
+        Ok(())
     }
+}
 
-    fn set_int(&self, gl: &Gl, name: &str, val: i32) -> Result<(), String> {
-        match self.get_uniform_id(gl, name) {
-            Ok(id) => {
-                self.enable(gl);
+impl Shader {
+    /// Compile and link a program from the given sources.
+    ///
+    /// Panics with the compiler diagnostics if either stage fails to build.
+    pub fn new(gl: &Gl, vertex_path: &str, fragment_path: &str) -> Self {
+        Self::with_defines(gl, vertex_path, fragment_path, ShaderDefines::new())
+    }
 
-                unsafe {
-                    gl.Uniform1i(id, val);
-                }
-                Ok(())
-            }
-            Err(err) => Err(err),
+    /// Compile the variant of a program selected by `defines`.
+    ///
+    /// Panics with the compiler diagnostics if either stage fails to build.
+    pub fn with_defines(
+        gl: &Gl,
+        vertex_path: &str,
+        fragment_path: &str,
+        defines: ShaderDefines,
+    ) -> Self {
+        Self::with_binary_cache(gl, vertex_path, fragment_path, defines, None)
+    }
+
+    /// Like `with_defines`, but loads the linked program from `binary_cache` when possible.
+    /// Reloads of this shader keep using the cache.
+    pub fn with_binary_cache(
+        gl: &Gl,
+        vertex_path: &str,
+        fragment_path: &str,
+        defines: ShaderDefines,
+        binary_cache: Option<Rc<ProgramBinaryCache>>,
+    ) -> Self {
+        match ShaderBuilder::new()
+            .vertex(vertex_path)
+            .fragment(fragment_path)
+            .defines(defines)
+            .binary_cache(binary_cache)
+            .build(gl)
+        {
+            Ok(shader) => shader,
+            Err(err) => panic!("{err}"),
         }
     }
 
-    fn set_float(&self, gl: &Gl, name: &str, val: f32) -> Result<(), String> {
-        match self.get_uniform_id(gl, name) {
-            Ok(id) => {
-                self.enable(gl);
-                unsafe {
-                    gl.Uniform1f(id, val);
-                }
-                Ok(())
+    /// Start building a program from any combination of stages.
+    pub fn builder() -> ShaderBuilder {
+        ShaderBuilder::new()
+    }
+
+    fn from_stages(
+        gl: &Gl,
+        stages: Vec<StageSource>,
+        defines: ShaderDefines,
+        binary_cache: Option<Rc<ProgramBinaryCache>>,
+    ) -> Result<Self, String> {
+        let (program_id, dependencies) =
+            compile_program(gl, &stages, &defines, binary_cache.as_deref())?;
+        let last_modified = newest_modification(&dependencies);
+
+        Ok(Self {
+            program_id: Cell::new(program_id),
+            reflection: RefCell::new(Reflection::new(gl, program_id)),
+            stages,
+            defines,
+            binary_cache,
+            dependencies: RefCell::new(dependencies),
+            last_modified: Cell::new(last_modified),
+        })
+    }
+
+    /// Recompile the program if any of its source files changed on disk since it was built.
+    ///
+    /// Returns true when the program was swapped. Uniform state does not carry over to the new
+    /// program, so callers need to re-apply it. If the new sources fail to build the previous
+    /// program stays in use and the diagnostics are printed.
+    pub fn reload_if_changed(&self, gl: &Gl) -> bool {
+        let last_modified = newest_modification(&self.dependencies.borrow());
+        if last_modified <= self.last_modified.get() {
+            return false;
+        }
+        self.last_modified.set(last_modified);
+
+        match compile_program(
+            gl,
+            &self.stages,
+            &self.defines,
+            self.binary_cache.as_deref(),
+        ) {
+            Ok((program_id, dependencies)) => {
+                unsafe { gl.DeleteProgram(self.program_id.replace(program_id)) };
+                *self.reflection.borrow_mut() = Reflection::new(gl, program_id);
+                self.last_modified.set(newest_modification(&dependencies));
+                *self.dependencies.borrow_mut() = dependencies;
+                println!("Reloaded shader {}", describe_stages(&self.stages));
+                true
+            }
+            Err(err) => {
+                eprintln!("Keeping previous shader program.\n{err}");
+                false
             }
-            Err(err) => Err(err),
         }
     }
 
-    fn set_vec2(&self, gl: &Gl, name: &str, val: (f32, f32)) -> Result<(), String> {
-        match self.get_uniform_id(gl, name) {
-            Ok(id) => {
-                self.enable(gl);
-                unsafe {
-                    gl.Uniform2f(id, val.0, val.1);
-                }
-                Ok(())
-            }
-            Err(err) => Err(err),
+    /// Reflected interface of the current program.
+    pub fn reflection(&self) -> std::cell::Ref<'_, Reflection> {
+        self.reflection.borrow()
+    }
+
+    /// Look up an active vertex attribute by name.
+    pub fn attribute(&self, name: &str) -> Result<AttributeInfo, String> {
+        self.reflection
+            .borrow()
+            .attribute(name)
+            .cloned()
+            .ok_or_else(|| format!("Attribute {name} is not an active attribute"))
+    }
+
+    /// Location of the default block uniform `name`, checking that its GLSL type is one of
+    /// `expected` and that it has room for `count` elements. Integer setters are also accepted
+    /// for samplers.
+    fn get_uniform_id(&self, name: &str, expected: &[GLenum], count: usize) -> Result<i32, String> {
+        let reflection = self.reflection.borrow();
+        let uniform = reflection
+            .uniform(name)
+            .ok_or_else(|| format!("Uniform {name} is not an active uniform"))?;
+
+        if uniform.location == -1 {
+            return Err(format!(
+                "Uniform {name} is inside a uniform block and can't be set directly"
+            ));
+        }
+
+        let sampler_set_as_int = is_sampler(uniform.gl_type) && expected.contains(&gl::INT);
+        if !expected.contains(&uniform.gl_type) && !sampler_set_as_int {
+            return Err(format!(
+                "Uniform {name} is a {}, not a {}",
+                glsl_type_name(uniform.gl_type),
+                glsl_type_name(expected[0])
+            ));
+        }
+
+        if count > uniform.size as usize {
+            return Err(format!(
+                "Uniform {name} has {} elements, can't set {count}",
+                uniform.size
+            ));
         }
+
+        Ok(uniform.location)
     }
-    fn set_vec3(&self, gl: &Gl, name: &str, val: (f32, f32, f32)) -> Result<(), String> {
-        match self.get_uniform_id(gl, name) {
-            Ok(id) => {
-                self.enable(gl);
-                unsafe {
-                    gl.Uniform3f(id, val.0, val.1, val.2);
-                }
-                Ok(())
-            }
-            Err(err) => Err(err),
+}
+
+/// Newest modification time of the on-disk copies of `paths`. Embedded sources never change.
+fn newest_modification(paths: &[PathBuf]) -> Option<SystemTime> {
+    paths
+        .iter()
+        .filter_map(|path| assets::resolver().disk_path(path))
+        .filter_map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+        .max()
+}
+
+/// Build a program, returning it together with every source file it was built from. Programs
+/// found in `binary_cache` skip compilation, freshly linked ones are added to it.
+fn compile_program(
+    gl: &Gl,
+    stages: &[StageSource],
+    defines: &ShaderDefines,
+    binary_cache: Option<&ProgramBinaryCache>,
+) -> Result<(GLuint, Vec<PathBuf>), String> {
+    let sources = stages
+        .iter()
+        .map(|stage| load_source(&stage.path, defines))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut dependencies: Vec<PathBuf> = vec![];
+    for path in sources.iter().flat_map(|source| &source.files) {
+        if !dependencies.contains(path) {
+            dependencies.push(path.clone());
         }
     }
-    fn set_mat4(&self, gl: &Gl, name: &str, val: Mat4) -> Result<(), String> {
-        match self.get_uniform_id(gl, name) {
-            Ok(id) => {
-                self.enable(gl);
-                unsafe {
-                    gl.UniformMatrix4fv(id, 1, gl::FALSE, val.as_ref().as_ptr());
+
+    let cache_key = binary_cache.map(|cache| {
+        cache.key(
+            &sources
+                .iter()
+                .map(|source| source.code.as_str())
+                .collect::<Vec<_>>(),
+        )
+    });
+    if let (Some(cache), Some(key)) = (binary_cache, cache_key) {
+        if let Some(program_id) = cache.load(gl, key) {
+            return Ok((program_id, dependencies));
+        }
+    }
+
+    let mut shaders = vec![];
+    for (stage, source) in stages.iter().zip(&sources) {
+        match compile_stage(gl, stage.stage, &stage.path, source) {
+            Ok(shader) => shaders.push(shader),
+            Err(err) => {
+                for shader in shaders {
+                    unsafe { gl.DeleteShader(shader) };
                 }
-                Ok(())
+                return Err(err);
             }
-            Err(err) => Err(err),
         }
     }
-}
 
-impl Shader {
-    pub fn new(gl: &Gl, vertex_path: &str, fragment_path: &str) -> Self {
-        let vertex_shader_source = fs::read(vertex_path).unwrap();
-        let vertex_shader =
-            unsafe { create_shader(gl, gl::VERTEX_SHADER, &add_null_term(&vertex_shader_source)) };
-
-        let fragment_shader_source = fs::read(fragment_path).unwrap();
-        let fragment_shader = unsafe {
-            create_shader(
-                gl,
-                gl::FRAGMENT_SHADER,
-                &add_null_term(&fragment_shader_source),
-            )
-        };
-
-        let program_id = unsafe { gl.CreateProgram() };
-        unsafe {
-            gl.AttachShader(program_id, vertex_shader);
-            gl.AttachShader(program_id, fragment_shader);
-            gl.LinkProgram(program_id);
-            gl.DeleteShader(vertex_shader);
-            gl.DeleteShader(fragment_shader);
-        };
-
-        Self { program_id }
-    }
-
-    fn get_uniform_id(&self, gl: &Gl, name: &str) -> Result<i32, String> {
-        let uniform_id = unsafe {
-            gl.GetUniformLocation(
-                self.program_id,
-                add_null_term(name.as_bytes()).as_ptr().cast(),
-            )
-        };
-
-        if uniform_id == -1 {
-            return Err(String::from("Uniform ID was returned as -1"));
+    let program_id = unsafe { gl.CreateProgram() };
+    let mut status = 0;
+    unsafe {
+        if binary_cache.is_some() {
+            gl.ProgramParameteri(
+                program_id,
+                gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                gl::TRUE as i32,
+            );
+        }
+        for shader in &shaders {
+            gl.AttachShader(program_id, *shader);
+        }
+        gl.LinkProgram(program_id);
+        for shader in shaders {
+            gl.DeleteShader(shader);
         }
+        gl.GetProgramiv(program_id, gl::LINK_STATUS, &mut status);
+    };
+
+    if status == gl::FALSE as i32 {
+        let log = get_program_info_log(gl, program_id);
+        unsafe { gl.DeleteProgram(program_id) };
+        return Err(format!(
+            "Failed to link {}:\n{log}",
+            describe_stages(stages)
+        ));
+    }
 
-        Ok(uniform_id)
+    if let (Some(cache), Some(key)) = (binary_cache, cache_key) {
+        cache.store(gl, key, program_id);
     }
+
+    Ok((program_id, dependencies))
+}
+
+fn describe_stages(stages: &[StageSource]) -> String {
+    stages
+        .iter()
+        .map(|stage| stage.path.as_str())
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+fn load_source(path: &str, defines: &ShaderDefines) -> Result<PreprocessedSource, String> {
+    preprocess(Path::new(SHADER_ROOT), Path::new(path), defines, |path| {
+        assets::resolver().read_to_string(path)
+    })
+}
+
+fn compile_stage(
+    gl: &Gl,
+    stage: ShaderStage,
+    path: &str,
+    source: &PreprocessedSource,
+) -> Result<GLuint, String> {
+    let shader =
+        unsafe { create_shader(gl, stage.gl_enum(), &add_null_term(source.code.as_bytes())) };
+
+    let mut status = 0;
+    unsafe { gl.GetShaderiv(shader, gl::COMPILE_STATUS, &mut status) };
+    if status == gl::FALSE as i32 {
+        let log = source.annotate_log(&get_shader_info_log(gl, shader));
+        unsafe { gl.DeleteShader(shader) };
+        return Err(format!(
+            "Failed to compile {} stage {path}:\n{log}",
+            stage.name()
+        ));
+    }
+
+    Ok(shader)
 }
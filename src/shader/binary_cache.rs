@@ -0,0 +1,193 @@
+use std::{env, fs, path::PathBuf};
+
+use crate::gl::{
+    self,
+    types::{GLenum, GLuint},
+    Gl, GlInfo,
+};
+
+/// Environment variable overriding the directory linked programs are cached in.
+pub const SHADER_CACHE_ENV: &str = "LEARN_OGL_SHADER_CACHE";
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Linked program binaries stored on disk, so unchanged programs skip compilation on the next
+/// start.
+///
+/// Entries are keyed by a hash of the preprocessed sources, which include the defines, and the
+/// driver strings. A driver update therefore never sees binaries from another driver, and a
+/// binary the driver still rejects falls back to a normal compile.
+pub struct ProgramBinaryCache {
+    dir: PathBuf,
+    driver: String,
+    enabled: bool,
+}
+
+impl ProgramBinaryCache {
+    pub fn new(gl: &Gl, dir: impl Into<PathBuf>, info: &GlInfo) -> Self {
+        let mut formats = 0;
+        unsafe { gl.GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats) };
+
+        let dir = dir.into();
+        let enabled = formats > 0 && fs::create_dir_all(&dir).is_ok();
+        if !enabled {
+            eprintln!("Program binary cache disabled");
+        }
+
+        Self {
+            dir,
+            driver: driver_string(info),
+            enabled,
+        }
+    }
+
+    /// `LEARN_OGL_SHADER_CACHE`, else the user cache directory, else the temp directory.
+    pub fn default_dir() -> PathBuf {
+        if let Some(dir) = env::var_os(SHADER_CACHE_ENV) {
+            return PathBuf::from(dir);
+        }
+
+        let cache_home = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(env::temp_dir);
+        cache_home.join("learn_ogl_rs").join("programs")
+    }
+
+    /// Cache key of a program built from the preprocessed `sources` of each stage.
+    pub fn key(&self, sources: &[&str]) -> u64 {
+        program_key(&self.driver, sources)
+    }
+
+    /// Create a program from the cached binary for `key`. Returns `None` on a miss or if the
+    /// driver rejects the binary, in which case the entry is dropped.
+    pub fn load(&self, gl: &Gl, key: u64) -> Option<GLuint> {
+        if !self.enabled {
+            return None;
+        }
+
+        let path = self.entry_path(key);
+        let data = fs::read(&path).ok()?;
+        if data.len() < 4 {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        let (format, binary) = data.split_at(4);
+        let format = GLenum::from_le_bytes(format.try_into().unwrap());
+
+        let program = unsafe { gl.CreateProgram() };
+        let mut status = 0;
+        unsafe {
+            gl.ProgramBinary(program, format, binary.as_ptr().cast(), binary.len() as i32);
+            gl.GetProgramiv(program, gl::LINK_STATUS, &mut status);
+        }
+
+        if status == gl::FALSE as i32 {
+            unsafe { gl.DeleteProgram(program) };
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+
+        Some(program)
+    }
+
+    /// Store the binary of a linked `program` under `key`. The program has to be linked with
+    /// `PROGRAM_BINARY_RETRIEVABLE_HINT` set.
+    pub fn store(&self, gl: &Gl, key: u64, program: GLuint) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut length = 0;
+        unsafe { gl.GetProgramiv(program, gl::PROGRAM_BINARY_LENGTH, &mut length) };
+        if length <= 0 {
+            return;
+        }
+
+        let mut format = 0;
+        let mut binary = vec![0u8; length as usize];
+        let mut written = 0;
+        unsafe {
+            gl.GetProgramBinary(
+                program,
+                length,
+                &mut written,
+                &mut format,
+                binary.as_mut_ptr().cast(),
+            )
+        };
+        binary.truncate(written as usize);
+
+        let mut data = format.to_le_bytes().to_vec();
+        data.extend(binary);
+        if let Err(err) = fs::write(self.entry_path(key), data) {
+            eprintln!("Failed to write program binary cache entry: {err}");
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn entry_path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{key:016x}.bin"))
+    }
+}
+
+fn driver_string(info: &GlInfo) -> String {
+    format!("{}\n{}\n{}", info.vendor, info.renderer, info.version)
+}
+
+fn program_key(driver: &str, sources: &[&str]) -> u64 {
+    // Hash the lengths too, so moving text between stages changes the key.
+    [driver]
+        .iter()
+        .chain(sources)
+        .fold(FNV_OFFSET_BASIS, |hash, part| {
+            let hash = fnv1a(hash, &(part.len() as u64).to_le_bytes());
+            fnv1a(hash, part.as_bytes())
+        })
+}
+
+/// 64 bit FNV-1a. Unlike `DefaultHasher` it is stable across builds, which the on-disk keys
+/// rely on.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_program_key() {
+        let info = GlInfo {
+            vendor: "vendor".to_string(),
+            renderer: "renderer".to_string(),
+            version: "4.6".to_string(),
+            shading_language_version: "4.60".to_string(),
+        };
+        let driver = driver_string(&info);
+        let key = program_key(&driver, &["vert", "frag"]);
+
+        assert_eq!(key, program_key(&driver, &["vert", "frag"]));
+        assert_ne!(key, program_key(&driver, &["ver", "tfrag"]));
+        assert_ne!(key, program_key(&driver, &["vert", "#define FOG\nfrag"]));
+
+        let updated_driver = driver_string(&GlInfo {
+            version: "4.6 (updated)".to_string(),
+            ..info
+        });
+        assert_ne!(key, program_key(&updated_driver, &["vert", "frag"]));
+    }
+}
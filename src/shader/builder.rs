@@ -0,0 +1,218 @@
+use std::rc::Rc;
+
+use crate::gl::{self, types::GLenum, Gl};
+
+use super::{binary_cache::ProgramBinaryCache, Shader, ShaderDefines, StageSource};
+
+/// A programmable stage of the pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ShaderStage {
+    // Declared in pipeline order, which is the order stages are compiled in.
+    Vertex,
+    TessControl,
+    TessEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::TessControl => gl::TESS_CONTROL_SHADER,
+            ShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+            ShaderStage::Compute => gl::COMPUTE_SHADER,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShaderStage::Vertex => "vertex",
+            ShaderStage::TessControl => "tessellation control",
+            ShaderStage::TessEvaluation => "tessellation evaluation",
+            ShaderStage::Geometry => "geometry",
+            ShaderStage::Fragment => "fragment",
+            ShaderStage::Compute => "compute",
+        }
+    }
+}
+
+/// Collects the stages of a program and builds it once the combination has been validated.
+/// Tessellation and geometry stages are added the same way.
+///
+/// ```ignore
+/// let shader = ShaderBuilder::new()
+///     .vertex("src/shader/light_vert.glsl")
+///     .fragment("src/shader/lit_object_frag.glsl")
+///     .defines(ShaderDefines::new().flag("HAS_DIFFUSE_MAP"))
+///     .build(gl)?;
+/// ```
+#[derive(Default)]
+pub struct ShaderBuilder {
+    stages: Vec<StageSource>,
+    defines: ShaderDefines,
+    binary_cache: Option<Rc<ProgramBinaryCache>>,
+}
+
+impl ShaderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stage(mut self, stage: ShaderStage, path: &str) -> Self {
+        self.stages.push(StageSource {
+            stage,
+            path: path.to_string(),
+        });
+        self
+    }
+
+    pub fn vertex(self, path: &str) -> Self {
+        self.stage(ShaderStage::Vertex, path)
+    }
+
+    pub fn tess_control(self, path: &str) -> Self {
+        self.stage(ShaderStage::TessControl, path)
+    }
+
+    pub fn tess_evaluation(self, path: &str) -> Self {
+        self.stage(ShaderStage::TessEvaluation, path)
+    }
+
+    pub fn geometry(self, path: &str) -> Self {
+        self.stage(ShaderStage::Geometry, path)
+    }
+
+    pub fn fragment(self, path: &str) -> Self {
+        self.stage(ShaderStage::Fragment, path)
+    }
+
+    pub fn compute(self, path: &str) -> Self {
+        self.stage(ShaderStage::Compute, path)
+    }
+
+    /// Defines applied to every stage.
+    pub fn defines(mut self, defines: ShaderDefines) -> Self {
+        self.defines = defines;
+        self
+    }
+
+    pub fn binary_cache(mut self, binary_cache: Option<Rc<ProgramBinaryCache>>) -> Self {
+        self.binary_cache = binary_cache;
+        self
+    }
+
+    /// Validate the stage combination, then compile and link the program. Errors name the stage
+    /// and file that failed.
+    pub fn build(mut self, gl: &Gl) -> Result<Shader, String> {
+        validate_stages(&self.stages)?;
+        self.stages.sort_by_key(|source| source.stage);
+        Shader::from_stages(gl, self.stages, self.defines, self.binary_cache)
+    }
+}
+
+/// Check that `stages` form a program GL can link.
+fn validate_stages(stages: &[StageSource]) -> Result<(), String> {
+    if stages.is_empty() {
+        return Err("A program needs at least one stage".to_string());
+    }
+
+    let has = |stage| stages.iter().any(|source| source.stage == stage);
+
+    for (i, source) in stages.iter().enumerate() {
+        if stages[..i].iter().any(|other| other.stage == source.stage) {
+            return Err(format!(
+                "The {} stage is given twice, the second time as {}",
+                source.stage.name(),
+                source.path
+            ));
+        }
+    }
+
+    if has(ShaderStage::Compute) {
+        if stages.len() > 1 {
+            return Err("A compute stage can't be combined with other stages".to_string());
+        }
+        return Ok(());
+    }
+
+    if !has(ShaderStage::Vertex) {
+        return Err("A graphics program needs a vertex stage".to_string());
+    }
+    if has(ShaderStage::TessControl) && !has(ShaderStage::TessEvaluation) {
+        return Err(
+            "A tessellation control stage needs a tessellation evaluation stage".to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stages(stages: &[ShaderStage]) -> Vec<StageSource> {
+        stages
+            .iter()
+            .map(|stage| StageSource {
+                stage: *stage,
+                path: format!("{}.glsl", stage.name()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_stages() {
+        use ShaderStage::*;
+
+        assert!(validate_stages(&stages(&[Vertex, Fragment])).is_ok());
+        assert!(validate_stages(&stages(&[Fragment, Geometry, Vertex])).is_ok());
+        assert!(validate_stages(&stages(&[
+            Vertex,
+            TessControl,
+            TessEvaluation,
+            Geometry,
+            Fragment
+        ]))
+        .is_ok());
+        // The tessellation control stage is optional.
+        assert!(validate_stages(&stages(&[Vertex, TessEvaluation, Fragment])).is_ok());
+        assert!(validate_stages(&stages(&[Compute])).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_stages() {
+        use ShaderStage::*;
+
+        assert_eq!(
+            validate_stages(&[]).err().unwrap(),
+            "A program needs at least one stage"
+        );
+        assert_eq!(
+            validate_stages(&stages(&[Geometry, Fragment]))
+                .err()
+                .unwrap(),
+            "A graphics program needs a vertex stage"
+        );
+        assert_eq!(
+            validate_stages(&stages(&[Vertex, TessControl, Fragment]))
+                .err()
+                .unwrap(),
+            "A tessellation control stage needs a tessellation evaluation stage"
+        );
+        assert_eq!(
+            validate_stages(&stages(&[Compute, Vertex])).err().unwrap(),
+            "A compute stage can't be combined with other stages"
+        );
+        assert_eq!(
+            validate_stages(&stages(&[Vertex, Fragment, Fragment]))
+                .err()
+                .unwrap(),
+            "The fragment stage is given twice, the second time as fragment.glsl"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::gl::Gl;
+
+use super::{binary_cache::ProgramBinaryCache, Shader, ShaderDefines};
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ShaderKey {
+    vertex_path: String,
+    fragment_path: String,
+    defines: ShaderDefines,
+}
+
+/// Compiled program variants keyed by their sources and defines, so every user of the same
+/// variant shares one program.
+#[derive(Default)]
+pub struct ShaderCache {
+    programs: HashMap<ShaderKey, Rc<Shader>>,
+    binary_cache: Option<Rc<ProgramBinaryCache>>,
+}
+
+impl ShaderCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A cache that also persists linked programs to `binary_cache` across runs.
+    pub fn with_binary_cache(binary_cache: ProgramBinaryCache) -> Self {
+        Self {
+            programs: HashMap::new(),
+            binary_cache: Some(Rc::new(binary_cache)),
+        }
+    }
+
+    /// Get the variant of a program for `defines`, compiling it on first use.
+    pub fn get(
+        &mut self,
+        gl: &Gl,
+        vertex_path: &str,
+        fragment_path: &str,
+        defines: &ShaderDefines,
+    ) -> Rc<Shader> {
+        let key = ShaderKey {
+            vertex_path: vertex_path.to_string(),
+            fragment_path: fragment_path.to_string(),
+            defines: defines.clone(),
+        };
+
+        let shader = self.programs.entry(key).or_insert_with(|| {
+            Rc::new(Shader::with_binary_cache(
+                gl,
+                vertex_path,
+                fragment_path,
+                defines.clone(),
+                self.binary_cache.clone(),
+            ))
+        });
+        Rc::clone(shader)
+    }
+
+    /// Reload every cached program whose sources changed. Returns true if any was swapped.
+    pub fn reload_if_changed(&self, gl: &Gl) -> bool {
+        // Every program has to be checked, so no short circuiting.
+        let mut reloaded = false;
+        for shader in self.programs.values() {
+            reloaded |= shader.reload_if_changed(gl);
+        }
+        reloaded
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+}
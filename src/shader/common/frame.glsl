@@ -0,0 +1,12 @@
+#ifndef COMMON_FRAME_GLSL
+#define COMMON_FRAME_GLSL
+
+// Shared by every program, updated once per frame. See `renderer::uniform_buffer`.
+layout(std140, binding = 0) uniform Frame {
+    mat4 view;
+    mat4 projection;
+    vec3 cameraPosition;
+    float time;
+};
+
+#endif
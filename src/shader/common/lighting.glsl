@@ -0,0 +1,38 @@
+#ifndef COMMON_LIGHTING_GLSL
+#define COMMON_LIGHTING_GLSL
+
+#ifndef MAX_LIGHTS
+#define MAX_LIGHTS 8
+#endif
+
+struct Material {
+    sampler2D diffuse;
+    sampler2D specular;
+    // Tangent space normals and heights, see `HAS_NORMAL_MAP` and `HAS_HEIGHT_MAP`.
+    sampler2D normal;
+    sampler2D height;
+    // Multiplied with the maps, or used on their own without them.
+    vec3 diffuseColor;
+    vec3 specularColor;
+    float shininess;
+};
+
+struct Light {
+    vec3 position;
+
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+
+    float constant;
+    float linear;
+    float quadratic;
+};
+
+// Shared by every program, updated once per frame. See `renderer::uniform_buffer`.
+layout(std140, binding = 1) uniform Lights {
+    int lightCount;
+    Light lights[MAX_LIGHTS];
+};
+
+#endif
@@ -0,0 +1,118 @@
+use std::ops::Deref;
+
+use glam::UVec3;
+
+use crate::gl::{self, types::GLbitfield, Gl};
+
+use super::{builder::ShaderBuilder, Shader, ShaderDefines, ShaderTrait};
+
+/// A program made of a single compute stage. Derefs to `Shader` for uniforms, reflection and
+/// hot reloading.
+pub struct ComputeShader {
+    shader: Shader,
+}
+
+impl ComputeShader {
+    /// Panics with the compiler diagnostics if the program fails to build.
+    pub fn new(gl: &Gl, path: &str) -> Self {
+        Self::with_defines(gl, path, ShaderDefines::new())
+    }
+
+    /// Panics with the compiler diagnostics if the program fails to build.
+    pub fn with_defines(gl: &Gl, path: &str, defines: ShaderDefines) -> Self {
+        match ShaderBuilder::new()
+            .compute(path)
+            .defines(defines)
+            .build(gl)
+        {
+            Ok(shader) => Self { shader },
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// The `local_size_x/y/z` the program was declared with.
+    pub fn local_size(&self, gl: &Gl) -> UVec3 {
+        let mut size = [0; 3];
+        unsafe {
+            gl.GetProgramiv(
+                self.get_id(),
+                gl::COMPUTE_WORK_GROUP_SIZE,
+                size.as_mut_ptr(),
+            )
+        };
+        UVec3::from_array(size.map(|size| size as u32))
+    }
+
+    /// Run `groups` work groups. Writes are not visible to later commands until a matching
+    /// barrier, e.g. `storage_barrier`.
+    pub fn dispatch(&self, gl: &Gl, groups: UVec3) {
+        self.enable(gl);
+        unsafe { gl.DispatchCompute(groups.x, groups.y, groups.z) };
+    }
+
+    /// Run at least `invocations` invocations, rounded up to whole work groups. The shader has
+    /// to bounds check against the real count.
+    pub fn dispatch_invocations(&self, gl: &Gl, invocations: UVec3) {
+        self.dispatch(gl, work_groups(invocations, self.local_size(gl)));
+    }
+}
+
+impl Deref for ComputeShader {
+    type Target = Shader;
+
+    fn deref(&self) -> &Shader {
+        &self.shader
+    }
+}
+
+/// Number of work groups of `local_size` needed to cover `invocations`.
+pub fn work_groups(invocations: UVec3, local_size: UVec3) -> UVec3 {
+    UVec3::new(
+        invocations.x.div_ceil(local_size.x.max(1)),
+        invocations.y.div_ceil(local_size.y.max(1)),
+        invocations.z.div_ceil(local_size.z.max(1)),
+    )
+}
+
+/// Order earlier shader writes before the accesses selected by `barriers`, a combination of
+/// `gl::*_BARRIER_BIT`s.
+pub fn memory_barrier(gl: &Gl, barriers: GLbitfield) {
+    unsafe { gl.MemoryBarrier(barriers) };
+}
+
+/// Make storage buffer writes visible to storage buffer reads of later dispatches and draws.
+pub fn storage_barrier(gl: &Gl) {
+    memory_barrier(gl, gl::SHADER_STORAGE_BARRIER_BIT);
+}
+
+/// Make storage buffer writes visible to draws sourcing the buffer as vertex or index data.
+pub fn vertex_barrier(gl: &Gl) {
+    memory_barrier(
+        gl,
+        gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT | gl::ELEMENT_ARRAY_BARRIER_BIT,
+    );
+}
+
+/// Make storage buffer writes visible to draws reading the buffer as indirect commands.
+pub fn command_barrier(gl: &Gl) {
+    memory_barrier(gl, gl::COMMAND_BARRIER_BIT);
+}
+
+#[cfg(test)]
+mod test {
+    use glam::uvec3;
+
+    use super::*;
+
+    #[test]
+    fn test_work_groups() {
+        assert_eq!(
+            work_groups(uvec3(1000, 1, 1), uvec3(64, 1, 1)),
+            uvec3(16, 1, 1)
+        );
+        assert_eq!(
+            work_groups(uvec3(64, 65, 0), uvec3(64, 8, 1)),
+            uvec3(1, 9, 0)
+        );
+    }
+}
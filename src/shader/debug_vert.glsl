@@ -0,0 +1,14 @@
+#version 460 core
+layout(location = 0) in vec3 aPos;
+layout(location = 1) in vec4 aColor;
+
+#include "common/frame.glsl"
+
+out vec4 Color;
+
+void main()
+{
+    // Debug vertices are already in world space.
+    gl_Position = projection * view * vec4(aPos, 1.0);
+    Color = aColor;
+}
@@ -3,34 +3,93 @@ layout(location = 0) in vec3 aPos;
 layout(location = 1) in vec3 aNormal;
 layout(location = 2) in vec2 aTexCoords;
 
-struct Light {
-    vec3 position;
-
-    vec3 ambient;
-    vec3 diffuse;
-    vec3 specular;
-    
-    float constant;
-    float linear;
-    float quadratic;
-};
+#include "common/frame.glsl"
+
+#if defined(INSTANCED) && defined(SKINNED)
+#error Skinned materials are not drawn instanced, see renderer::batch::check_material
+#endif
+
+#ifdef INSTANCED
+// Per instance, see `renderer::batch::InstanceData`.
+layout(location = 3) in mat4 aModel;
+layout(location = 7) in mat3 aNormalMatrix;
+layout(location = 10) in vec3 aDiffuseColor;
+layout(location = 11) in vec3 aSpecularColor;
+layout(location = 12) in float aShininess;
 
-uniform Light light;
+flat out vec3 InstanceDiffuseColor;
+flat out vec3 InstanceSpecularColor;
+flat out float InstanceShininess;
+
+#ifdef LOD_FADE
+layout(location = 14) in float aFade;
+flat out float InstanceFade;
+#endif
+#else
 uniform mat4 model;
-uniform mat4 view;
-uniform mat4 projection;
+
+#ifdef SKINNED
+// See `animation::skin::SkinnedVertex`.
+layout(location = 3) in ivec4 aJoints;
+layout(location = 4) in vec4 aWeights;
+
+// Skinning matrices, see `animation::skin::JointBuffer`.
+layout(std430, binding = 2) readonly buffer Joints {
+    mat4 jointMatrices[];
+};
+#endif
+#endif
+
+#ifdef HAS_TANGENTS
+// xyz along +u, w the handedness of the bitangent, see `mesh::tangents`.
+layout(location = 13) in vec4 aTangent;
+
+out vec3 Tangent;
+out float BitangentSign;
+#endif
 
 out vec3 FragPos;
 out vec3 Normal;
-out vec3 LightPos;
 out vec2 TexCoords;
 
 void main()
 {
-    gl_Position = projection * view * model * vec4(aPos, 1.0);
-    FragPos = vec3(view * model * vec4(aPos, 1.0));
-    Normal = mat3(transpose(inverse(view * model))) * aNormal;
-    LightPos = vec3(view * vec4(light.position, 1.0));
+    vec4 position = vec4(aPos, 1.0);
+    vec3 normal = aNormal;
+#ifdef SKINNED
+    mat4 skin = aWeights.x * jointMatrices[aJoints.x]
+              + aWeights.y * jointMatrices[aJoints.y]
+              + aWeights.z * jointMatrices[aJoints.z]
+              + aWeights.w * jointMatrices[aJoints.w];
+    position = skin * position;
+    // Joints don't scale unevenly, so the normals can move like the positions.
+    normal = mat3(skin) * normal;
+#endif
+
+#ifdef INSTANCED
+    mat4 model = aModel;
+    // The view matrix has no scale, so it can rotate the world space normal matrix as is.
+    Normal = mat3(view) * aNormalMatrix * normal;
+    InstanceDiffuseColor = aDiffuseColor;
+    InstanceSpecularColor = aSpecularColor;
+    InstanceShininess = aShininess;
+#ifdef LOD_FADE
+    InstanceFade = aFade;
+#endif
+#else
+    Normal = mat3(transpose(inverse(view * model))) * normal;
+#endif
+    gl_Position = projection * view * model * position;
+    FragPos = vec3(view * model * position);
     TexCoords = aTexCoords;
+#ifdef HAS_TANGENTS
+    // Tangents follow the surface, so unlike normals they transform by the model matrix.
+    vec3 tangent = aTangent.xyz;
+#ifdef SKINNED
+    tangent = mat3(skin) * tangent;
+#endif
+    Tangent = mat3(view * model) * tangent;
+    BitangentSign = aTangent.w;
+#endif
 
 }
@@ -1,71 +1,156 @@
 #version 460 core
 
-struct Material {
-    sampler2D diffuse;
-    sampler2D specular;
-    float shininess;
-};
-
-struct Light {
-    vec3 position;
-
-    vec3 ambient;
-    vec3 diffuse;
-    vec3 specular;
-
-    float constant;
-    float linear;
-    float quadratic;
-};
+#include "common/frame.glsl"
+#include "common/lighting.glsl"
 
 uniform Material material;
-uniform Light light;
+
+#ifdef INSTANCED
+flat in vec3 InstanceDiffuseColor;
+flat in vec3 InstanceSpecularColor;
+flat in float InstanceShininess;
+#define DIFFUSE_COLOR InstanceDiffuseColor
+#define SPECULAR_COLOR InstanceSpecularColor
+#define SHININESS InstanceShininess
+
+#ifdef LOD_FADE
+flat in float InstanceFade;
+#endif
+#else
+#define DIFFUSE_COLOR material.diffuseColor
+#define SPECULAR_COLOR material.specularColor
+#define SHININESS material.shininess
+#endif
 
 in vec3 FragPos;
 in vec3 Normal;
-in vec3 LightPos;
 in vec2 TexCoords;
 
+#ifdef HAS_TANGENTS
+in vec3 Tangent;
+in float BitangentSign;
+#endif
+
 out vec4 FragColor;
 
-vec3 calculateAmbientLighting(sampler2D diffuse, vec3 lightColor);
-vec3 calculateDiffuseLighting(vec3 normal, vec3 lightDir, vec3 lightColor, sampler2D diffuse);
-vec3 calculateSpecularLighting(vec3 normal, vec3 lightDir, vec3 lightColor, vec3 FragPos, sampler2D specularMap, float shininess);
+// TexCoords, shifted by the parallax of the height map if there is one.
+vec2 texCoords;
+
+vec3 diffuseColor();
+vec2 parallaxTexCoords(vec3 viewDirTangent);
+float orderedDither(vec2 fragCoord);
+vec3 calculateAmbientLighting(vec3 lightColor);
+vec3 calculateDiffuseLighting(vec3 normal, vec3 lightDir, vec3 lightColor);
+vec3 calculateSpecularLighting(vec3 normal, vec3 lightDir, vec3 lightColor, vec3 FragPos, float shininess);
 
 void main()
 {
-    vec3 norm = normalize(Normal);
-    vec3 lightDir = normalize(LightPos - FragPos);
-
-    float distance = length(light.position - FragPos);
-    float attenuation = 1.0 / (light.constant + light.linear * distance + light.quadratic * (pow(distance, 2)));
-
-    vec3 ambientLighting = calculateAmbientLighting(material.diffuse, light.ambient) * attenuation;
-    vec3 diffuseLighting = calculateDiffuseLighting(norm, lightDir, light.diffuse, material.diffuse) * attenuation;
-    vec3 specularLighting = calculateSpecularLighting(norm, lightDir, light.specular, FragPos, material.specular, material.shininess) * attenuation;
+#ifdef LOD_FADE
+    // The two levels of a cross-fade keep complementary pixels of the dither pattern, see
+    // `InstanceData::with_fade`.
+    if (InstanceFade != 0.0) {
+        bool newLevelPixel = orderedDither(gl_FragCoord.xy) < abs(InstanceFade);
+        if (newLevelPixel != (InstanceFade > 0.0)) {
+            discard;
+        }
+    }
+#endif
 
-    vec3 resultLighting = ambientLighting + diffuseLighting + specularLighting;
+    vec3 norm = normalize(Normal);
+    texCoords = TexCoords;
+
+#ifdef HAS_TANGENTS
+    // MikkTSpace expects the bitangent rebuilt per fragment from the unnormalized
+    // interpolated vectors.
+    vec3 bitangent = BitangentSign * cross(Normal, Tangent);
+    mat3 tbn = mat3(Tangent, bitangent, Normal);
+#ifdef HAS_HEIGHT_MAP
+    vec3 viewDir = normalize(-FragPos);
+    texCoords = parallaxTexCoords(normalize(viewDir * tbn));
+#endif
+#ifdef HAS_NORMAL_MAP
+    norm = normalize(tbn * (texture(material.normal, texCoords).rgb * 2.0 - 1.0));
+#endif
+#endif
+
+    vec3 resultLighting = vec3(0.0);
+
+    for (int i = 0; i < min(lightCount, MAX_LIGHTS); i++) {
+        Light light = lights[i];
+        // Lighting is done in view space.
+        vec3 lightPos = vec3(view * vec4(light.position, 1.0));
+        vec3 lightDir = normalize(lightPos - FragPos);
+
+        float distance = length(lightPos - FragPos);
+        float attenuation = 1.0 / (light.constant + light.linear * distance + light.quadratic * (pow(distance, 2)));
+
+        vec3 ambientLighting = calculateAmbientLighting(light.ambient) * attenuation;
+        vec3 diffuseLighting = calculateDiffuseLighting(norm, lightDir, light.diffuse) * attenuation;
+        vec3 specularLighting = calculateSpecularLighting(norm, lightDir, light.specular, FragPos, SHININESS) * attenuation;
+
+        resultLighting += ambientLighting + diffuseLighting + specularLighting;
+    }
+
+#ifdef FOG_DENSITY
+#ifndef FOG_COLOR
+#define FOG_COLOR vec3(0.1)
+#endif
+    // Exponential squared fog over the view space distance.
+    float fogFactor = exp(-pow(FOG_DENSITY * length(FragPos), 2.0));
+    resultLighting = mix(FOG_COLOR, resultLighting, fogFactor);
+#endif
 
     FragColor = vec4(resultLighting, 1.0);
 }
 
-vec3 calculateAmbientLighting(sampler2D diffuse, vec3 lightColor) {
-    vec3 ambientLighting = lightColor * vec3(texture(diffuse, TexCoords));
+vec3 diffuseColor() {
+#ifdef HAS_DIFFUSE_MAP
+    return DIFFUSE_COLOR * vec3(texture(material.diffuse, texCoords));
+#else
+    return DIFFUSE_COLOR;
+#endif
+}
+
+vec3 calculateAmbientLighting(vec3 lightColor) {
+    vec3 ambientLighting = lightColor * diffuseColor();
     return ambientLighting;
 }
 
-vec3 calculateDiffuseLighting(vec3 normal, vec3 lightDir, vec3 lightColor, sampler2D diffuse) {
+vec3 calculateDiffuseLighting(vec3 normal, vec3 lightDir, vec3 lightColor) {
     float diff = max(dot(normal, lightDir), 0.0);
 
-    vec3 diffuseLighting = lightColor * diff * vec3(texture(diffuse, TexCoords));
+    vec3 diffuseLighting = lightColor * diff * diffuseColor();
     return diffuseLighting;
 }
 
-vec3 calculateSpecularLighting(vec3 normal, vec3 lightDir, vec3 lightColor, vec3 FragPos, sampler2D specularMap, float shininess) {
+vec3 calculateSpecularLighting(vec3 normal, vec3 lightDir, vec3 lightColor, vec3 FragPos, float shininess) {
     vec3 viewDir = normalize(-FragPos);
     vec3 reflectDir = reflect(-lightDir, normal);
     float angleBetween = dot(viewDir, reflectDir);
     float spec = pow(max(angleBetween, 0.0), shininess);
-    vec3 specularLighting = lightColor * spec * vec3(texture(specularMap, TexCoords));
+#ifdef HAS_SPECULAR_MAP
+    vec3 specularStrength = SPECULAR_COLOR * vec3(texture(material.specular, texCoords));
+#else
+    vec3 specularStrength = SPECULAR_COLOR;
+#endif
+    vec3 specularLighting = lightColor * spec * specularStrength;
     return specularLighting;
 }
+
+vec2 parallaxTexCoords(vec3 viewDirTangent) {
+#ifdef HAS_HEIGHT_MAP
+    // Parallax offset mapping with offset limiting: the offset isn't divided by the view
+    // angle, so grazing angles don't swim.
+    float depth = 1.0 - texture(material.height, TexCoords).r;
+    return TexCoords - viewDirTangent.xy * depth * HEIGHT_SCALE;
+#else
+    return TexCoords;
+#endif
+}
+
+// A threshold in (0, 1) from a 4x4 Bayer matrix.
+float orderedDither(vec2 fragCoord) {
+    const float bayer[16] = float[16](0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5);
+    ivec2 cell = ivec2(fragCoord) % 4;
+    return (bayer[cell.y * 4 + cell.x] + 0.5) / 16.0;
+}
@@ -0,0 +1,15 @@
+#version 460 core
+layout(location = 0) in vec2 aPos;
+layout(location = 1) in vec4 aColor;
+
+uniform vec2 screenSize;
+
+out vec4 Color;
+
+void main()
+{
+    // Overlay vertices are in pixels with the origin at the top left.
+    vec2 ndc = (aPos / screenSize) * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+    Color = aColor;
+}
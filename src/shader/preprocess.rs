@@ -0,0 +1,292 @@
+use std::path::{Path, PathBuf};
+
+use super::ShaderDefines;
+
+/// Directory `#include` paths are resolved against.
+pub const SHADER_ROOT: &str = "src/shader";
+
+/// GLSL source with all includes expanded.
+pub struct PreprocessedSource {
+    pub code: String,
+    /// Every file that went into `code`. The index of a file is the source string number used
+    /// for it in the emitted `#line` directives.
+    pub files: Vec<PathBuf>,
+}
+
+impl PreprocessedSource {
+    /// Replace the source string numbers at the start of compiler log lines, e.g. `0:12(5)` or
+    /// `0(12)`, with the name of the file they refer to.
+    pub fn annotate_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| {
+                let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+                let rest = &line[digits..];
+                let file = line[..digits]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| self.files.get(index));
+
+                match file {
+                    Some(file) if rest.starts_with(':') || rest.starts_with('(') => {
+                        format!("{}{rest}", file.display())
+                    }
+                    _ => line.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Expand `#include "file"` directives in the shader at `path`, resolving includes relative to
+/// `root`. `read` loads the contents of a file.
+///
+/// The `#version` line of the top level file is kept as the first line of the output, followed
+/// by a `#define` for each of `defines`. `#line` directives are emitted around every include so
+/// compiler diagnostics can be mapped back to the file and line they came from.
+pub fn preprocess(
+    root: &Path,
+    path: &Path,
+    defines: &ShaderDefines,
+    mut read: impl FnMut(&Path) -> Result<String, String>,
+) -> Result<PreprocessedSource, String> {
+    let source = read(path)?;
+
+    let mut preprocessor = Preprocessor {
+        root,
+        read: &mut read,
+        files: vec![path.to_path_buf()],
+        stack: vec![path.to_path_buf()],
+        code: String::new(),
+    };
+
+    let body = match source
+        .lines()
+        .position(|line| line.trim_start().starts_with("#version"))
+    {
+        Some(version_line) => {
+            let version = source.lines().nth(version_line).unwrap();
+            preprocessor.code.push_str(version.trim());
+            preprocessor.code.push('\n');
+            // Blank out the original so line numbers below it stay the same.
+            source
+                .lines()
+                .enumerate()
+                .map(|(i, line)| if i == version_line { "" } else { line })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        None => source,
+    };
+
+    for (name, value) in defines.iter() {
+        let define = match value {
+            "" => format!("#define {name}\n"),
+            value => format!("#define {name} {value}\n"),
+        };
+        preprocessor.code.push_str(&define);
+    }
+    preprocessor.code.push_str("#line 1 0\n");
+    preprocessor.expand(&body, 0)?;
+
+    Ok(PreprocessedSource {
+        code: preprocessor.code,
+        files: preprocessor.files,
+    })
+}
+
+struct Preprocessor<'a, F: FnMut(&Path) -> Result<String, String>> {
+    root: &'a Path,
+    read: &'a mut F,
+    files: Vec<PathBuf>,
+    /// Chain of files currently being expanded, used to detect include cycles.
+    stack: Vec<PathBuf>,
+    code: String,
+}
+
+impl<F: FnMut(&Path) -> Result<String, String>> Preprocessor<'_, F> {
+    fn expand(&mut self, source: &str, file_index: usize) -> Result<(), String> {
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let trimmed = line.trim_start();
+
+            if trimmed.starts_with("#version") && file_index != 0 {
+                // Only the top level file decides the version.
+                self.code.push('\n');
+                continue;
+            }
+
+            let Some(directive) = trimmed.strip_prefix("#include") else {
+                self.code.push_str(line);
+                self.code.push('\n');
+                continue;
+            };
+
+            let location = format!("{}:{line_number}", self.files[file_index].display());
+            let include = parse_include_path(directive)
+                .ok_or_else(|| format!("{location}: malformed #include directive"))?;
+            let include_path = self.root.join(include);
+
+            if let Some(start) = self.stack.iter().position(|path| *path == include_path) {
+                let cycle = self.stack[start..]
+                    .iter()
+                    .chain([&include_path])
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(format!("{location}: include cycle {cycle}"));
+            }
+
+            let include_source = (self.read)(&include_path)
+                .map_err(|err| format!("{location}: failed to read include: {err}"))?;
+
+            let include_index = match self.files.iter().position(|path| *path == include_path) {
+                Some(index) => index,
+                None => {
+                    self.files.push(include_path.clone());
+                    self.files.len() - 1
+                }
+            };
+
+            self.stack.push(include_path);
+            self.code.push_str(&format!("#line 1 {include_index}\n"));
+            self.expand(&include_source, include_index)?;
+            self.code
+                .push_str(&format!("#line {} {file_index}\n", line_number + 1));
+            self.stack.pop();
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_include_path(directive: &str) -> Option<&str> {
+    let directive = directive.trim();
+    let path = directive.strip_prefix('"')?.strip_suffix('"')?;
+    (!path.is_empty()).then_some(path)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn read_from<'a>(
+        files: &'a HashMap<&str, &str>,
+    ) -> impl FnMut(&Path) -> Result<String, String> + 'a {
+        |path| {
+            files
+                .get(path.to_str().unwrap())
+                .map(|source| source.to_string())
+                .ok_or_else(|| format!("{} not found", path.display()))
+        }
+    }
+
+    #[test]
+    fn test_include() {
+        let files = HashMap::from([
+            (
+                "root/main.glsl",
+                "#version 460 core\n#include \"common/a.glsl\"\nvoid main() {}",
+            ),
+            ("root/common/a.glsl", "float a;"),
+        ]);
+
+        let source = preprocess(
+            Path::new("root"),
+            Path::new("root/main.glsl"),
+            &ShaderDefines::new(),
+            read_from(&files),
+        )
+        .unwrap();
+
+        assert_eq!(
+            source.code,
+            "#version 460 core\n#line 1 0\n\n#line 1 1\nfloat a;\n#line 3 0\nvoid main() {}\n"
+        );
+        assert_eq!(
+            source.files,
+            vec![
+                PathBuf::from("root/main.glsl"),
+                PathBuf::from("root/common/a.glsl")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_defines() {
+        let files = HashMap::from([("root/main.glsl", "// header\n#version 460 core\nfloat a;")]);
+        let defines = ShaderDefines::new()
+            .with("MAX_LIGHTS", 4)
+            .flag("HAS_SPECULAR_MAP");
+
+        let source = preprocess(
+            Path::new("root"),
+            Path::new("root/main.glsl"),
+            &defines,
+            read_from(&files),
+        )
+        .unwrap();
+
+        assert_eq!(
+            source.code,
+            "#version 460 core\n#define HAS_SPECULAR_MAP\n#define MAX_LIGHTS 4\n#line 1 0\n// header\n\nfloat a;\n"
+        );
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let files = HashMap::from([
+            ("root/main.glsl", "#include \"a.glsl\""),
+            ("root/a.glsl", "#include \"b.glsl\""),
+            ("root/b.glsl", "#include \"a.glsl\""),
+        ]);
+
+        let err = preprocess(
+            Path::new("root"),
+            Path::new("root/main.glsl"),
+            &ShaderDefines::new(),
+            read_from(&files),
+        )
+        .err()
+        .unwrap();
+
+        assert_eq!(
+            err,
+            "root/b.glsl:1: include cycle root/a.glsl -> root/b.glsl -> root/a.glsl"
+        );
+    }
+
+    #[test]
+    fn test_missing_include() {
+        let files = HashMap::from([("root/main.glsl", "\n#include \"missing.glsl\"")]);
+
+        let err = preprocess(
+            Path::new("root"),
+            Path::new("root/main.glsl"),
+            &ShaderDefines::new(),
+            read_from(&files),
+        )
+        .err()
+        .unwrap();
+
+        assert_eq!(
+            err,
+            "root/main.glsl:2: failed to read include: root/missing.glsl not found"
+        );
+    }
+
+    #[test]
+    fn test_annotate_log() {
+        let source = PreprocessedSource {
+            code: String::new(),
+            files: vec![PathBuf::from("main.glsl"), PathBuf::from("common.glsl")],
+        };
+
+        assert_eq!(
+            source.annotate_log("1:4(2): error: x\n0(7) : error C0000: y\n5:1: z"),
+            "common.glsl:4(2): error: x\nmain.glsl(7) : error C0000: y\n5:1: z"
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+
+use crate::{
+    gl::{
+        self,
+        types::{GLenum, GLint, GLuint},
+        Gl,
+    },
+    helper::add_null_term,
+};
+
+/// An active uniform of a linked program.
+#[derive(Clone, Debug)]
+pub struct UniformInfo {
+    pub name: String,
+    pub gl_type: GLenum,
+    /// Number of array elements from this entry to the end of the array, 1 for non-arrays.
+    pub size: GLint,
+    /// -1 for uniforms that live in a uniform block.
+    pub location: GLint,
+    /// Index into the program's uniform blocks, -1 for default block uniforms.
+    pub block_index: GLint,
+    /// Byte offset inside the uniform block, -1 for default block uniforms.
+    pub offset: GLint,
+}
+
+/// An active uniform or shader storage block of a linked program.
+#[derive(Clone, Debug)]
+pub struct UniformBlockInfo {
+    pub name: String,
+    pub index: GLuint,
+    pub binding: GLint,
+    pub data_size: GLint,
+}
+
+/// An active vertex attribute of a linked program.
+#[derive(Clone, Debug)]
+pub struct AttributeInfo {
+    pub name: String,
+    pub gl_type: GLenum,
+    pub size: GLint,
+    pub location: GLint,
+}
+
+/// Everything the linker reports about a program's interface, queried once after linking.
+#[derive(Default, Debug)]
+pub struct Reflection {
+    uniforms: HashMap<String, UniformInfo>,
+    uniform_blocks: HashMap<String, UniformBlockInfo>,
+    storage_blocks: HashMap<String, UniformBlockInfo>,
+    attributes: HashMap<String, AttributeInfo>,
+}
+
+impl Reflection {
+    pub fn new(gl: &Gl, program: GLuint) -> Self {
+        let mut reflection = Self::default();
+
+        for index in 0..active_resources(gl, program, gl::UNIFORM) {
+            let [gl_type, size, location, block_index, offset] = resource_properties(
+                gl,
+                program,
+                gl::UNIFORM,
+                index,
+                [
+                    gl::TYPE,
+                    gl::ARRAY_SIZE,
+                    gl::LOCATION,
+                    gl::BLOCK_INDEX,
+                    gl::OFFSET,
+                ],
+            );
+            let name = resource_name(gl, program, gl::UNIFORM, index);
+            let info = UniformInfo {
+                name: name.clone(),
+                gl_type: gl_type as GLenum,
+                size,
+                location,
+                block_index,
+                offset,
+            };
+
+            // Arrays are reported once as `name[0]`. Make every element addressable, as well
+            // as the bare array name.
+            match name.strip_suffix("[0]") {
+                Some(base) => {
+                    for element in 1..size {
+                        let element_name = format!("{base}[{element}]");
+                        let location = if location == -1 {
+                            -1
+                        } else {
+                            unsafe {
+                                gl.GetUniformLocation(
+                                    program,
+                                    add_null_term(element_name.as_bytes()).as_ptr().cast(),
+                                )
+                            }
+                        };
+                        reflection.uniforms.insert(
+                            element_name.clone(),
+                            UniformInfo {
+                                name: element_name,
+                                size: size - element,
+                                location,
+                                ..info.clone()
+                            },
+                        );
+                    }
+                    reflection.uniforms.insert(base.to_string(), info.clone());
+                    reflection.uniforms.insert(name, info);
+                }
+                None => {
+                    reflection.uniforms.insert(name, info);
+                }
+            }
+        }
+
+        reflection.uniform_blocks = blocks(gl, program, gl::UNIFORM_BLOCK);
+        reflection.storage_blocks = blocks(gl, program, gl::SHADER_STORAGE_BLOCK);
+
+        for index in 0..active_resources(gl, program, gl::PROGRAM_INPUT) {
+            let [gl_type, size, location] = resource_properties(
+                gl,
+                program,
+                gl::PROGRAM_INPUT,
+                index,
+                [gl::TYPE, gl::ARRAY_SIZE, gl::LOCATION],
+            );
+            let name = resource_name(gl, program, gl::PROGRAM_INPUT, index);
+            reflection.attributes.insert(
+                name.clone(),
+                AttributeInfo {
+                    name,
+                    gl_type: gl_type as GLenum,
+                    size,
+                    location,
+                },
+            );
+        }
+
+        reflection
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms.get(name)
+    }
+
+    pub fn uniforms(&self) -> impl Iterator<Item = &UniformInfo> {
+        self.uniforms.values()
+    }
+
+    pub fn uniform_block(&self, name: &str) -> Option<&UniformBlockInfo> {
+        self.uniform_blocks.get(name)
+    }
+
+    pub fn uniform_blocks(&self) -> impl Iterator<Item = &UniformBlockInfo> {
+        self.uniform_blocks.values()
+    }
+
+    pub fn storage_block(&self, name: &str) -> Option<&UniformBlockInfo> {
+        self.storage_blocks.get(name)
+    }
+
+    pub fn storage_blocks(&self) -> impl Iterator<Item = &UniformBlockInfo> {
+        self.storage_blocks.values()
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttributeInfo> {
+        self.attributes.get(name)
+    }
+
+    pub fn attributes(&self) -> impl Iterator<Item = &AttributeInfo> {
+        self.attributes.values()
+    }
+}
+
+fn blocks(gl: &Gl, program: GLuint, interface: GLenum) -> HashMap<String, UniformBlockInfo> {
+    (0..active_resources(gl, program, interface))
+        .map(|index| {
+            let [binding, data_size] = resource_properties(
+                gl,
+                program,
+                interface,
+                index,
+                [gl::BUFFER_BINDING, gl::BUFFER_DATA_SIZE],
+            );
+            let name = resource_name(gl, program, interface, index);
+            let info = UniformBlockInfo {
+                name: name.clone(),
+                index,
+                binding,
+                data_size,
+            };
+            (name, info)
+        })
+        .collect()
+}
+
+fn active_resources(gl: &Gl, program: GLuint, interface: GLenum) -> GLuint {
+    let mut count = 0;
+    unsafe { gl.GetProgramInterfaceiv(program, interface, gl::ACTIVE_RESOURCES, &mut count) };
+    count as GLuint
+}
+
+fn resource_properties<const N: usize>(
+    gl: &Gl,
+    program: GLuint,
+    interface: GLenum,
+    index: GLuint,
+    props: [GLenum; N],
+) -> [GLint; N] {
+    let mut values = [0; N];
+    unsafe {
+        gl.GetProgramResourceiv(
+            program,
+            interface,
+            index,
+            N as i32,
+            props.as_ptr(),
+            N as i32,
+            std::ptr::null_mut(),
+            values.as_mut_ptr(),
+        )
+    };
+    values
+}
+
+fn resource_name(gl: &Gl, program: GLuint, interface: GLenum, index: GLuint) -> String {
+    let [length] = resource_properties(gl, program, interface, index, [gl::NAME_LENGTH]);
+    let mut name = vec![0u8; length.max(1) as usize];
+    let mut written = 0;
+    unsafe {
+        gl.GetProgramResourceName(
+            program,
+            interface,
+            index,
+            length,
+            &mut written,
+            name.as_mut_ptr().cast(),
+        )
+    };
+    name.truncate(written as usize);
+    String::from_utf8_lossy(&name).to_string()
+}
+
+/// Name of a GLSL type as it appears in shader source, for diagnostics.
+pub fn glsl_type_name(gl_type: GLenum) -> &'static str {
+    match gl_type {
+        gl::BOOL => "bool",
+        gl::INT => "int",
+        gl::UNSIGNED_INT => "uint",
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_CUBE_SHADOW => "samplerCubeShadow",
+        gl::SAMPLER_2D_MULTISAMPLE => "sampler2DMS",
+        gl::SAMPLER_BUFFER => "samplerBuffer",
+        gl::INT_SAMPLER_2D => "isampler2D",
+        gl::UNSIGNED_INT_SAMPLER_2D => "usampler2D",
+        _ => "unknown",
+    }
+}
+
+/// Whether `gl_type` is an opaque sampler type, which is set through an integer uniform.
+pub fn is_sampler(gl_type: GLenum) -> bool {
+    matches!(
+        gl_type,
+        gl::SAMPLER_1D
+            | gl::SAMPLER_2D
+            | gl::SAMPLER_3D
+            | gl::SAMPLER_CUBE
+            | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_2D_SHADOW
+            | gl::SAMPLER_CUBE_SHADOW
+            | gl::SAMPLER_2D_MULTISAMPLE
+            | gl::SAMPLER_BUFFER
+            | gl::INT_SAMPLER_2D
+            | gl::UNSIGNED_INT_SAMPLER_2D
+    )
+}
+
+/// Number of float components of a float attribute type, `None` for other types.
+pub fn float_components(gl_type: GLenum) -> Option<u32> {
+    match gl_type {
+        gl::FLOAT => Some(1),
+        gl::FLOAT_VEC2 => Some(2),
+        gl::FLOAT_VEC3 => Some(3),
+        gl::FLOAT_VEC4 => Some(4),
+        _ => None,
+    }
+}
+
+/// Components per column and columns of a float matrix attribute type, `None` for other types.
+pub fn float_matrix_columns(gl_type: GLenum) -> Option<(u32, u32)> {
+    match gl_type {
+        gl::FLOAT_MAT2 => Some((2, 2)),
+        gl::FLOAT_MAT3 => Some((3, 3)),
+        gl::FLOAT_MAT4 => Some((4, 4)),
+        _ => None,
+    }
+}
+
+/// Number of components of a signed integer attribute type, `None` for other types.
+pub fn int_components(gl_type: GLenum) -> Option<u32> {
+    match gl_type {
+        gl::INT => Some(1),
+        gl::INT_VEC2 => Some(2),
+        gl::INT_VEC3 => Some(3),
+        gl::INT_VEC4 => Some(4),
+        _ => None,
+    }
+}
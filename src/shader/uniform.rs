@@ -0,0 +1,184 @@
+use glam::{IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4};
+
+use crate::gl::{
+    self,
+    types::{GLenum, GLint, GLsizei},
+    Gl,
+};
+
+use super::ShaderTrait;
+
+/// A Rust type with a GLSL counterpart that can be uploaded to a default block uniform.
+pub trait UniformValue: Sized {
+    /// GLSL types a value can be assigned to. The first one is the matching type, used in
+    /// error messages.
+    const GL_TYPES: &'static [GLenum];
+
+    /// Upload `values` to the consecutive array elements starting at `location`.
+    ///
+    /// # Safety
+    /// `location` has to belong to the current program and have one of `GL_TYPES`.
+    unsafe fn upload(gl: &Gl, location: GLint, values: &[Self]);
+}
+
+/// Anything `ShaderTrait::set_uniform` accepts: a single value, an array or a slice of them.
+pub trait Uniform {
+    type Element: UniformValue;
+
+    fn elements(&self) -> &[Self::Element];
+}
+
+impl<T: UniformValue> Uniform for T {
+    type Element = T;
+
+    fn elements(&self) -> &[T] {
+        std::slice::from_ref(self)
+    }
+}
+
+impl<T: UniformValue, const N: usize> Uniform for [T; N] {
+    type Element = T;
+
+    fn elements(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T: UniformValue> Uniform for [T] {
+    type Element = T;
+
+    fn elements(&self) -> &[T] {
+        self
+    }
+}
+
+/// A Rust struct mirroring a GLSL struct uniform. Implement it with `uniform_struct!`.
+pub trait UniformStruct {
+    /// Set every field as `name.field`.
+    fn set_uniforms(&self, gl: &Gl, shader: &impl ShaderTrait, name: &str) -> Result<(), String>;
+}
+
+/// Implement `UniformStruct` for a struct, uploading the listed fields under their own names or
+/// the GLSL name given with `as`. Fields that have no GLSL counterpart are left out of the list.
+///
+/// ```ignore
+/// uniform_struct!(Material { shininess, diffuse as "diffuseColor" });
+/// ```
+macro_rules! uniform_struct {
+    (@name $field:ident) => {
+        stringify!($field)
+    };
+    (@name $field:ident $glsl_name:literal) => {
+        $glsl_name
+    };
+    ($ty:ty { $($field:ident $(as $glsl_name:literal)?),+ $(,)? }) => {
+        impl $crate::shader::uniform::UniformStruct for $ty {
+            fn set_uniforms(
+                &self,
+                gl: &$crate::gl::Gl,
+                shader: &impl $crate::shader::ShaderTrait,
+                name: &str,
+            ) -> Result<(), String> {
+                $(
+                    shader.set_uniform(
+                        gl,
+                        &format!("{name}.{}", uniform_struct!(@name $field $($glsl_name)?)),
+                        &self.$field,
+                    )?;
+                )+
+                Ok(())
+            }
+        }
+    };
+}
+pub(crate) use uniform_struct;
+
+/// Implement `UniformValue` for types whose slices can be passed to a `glUniform*v` call as is.
+macro_rules! packed_uniform_value {
+    ($($ty:ty => $component:ty, [$($gl_type:path),+], $upload:ident;)+) => {
+        $(
+            impl UniformValue for $ty {
+                const GL_TYPES: &'static [GLenum] = &[$($gl_type),+];
+
+                unsafe fn upload(gl: &Gl, location: GLint, values: &[Self]) {
+                    gl.$upload(location, values.len() as GLsizei, values.as_ptr().cast::<$component>());
+                }
+            }
+        )+
+    };
+}
+
+packed_uniform_value! {
+    i32 => i32, [gl::INT, gl::BOOL], Uniform1iv;
+    u32 => u32, [gl::UNSIGNED_INT, gl::BOOL], Uniform1uiv;
+    f32 => f32, [gl::FLOAT], Uniform1fv;
+    Vec2 => f32, [gl::FLOAT_VEC2], Uniform2fv;
+    Vec3 => f32, [gl::FLOAT_VEC3], Uniform3fv;
+    Vec4 => f32, [gl::FLOAT_VEC4], Uniform4fv;
+    IVec2 => i32, [gl::INT_VEC2, gl::BOOL_VEC2], Uniform2iv;
+    IVec3 => i32, [gl::INT_VEC3, gl::BOOL_VEC3], Uniform3iv;
+    IVec4 => i32, [gl::INT_VEC4, gl::BOOL_VEC4], Uniform4iv;
+    UVec2 => u32, [gl::UNSIGNED_INT_VEC2, gl::BOOL_VEC2], Uniform2uiv;
+    UVec3 => u32, [gl::UNSIGNED_INT_VEC3, gl::BOOL_VEC3], Uniform3uiv;
+    UVec4 => u32, [gl::UNSIGNED_INT_VEC4, gl::BOOL_VEC4], Uniform4uiv;
+}
+
+/// Implement `UniformValue` for column major matrices.
+macro_rules! matrix_uniform_value {
+    ($($ty:ty => $gl_type:path, $upload:ident;)+) => {
+        $(
+            impl UniformValue for $ty {
+                const GL_TYPES: &'static [GLenum] = &[$gl_type];
+
+                unsafe fn upload(gl: &Gl, location: GLint, values: &[Self]) {
+                    gl.$upload(
+                        location,
+                        values.len() as GLsizei,
+                        gl::FALSE,
+                        values.as_ptr().cast::<f32>(),
+                    );
+                }
+            }
+        )+
+    };
+}
+
+matrix_uniform_value! {
+    Mat2 => gl::FLOAT_MAT2, UniformMatrix2fv;
+    Mat3 => gl::FLOAT_MAT3, UniformMatrix3fv;
+    Mat4 => gl::FLOAT_MAT4, UniformMatrix4fv;
+}
+
+impl UniformValue for bool {
+    const GL_TYPES: &'static [GLenum] = &[gl::BOOL];
+
+    unsafe fn upload(gl: &Gl, location: GLint, values: &[Self]) {
+        let values = values.iter().map(|val| *val as i32).collect::<Vec<_>>();
+        i32::upload(gl, location, &values);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::mem::size_of;
+
+    use super::*;
+
+    #[test]
+    fn test_glam_types_are_packed() {
+        // The uploads hand slices of these straight to GL.
+        assert_eq!(size_of::<[Vec3; 2]>(), 24);
+        assert_eq!(size_of::<[Vec4; 2]>(), 32);
+        assert_eq!(size_of::<[IVec3; 2]>(), 24);
+        assert_eq!(size_of::<[Mat2; 2]>(), 32);
+        assert_eq!(size_of::<[Mat3; 2]>(), 72);
+        assert_eq!(size_of::<[Mat4; 2]>(), 128);
+    }
+
+    #[test]
+    fn test_elements() {
+        assert_eq!(1.0f32.elements(), &[1.0]);
+        assert_eq!([1, 2, 3].elements().len(), 3);
+        assert_eq!(Vec::<Vec3>::new()[..].elements().len(), 0);
+    }
+}
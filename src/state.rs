@@ -19,6 +19,12 @@ use winit::{
 
 use crate::{gl::create_gl_context, renderer::Renderer, timer::Timer};
 
+const OVERLAY_TOGGLE_KEY: KeyCode = KeyCode::F3;
+const FOG_TOGGLE_KEY: KeyCode = KeyCode::F4;
+const PARALLAX_TOGGLE_KEY: KeyCode = KeyCode::F5;
+const DEBUG_DRAW_TOGGLE_KEY: KeyCode = KeyCode::F6;
+const ANIMATION_SWITCH_KEY: KeyCode = KeyCode::F7;
+
 pub struct App {
     window: Option<Window>,
     surface: Option<Surface<WindowSurface>>,
@@ -34,7 +40,7 @@ impl App {
     pub fn new(template: ConfigTemplateBuilder, display_builder: DisplayBuilder) -> Self {
         App {
             template,
-            gl_display: GlDisplayCreationState::Builder(display_builder),
+            gl_display: GlDisplayCreationState::Builder(Box::new(display_builder)),
             window: None,
             surface: None,
             gl_context: None,
@@ -60,7 +66,7 @@ impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         let (window, gl_config) = match &self.gl_display {
             GlDisplayCreationState::Builder(display_builder) => {
-                let (window, gl_config) = match display_builder.clone().build(
+                let (window, gl_config) = match (**display_builder).clone().build(
                     event_loop,
                     self.template.clone(),
                     config_picker,
@@ -171,6 +177,18 @@ impl ApplicationHandler for App {
                 is_synthetic: _,
             } => match event.state {
                 ElementState::Pressed => {
+                    if let (Some(renderer), false) = (self.renderer.as_mut(), event.repeat) {
+                        match event.physical_key {
+                            PhysicalKey::Code(OVERLAY_TOGGLE_KEY) => renderer.toggle_overlay(),
+                            PhysicalKey::Code(FOG_TOGGLE_KEY) => renderer.toggle_fog(),
+                            PhysicalKey::Code(PARALLAX_TOGGLE_KEY) => renderer.toggle_parallax(),
+                            PhysicalKey::Code(DEBUG_DRAW_TOGGLE_KEY) => {
+                                renderer.toggle_debug_draw()
+                            }
+                            PhysicalKey::Code(ANIMATION_SWITCH_KEY) => renderer.next_animation(),
+                            _ => (),
+                        }
+                    }
                     self.keys_down.insert(event.physical_key);
                 }
                 ElementState::Released => {
@@ -234,7 +252,7 @@ fn group_keys(keys_down: &HashSet<PhysicalKey>) -> GroupedKeys {
 
 enum GlDisplayCreationState {
     /// The display was not built yet.
-    Builder(DisplayBuilder),
+    Builder(Box<DisplayBuilder>),
     /// The display was already created for the application.
     Init,
 }
@@ -1,9 +1,13 @@
-use std::time::Instant;
+use std::{collections::VecDeque, time::Instant};
+
+/// Number of frames kept for the frame time history.
+pub const FRAME_HISTORY_LEN: usize = 120;
 
 pub struct Timer {
     last_instant: Instant,
     last_delta: f32,
     elapsed: f32,
+    frame_times: VecDeque<f32>,
 }
 
 impl Timer {
@@ -16,12 +20,31 @@ impl Timer {
         self.last_delta = self.last_instant.elapsed().as_secs_f32();
         self.elapsed += self.last_delta;
         self.last_instant = Instant::now();
+
+        if self.frame_times.len() == FRAME_HISTORY_LEN {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(self.last_delta);
     }
 
     pub fn elapsed(&self) -> f32 {
         self.elapsed
     }
 
+    /// Frame times in seconds for the last `FRAME_HISTORY_LEN` frames, oldest first.
+    pub fn frame_times(&self) -> &VecDeque<f32> {
+        &self.frame_times
+    }
+
+    /// Frames per second averaged over the frame time history.
+    pub fn fps(&self) -> f32 {
+        let total: f32 = self.frame_times.iter().sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        self.frame_times.len() as f32 / total
+    }
+
     pub fn new() -> Self {
         Self::default()
     }
@@ -33,6 +56,7 @@ impl Default for Timer {
             last_instant: Instant::now(),
             last_delta: 0.0,
             elapsed: 0.0,
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY_LEN),
         }
     }
 }